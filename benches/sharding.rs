@@ -0,0 +1,72 @@
+//! Throughput of `ShardedRouterSet` with 255 virtual routers (VRIDs 1 to 255) as the number of shards grows.
+//!
+//! Run with `cargo bench --bench sharding`.
+
+use pnet_base::MacAddr;
+use std::net::Ipv4Addr;
+use std::time::Instant;
+use vrrp_rs::{
+    ActionHandler, BackupMode, Event, InterfaceId, Interval, Level, LogEvent, Parameters,
+    ReceivedPacket, RoutePacket, SendPacket, ShardedRouterSet, VirtualAddresses, VRID,
+};
+
+const ROUTERS: u8 = 255;
+const ADVERTISEMENTS: usize = 2_000;
+
+struct NullHandler;
+
+impl ActionHandler for NullHandler {
+    fn join_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+
+    fn leave_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+
+    fn activate(&mut self, _: InterfaceId, _: VirtualAddresses) {}
+
+    fn deactivate(&mut self, _: InterfaceId) {}
+
+    fn send(&mut self, _: InterfaceId, _: SendPacket) {}
+
+    fn route(&mut self, _: RoutePacket) {}
+
+    fn notify(&mut self, _: Event) {}
+
+    fn log(&mut self, _: Level, _: LogEvent) {}
+}
+
+fn main() {
+    let config: Vec<_> = (1..=ROUTERS)
+        .map(|vrid| {
+            Parameters::new(
+                VRID::try_from(vrid).unwrap(),
+                vec![Ipv4Addr::new(10, 0, vrid, 1)].try_into().unwrap(),
+                BackupMode::with_primary_ip(Ipv4Addr::new(10, 0, vrid, 2)),
+            )
+        })
+        .collect();
+
+    for shards in [1, 2, 4, 8] {
+        let set = ShardedRouterSet::new(shards, |_| NullHandler);
+        set.apply_config(config.clone()).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..ADVERTISEMENTS {
+            for parameters in &config {
+                let advertisement = ReceivedPacket::advertisement(
+                    MacAddr(0, 0, 0x5e, 0, 1, parameters.vrid.into()),
+                    Ipv4Addr::new(10, 0, 0, 3),
+                    200,
+                    Interval::from_secs(1),
+                );
+                set.dispatch(parameters.interface, parameters.vrid, advertisement.into());
+            }
+        }
+        set.shutdown();
+
+        let elapsed = start.elapsed();
+        let inputs = ADVERTISEMENTS * ROUTERS as usize;
+        println!(
+            "{shards} shard(s): {inputs} advertisements in {elapsed:?}, {:.0}/s",
+            inputs as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
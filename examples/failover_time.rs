@@ -0,0 +1,43 @@
+//! Measures the failover time of a pair of virtual routers on the simulated network.
+//!
+//! cargo run --example failover_time --features scenario -- [interval centis] [runs]
+
+use std::env;
+use std::net::Ipv4Addr;
+use vrrp_rs::failover::{self, FailoverTimes, Loss};
+use vrrp_rs::{BackupMode, Interval, Parameters, Priority, VRID};
+
+fn main() {
+    let mut args = env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("a number"));
+    let centis = args.next().unwrap_or(100);
+    let runs = args.next().unwrap_or(100) as usize;
+
+    let router = |primary_ip, priority| Parameters {
+        advertisement_interval: Interval::from_centis(centis),
+        ..Parameters::new(
+            VRID::try_from(1).unwrap(),
+            vec![Ipv4Addr::new(192, 168, 0, 1)].try_into().unwrap(),
+            BackupMode::with_primary_ip(primary_ip)
+                .with_priority(Priority::try_from(priority).unwrap()),
+        )
+    };
+    let active = router(Ipv4Addr::new(192, 168, 0, 2), 200);
+    let backups = [router(Ipv4Addr::new(192, 168, 0, 3), 100)];
+
+    for loss in [Loss::Silent, Loss::Shutdown] {
+        report(loss, &failover::measure(&active, &backups, loss, runs));
+    }
+}
+
+fn report(loss: Loss, times: &FailoverTimes) {
+    println!(
+        "{loss:?}: min {:?}, mean {:?}, p99 {:?}, max {:?}, {} failed",
+        times.min().unwrap_or_default(),
+        times.mean().unwrap_or_default(),
+        times.percentile(99.0).unwrap_or_default(),
+        times.max().unwrap_or_default(),
+        times.failed,
+    );
+}
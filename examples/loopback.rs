@@ -0,0 +1,93 @@
+//! Runs two virtual routers over a socketpair and walks them through an election,
+//! a failover when the Active router shuts down, and a preemption when it comes back.
+
+#[cfg(unix)]
+fn main() -> std::io::Result<()> {
+    use std::net::Ipv4Addr;
+    use std::thread;
+    use std::time::Duration;
+    use vrrp_rs::testio::LoopbackTransport;
+    use vrrp_rs::{
+        BackupMode, Command, Input, Interval, Parameters, Priority, Router, State, Timestamp, VRID,
+    };
+
+    struct Node {
+        name: &'static str,
+        router: Router,
+        transport: LoopbackTransport,
+    }
+
+    impl Node {
+        fn new(name: &'static str, priority: u8, transport: LoopbackTransport) -> Self {
+            let primary_ip = Ipv4Addr::new(192, 168, 0, priority);
+            let mode = BackupMode::with_primary_ip(primary_ip)
+                .with_priority(Priority::try_from(priority).unwrap());
+            let parameters = Parameters {
+                advertisement_interval: Interval::from_centis(10),
+                ..Parameters::new(
+                    VRID::try_from(1).unwrap(),
+                    vec![Ipv4Addr::new(192, 168, 0, 1)].try_into().unwrap(),
+                    mode,
+                )
+            };
+            Self {
+                name,
+                router: Router::new(parameters),
+                transport,
+            }
+        }
+
+        fn handle(&mut self, now: Timestamp, input: Input) {
+            let was_active = self.transport.is_active();
+            self.router.dispatch(now, input, &mut self.transport);
+            if was_active != self.transport.is_active() {
+                println!("{}: {}", self.name, self.router.state().name());
+            }
+        }
+
+        fn poll(&mut self, now: Timestamp) -> std::io::Result<()> {
+            if self.router.state() != &State::Initialized && self.router.next_timer(now) <= now {
+                self.handle(now, Input::Timer);
+            }
+            while let Some(packet) = self.transport.receive()? {
+                self.handle(now, packet.into());
+            }
+            Ok(())
+        }
+    }
+
+    fn run(nodes: &mut [&mut Node], duration: Duration) -> std::io::Result<()> {
+        let end = Timestamp::now() + duration;
+        while Timestamp::now() < end {
+            for node in nodes.iter_mut() {
+                node.poll(Timestamp::now())?;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    let (left, right) = LoopbackTransport::pair()?;
+    let mut primary = Node::new("primary", 200, left);
+    let mut secondary = Node::new("secondary", 100, right);
+
+    println!("-- election");
+    primary.handle(Timestamp::now(), Command::Startup.into());
+    secondary.handle(Timestamp::now(), Command::Startup.into());
+    run(&mut [&mut primary, &mut secondary], Duration::from_secs(1))?;
+
+    println!("-- failover");
+    primary.handle(Timestamp::now(), Command::Shutdown.into());
+    run(&mut [&mut primary, &mut secondary], Duration::from_secs(1))?;
+
+    println!("-- preemption");
+    primary.handle(Timestamp::now(), Command::Startup.into());
+    run(&mut [&mut primary, &mut secondary], Duration::from_secs(1))?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("the loopback example needs unix datagram sockets");
+}
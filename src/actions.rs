@@ -1,12 +1,45 @@
 use crate::send::SendPacket;
-use crate::Parameters;
+use crate::{AddressFamily, Event, InterfaceId, Level, LogEvent, Parameters, VirtualAddresses};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum Action<'a> {
-    Activate,
-    Deactivate,
-    Send(SendPacket<'a>),
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action<A: AddressFamily = Ipv4Addr> {
+    /// Join the multicast group advertisements are received on.
+    JoinMulticast {
+        interface: InterfaceId,
+        group: A,
+    },
+    LeaveMulticast {
+        interface: InterfaceId,
+        group: A,
+    },
+    /// Assign the virtual addresses, with their prefix lengths, to the interface.
+    Activate {
+        interface: InterfaceId,
+        addresses: VirtualAddresses<A>,
+    },
+    Deactivate {
+        interface: InterfaceId,
+    },
+    /// The Backup is likely to become Active within one skew time, see
+    /// `Parameters::prepare_activate`.
+    PrepareActivate {
+        interface: InterfaceId,
+        addresses: VirtualAddresses<A>,
+    },
+    /// An advertisement arrived in time after `PrepareActivate`, or the router shut down.
+    CancelPrepare {
+        interface: InterfaceId,
+    },
+    Send {
+        interface: InterfaceId,
+        packet: SendPacket<A>,
+    },
     Route(RoutePacket),
+    Notify(Event<A>),
+    Log(Level, LogEvent<A>),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -16,59 +49,167 @@ pub enum RoutePacket {
     Forward,
 }
 
-impl From<RoutePacket> for Action<'_> {
+impl<A: AddressFamily> fmt::Display for Action<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::JoinMulticast { interface, group } => {
+                write!(f, "join {group} on interface {}", interface.0)
+            }
+            Action::LeaveMulticast { interface, group } => {
+                write!(f, "leave {group} on interface {}", interface.0)
+            }
+            Action::Activate {
+                interface,
+                addresses,
+            } => {
+                write!(f, "activate interface {}", interface.0)?;
+                for address in addresses {
+                    write!(f, " {}/{}", address.address, address.prefix_len)?;
+                }
+                Ok(())
+            }
+            Action::Deactivate { interface } => write!(f, "deactivate interface {}", interface.0),
+            Action::PrepareActivate { interface, .. } => {
+                write!(f, "prepare to activate interface {}", interface.0)
+            }
+            Action::CancelPrepare { interface } => {
+                write!(f, "cancel preparing interface {}", interface.0)
+            }
+            Action::Send { interface, packet } => {
+                write!(f, "send {packet} on interface {}", interface.0)
+            }
+            Action::Route(route) => write!(f, "route {route:?}"),
+            Action::Notify(event) => write!(f, "{:?}: {event:?}", event.severity()),
+            Action::Log(level, event) => write!(f, "{level:?}: {event:?}"),
+        }
+    }
+}
+
+impl<A: AddressFamily> From<RoutePacket> for Action<A> {
     fn from(value: RoutePacket) -> Self {
         Self::Route(value)
     }
 }
 
-impl<'a> From<SendPacket<'a>> for Action<'a> {
-    fn from(value: SendPacket<'a>) -> Self {
-        Self::Send(value)
+impl<A: AddressFamily> Action<A> {
+    pub(crate) fn send(parameters: &Parameters<A>, packet: SendPacket<A>) -> Self {
+        Self::Send {
+            interface: parameters.interface,
+            packet,
+        }
     }
 }
 
+// Room for the most steps one input chains with `Actions::then`, six when an owner steps
+// down: deactivate, the flap event, log, the conflict events and the state change. The
+// gratuitous ARPs for up to `wire::MAX_ADDRESSES` virtual addresses are a single step,
+// produced lazily. Steps past this spill into `Actions::overflow` rather than panic.
+const MAX_STEPS: usize = 8;
+
+/// The actions of one input, chained without allocating.
 #[derive(Debug, PartialEq)]
-pub enum Actions<'a> {
-    TransitionToActive(&'a Parameters, TransitionToActive),
-    ShutdownActive(&'a Parameters, ShutdownActive),
-    OneAction(Option<Action<'a>>),
-    None,
+pub struct Actions<A: AddressFamily = Ipv4Addr> {
+    steps: [Step<A>; MAX_STEPS],
+    overflow: Vec<Step<A>>,
+    len: usize,
+    next: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Step<A: AddressFamily> {
+    TransitionToActive(Arc<Parameters<A>>, TransitionToActive),
+    ShutdownActive(Arc<Parameters<A>>, ShutdownActive),
+    OneAction(Option<Action<A>>),
+}
+
+impl<A: AddressFamily> Actions<A> {
+    pub(crate) const NONE: Self = Self {
+        steps: [const { Step::OneAction(None) }; MAX_STEPS],
+        overflow: Vec::new(),
+        len: 0,
+        next: 0,
+    };
+
+    pub(crate) fn transition_to_active(
+        parameters: Arc<Parameters<A>>,
+        transition: TransitionToActive,
+    ) -> Self {
+        Self::from(Step::TransitionToActive(parameters, transition))
+    }
+
+    pub(crate) fn shutdown_active(
+        parameters: Arc<Parameters<A>>,
+        shutdown: ShutdownActive,
+    ) -> Self {
+        Self::from(Step::ShutdownActive(parameters, shutdown))
+    }
+
+    pub(crate) fn then(mut self, next: impl Into<Actions<A>>) -> Self {
+        let mut next = next.into();
+        for index in next.next..next.len {
+            let step = std::mem::replace(next.step(index), Step::OneAction(None));
+            if self.len < MAX_STEPS {
+                self.steps[self.len] = step;
+            } else {
+                debug_assert!(false, "more than {MAX_STEPS} steps chained");
+                self.overflow.push(step);
+            }
+            self.len += 1;
+        }
+        self
+    }
+
+    fn step(&mut self, index: usize) -> &mut Step<A> {
+        match index.checked_sub(MAX_STEPS) {
+            None => &mut self.steps[index],
+            Some(index) => &mut self.overflow[index],
+        }
+    }
 }
 
-impl<'a> From<Action<'a>> for Actions<'a> {
-    fn from(value: Action<'a>) -> Self {
-        Actions::OneAction(Some(value))
+impl<A: AddressFamily> From<Step<A>> for Actions<A> {
+    fn from(step: Step<A>) -> Self {
+        let mut actions = Actions::NONE;
+        actions.steps[0] = step;
+        actions.len = 1;
+        actions
     }
 }
 
-impl From<RoutePacket> for Actions<'_> {
-    fn from(value: RoutePacket) -> Self {
-        Action::Route(value).into()
+impl<A: AddressFamily> From<Action<A>> for Actions<A> {
+    fn from(value: Action<A>) -> Self {
+        Step::OneAction(Some(value)).into()
     }
 }
 
-impl<'a> From<SendPacket<'a>> for Actions<'a> {
-    fn from(value: SendPacket<'a>) -> Self {
-        Action::Send(value).into()
+impl<A: AddressFamily> From<RoutePacket> for Actions<A> {
+    fn from(value: RoutePacket) -> Self {
+        Action::Route(value).into()
     }
 }
 
-impl<'a> Iterator for Actions<'a> {
-    type Item = Action<'a>;
+impl<A: AddressFamily> Iterator for Actions<A> {
+    type Item = Action<A>;
 
-    fn next(&mut self) -> Option<Action<'a>> {
-        match self {
-            Actions::None => None,
-            Actions::ShutdownActive(p, shutdown) => shutdown.next_action(*p),
-            Actions::TransitionToActive(p, transition) => transition.next_action(*p),
-            Actions::OneAction(action) => action.take(),
+    fn next(&mut self) -> Option<Action<A>> {
+        while self.next < self.len {
+            let action = match self.step(self.next) {
+                Step::ShutdownActive(p, shutdown) => shutdown.next_action(p),
+                Step::TransitionToActive(p, transition) => transition.next_action(p),
+                Step::OneAction(action) => action.take(),
+            };
+            if action.is_some() {
+                return action;
+            }
+            self.next += 1;
         }
+        None
     }
 }
 
 #[derive(Debug, PartialEq, Default)]
 pub enum TransitionToActive {
+    JoinMulticast,
     #[default]
     Activate,
     Advertisment,
@@ -76,27 +217,39 @@ pub enum TransitionToActive {
 }
 
 impl TransitionToActive {
-    fn next_action<'a, 'b>(&'a mut self, parameters: &'b Parameters) -> Option<Action<'b>> {
+    fn next_action<A: AddressFamily>(
+        &mut self,
+        parameters: &Arc<Parameters<A>>,
+    ) -> Option<Action<A>> {
         use TransitionToActive::*;
         match *self {
+            JoinMulticast => {
+                *self = Activate;
+                Some(Action::JoinMulticast {
+                    interface: parameters.interface,
+                    group: A::MULTICAST_GROUP,
+                })
+            }
             Activate => {
                 *self = Advertisment;
-                Some(Action::Activate)
+                Some(Action::Activate {
+                    interface: parameters.interface,
+                    addresses: parameters.virtual_addresses.clone(),
+                })
             }
             Advertisment => {
                 *self = NextARP(0);
-                Some(SendPacket::Advertisement(&parameters).into())
+                Some(Action::send(
+                    parameters,
+                    SendPacket::advertisement(parameters.clone()),
+                ))
             }
             NextARP(offset) => parameters
                 .virtual_addresses
                 .get(offset)
                 .map(|next_address| {
                     *self = NextARP(offset + 1);
-                    SendPacket::GratuitousARP {
-                        sender_mac: parameters.mac_address(),
-                        sender_ip: next_address,
-                    }
-                    .into()
+                    Action::send(parameters, A::announcement(parameters, next_address))
                 }),
         }
     }
@@ -107,19 +260,35 @@ pub enum ShutdownActive {
     #[default]
     Advertisment,
     Deactivate,
+    LeaveMulticast,
     Done,
 }
 
 impl ShutdownActive {
-    fn next_action<'a, 'b>(&'a mut self, parameters: &'b Parameters) -> Option<Action<'b>> {
+    fn next_action<A: AddressFamily>(
+        &mut self,
+        parameters: &Arc<Parameters<A>>,
+    ) -> Option<Action<A>> {
         match *self {
             ShutdownActive::Advertisment => {
                 *self = ShutdownActive::Deactivate;
-                Some(SendPacket::ShutdownAdvertisement(parameters).into())
+                Some(Action::send(
+                    parameters,
+                    SendPacket::shutdown_advertisement(parameters.clone()),
+                ))
             }
             ShutdownActive::Deactivate => {
+                *self = ShutdownActive::LeaveMulticast;
+                Some(Action::Deactivate {
+                    interface: parameters.interface,
+                })
+            }
+            ShutdownActive::LeaveMulticast => {
                 *self = ShutdownActive::Done;
-                Some(Action::Deactivate)
+                Some(Action::LeaveMulticast {
+                    interface: parameters.interface,
+                    group: A::MULTICAST_GROUP,
+                })
             }
             ShutdownActive::Done => None,
         }
@@ -1,29 +1,166 @@
-use std::net::Ipv4Addr;
+use crate::{wire, AddressFamily, Error};
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::Index;
+
+/// How many virtual addresses are stored inline with the `smallvec` feature; more spill to
+/// the heap.
+#[cfg(feature = "smallvec")]
+pub const INLINE_ADDRESSES: usize = 4;
+
+#[cfg(feature = "smallvec")]
+type Storage<A> = smallvec::SmallVec<[VirtualAddress<A>; INLINE_ADDRESSES]>;
+#[cfg(not(feature = "smallvec"))]
+type Storage<A> = Vec<VirtualAddress<A>>;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct VirtualAddresses(Vec<Ipv4Addr>);
+pub struct VirtualAddresses<A: AddressFamily = Ipv4Addr>(Storage<A>);
+
+/// A virtual address with the prefix length it is assigned to the interface with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VirtualAddress<A: AddressFamily = Ipv4Addr> {
+    pub address: A,
+    pub prefix_len: u8,
+    /// Only answer ARP requests for the address from hosts in its subnet.
+    pub arp_subnet_only: bool,
+}
+
+impl<A: AddressFamily> VirtualAddress<A> {
+    pub fn with_arp_subnet_only(self, arp_subnet_only: bool) -> Self {
+        Self {
+            arp_subnet_only,
+            ..self
+        }
+    }
 
-impl TryFrom<Vec<Ipv4Addr>> for VirtualAddresses {
-    type Error = ();
+    /// Whether `ip` is in the subnet given by the prefix length.
+    pub fn in_subnet(&self, ip: A) -> bool {
+        self.address.same_prefix(ip, self.prefix_len)
+    }
+}
 
-    fn try_from(value: Vec<Ipv4Addr>) -> Result<Self, Self::Error> {
+impl<A: AddressFamily> From<A> for VirtualAddress<A> {
+    fn from(address: A) -> Self {
+        Self {
+            address,
+            prefix_len: A::MAX_PREFIX_LEN,
+            arp_subnet_only: false,
+        }
+    }
+}
+
+impl<A: AddressFamily> TryFrom<(A, u8)> for VirtualAddress<A> {
+    type Error = Error;
+
+    fn try_from((address, prefix_len): (A, u8)) -> Result<Self, Self::Error> {
+        if prefix_len > A::MAX_PREFIX_LEN {
+            return Err(Error::PrefixLength(prefix_len));
+        }
+        Ok(Self {
+            address,
+            prefix_len,
+            arp_subnet_only: false,
+        })
+    }
+}
+
+impl<A: AddressFamily> TryFrom<Vec<VirtualAddress<A>>> for VirtualAddresses<A> {
+    type Error = Error;
+
+    fn try_from(value: Vec<VirtualAddress<A>>) -> Result<Self, Self::Error> {
         if value.is_empty() {
-            return Err(());
+            return Err(Error::NoVirtualAddresses);
+        }
+        if value.len() > wire::MAX_ADDRESSES {
+            return Err(Error::TooManyVirtualAddresses(value.len()));
+        }
+        for (index, virtual_address) in value.iter().enumerate() {
+            if value[..index]
+                .iter()
+                .any(|other| other.address == virtual_address.address)
+            {
+                return Err(Error::DuplicateVirtualAddress(
+                    virtual_address.address.into(),
+                ));
+            }
         }
-        Ok(Self(value))
+        Ok(Self(value.into_iter().collect()))
     }
 }
 
-impl VirtualAddresses {
-    pub fn get(&self, index: u8) -> Option<Ipv4Addr> {
-        self.0.get(index as usize).copied()
+impl<A: AddressFamily> TryFrom<Vec<A>> for VirtualAddresses<A> {
+    type Error = Error;
+
+    fn try_from(value: Vec<A>) -> Result<Self, Self::Error> {
+        value
+            .into_iter()
+            .map(VirtualAddress::from)
+            .collect::<Vec<_>>()
+            .try_into()
     }
+}
 
-    pub fn first(&self) -> Ipv4Addr {
-        *self.0.first().unwrap()
+/// Rejects addresses of the other family with `Error::AddressFamily`.
+impl<A: AddressFamily> TryFrom<Vec<IpAddr>> for VirtualAddresses<A> {
+    type Error = Error;
+
+    fn try_from(value: Vec<IpAddr>) -> Result<Self, Self::Error> {
+        value
+            .into_iter()
+            .map(|address| A::from_ip(address).ok_or(Error::AddressFamily(address)))
+            .collect::<Result<Vec<_>, _>>()?
+            .try_into()
+    }
+}
+
+impl<A: AddressFamily> VirtualAddresses<A> {
+    pub fn get(&self, index: u8) -> Option<A> {
+        self.0
+            .get(index as usize)
+            .map(|virtual_address| virtual_address.address)
+    }
+
+    /// Never panics: there is always at least one address, see `TryFrom`.
+    pub fn first(&self) -> A {
+        self.0[0].address
+    }
+
+    pub fn contains(&self, ip: A) -> bool {
+        self.find(ip).is_some()
+    }
+
+    pub fn find(&self, ip: A) -> Option<&VirtualAddress<A>> {
+        self.0
+            .iter()
+            .find(|virtual_address| virtual_address.address == ip)
+    }
+
+    /// Iterates over the addresses in the order they were configured.
+    pub fn iter(&self) -> impl Iterator<Item = &VirtualAddress<A>> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
 
-    pub fn contains(&self, ip: Ipv4Addr) -> bool {
-        self.0.contains(&ip)
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<A: AddressFamily> Index<usize> for VirtualAddresses<A> {
+    type Output = VirtualAddress<A>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl<'a, A: AddressFamily> IntoIterator for &'a VirtualAddresses<A> {
+    type Item = &'a VirtualAddress<A>;
+    type IntoIter = std::slice::Iter<'a, VirtualAddress<A>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
     }
 }
@@ -0,0 +1,74 @@
+use crate::{InterfaceId, Parameters, VRID};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tracks the VRIDs in use on each interface, for creating virtual routers dynamically.
+#[derive(Clone, Debug, Default)]
+pub struct VridAllocator {
+    used: BTreeMap<InterfaceId, BTreeSet<VRID>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AllocationError {
+    /// The VRID is already in use on the interface.
+    Collision { interface: InterfaceId, vrid: VRID },
+    /// All 255 VRIDs are in use on the interface.
+    Exhausted { interface: InterfaceId },
+}
+
+impl VridAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the VRID of every configured virtual router.
+    pub fn from_parameters<'a>(
+        parameters: impl IntoIterator<Item = &'a Parameters>,
+    ) -> Result<Self, AllocationError> {
+        let mut allocator = Self::new();
+        for parameters in parameters {
+            allocator.reserve(parameters.interface, parameters.vrid)?;
+        }
+        Ok(allocator)
+    }
+
+    pub fn is_used(&self, interface: InterfaceId, vrid: VRID) -> bool {
+        self.used
+            .get(&interface)
+            .is_some_and(|used| used.contains(&vrid))
+    }
+
+    /// The lowest VRID not in use on the interface, without reserving it.
+    pub fn suggest(&self, interface: InterfaceId) -> Result<VRID, AllocationError> {
+        let used = self.used.get(&interface);
+        (1..=u8::MAX)
+            .filter_map(|vrid| VRID::try_from(vrid).ok())
+            .find(|vrid| used.is_none_or(|used| !used.contains(vrid)))
+            .ok_or(AllocationError::Exhausted { interface })
+    }
+
+    pub fn reserve(&mut self, interface: InterfaceId, vrid: VRID) -> Result<(), AllocationError> {
+        match self.used.entry(interface).or_default().insert(vrid) {
+            true => Ok(()),
+            false => Err(AllocationError::Collision { interface, vrid }),
+        }
+    }
+
+    /// Reserves and returns the lowest VRID not in use on the interface.
+    pub fn allocate(&mut self, interface: InterfaceId) -> Result<VRID, AllocationError> {
+        let vrid = self.suggest(interface)?;
+        self.reserve(interface, vrid)?;
+        Ok(vrid)
+    }
+
+    /// Returns whether the VRID was in use.
+    pub fn release(&mut self, interface: InterfaceId, vrid: VRID) -> bool {
+        let Some(used) = self.used.get_mut(&interface) else {
+            return false;
+        };
+        let released = used.remove(&vrid);
+        if used.is_empty() {
+            self.used.remove(&interface);
+        }
+        released
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{Interval, Timestamp};
+use pnet_base::MacAddr;
+use std::collections::HashMap;
+
+// Bounds the memory a storm of requests from spoofed MAC addresses can take.
+const MAX_REQUESTERS: usize = 1024;
+
+/// Protects the router from a host storming ARP requests for a virtual address: while
+/// Active, at most `max_replies` requests of the same requester are answered per `window`
+/// and the excess is counted in `Stats::arp_requests_rate_limited`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArpReplyLimit {
+    pub max_replies: u8,
+    pub window: Interval,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ArpReplyLimiter {
+    // The start of the current window of each requester, and the replies sent within it.
+    requesters: HashMap<MacAddr, (Timestamp, u8)>,
+}
+
+impl ArpReplyLimiter {
+    /// Whether a request of `requester` may be answered.
+    pub(crate) fn allow(
+        &mut self,
+        now: Timestamp,
+        requester: MacAddr,
+        limit: &ArpReplyLimit,
+    ) -> bool {
+        if self.requesters.len() >= MAX_REQUESTERS && !self.requesters.contains_key(&requester) {
+            self.requesters
+                .retain(|_, (start, _)| now < *start + limit.window);
+            if self.requesters.len() >= MAX_REQUESTERS {
+                self.requesters.clear();
+            }
+        }
+        let (start, replies) = self.requesters.entry(requester).or_insert((now, 0));
+        if now >= *start + limit.window {
+            (*start, *replies) = (now, 0);
+        }
+        if *replies >= limit.max_replies {
+            return false;
+        }
+        *replies += 1;
+        true
+    }
+}
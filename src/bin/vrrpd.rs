@@ -0,0 +1,290 @@
+//! A minimal VRRP daemon: reads the `vrrp_instance` blocks of a keepalived.conf, exchanges
+//! advertisements and ARP over raw sockets and manages the virtual addresses over netlink.
+//!
+//! Usage: `vrrpd <keepalived.conf>`; stops gracefully on SIGINT or SIGTERM.
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::Duration;
+use std::{env, fs, io, process, thread};
+use vrrp_rs::compat::keepalived;
+use vrrp_rs::transport::{PnetTransport, Transport};
+use vrrp_rs::{
+    wire, Action, ActionHandler, Command, Event, Input, InterfaceId, Level, LogEvent,
+    NetlinkExecutor, ReceivedPacket, RoutePacket, RouterSet, SendPacket, Timestamp,
+    VirtualAddresses, VRID,
+};
+
+// How often blocked loops check whether the daemon is stopping.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn stop(_: libc::c_int) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+/// A packet received on an interface, with the VRID of an advertisement.
+type Received = (InterfaceId, Option<VRID>, ReceivedPacket);
+
+struct Instance {
+    name: String,
+    executor: NetlinkExecutor,
+}
+
+/// Carries out the actions of the virtual router `key`.
+struct Io<'a> {
+    key: (InterfaceId, VRID),
+    instances: &'a mut BTreeMap<(InterfaceId, VRID), Instance>,
+    transports: &'a mut BTreeMap<InterfaceId, PnetTransport>,
+}
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: vrrpd <keepalived.conf>");
+        process::exit(2);
+    };
+    if let Err(error) = run(&path) {
+        eprintln!("vrrpd: {error}");
+        process::exit(1);
+    }
+}
+
+fn run(path: &str) -> io::Result<()> {
+    let config = fs::read_to_string(path)?;
+    let interfaces = pnet_datalink::interfaces();
+    let by_name = |name: &str| {
+        let interface = interfaces.iter().find(|interface| interface.name == name)?;
+        let primary_ip = interface
+            .ips
+            .iter()
+            .find_map(|network| match network.ip() {
+                IpAddr::V4(address) => Some(address),
+                IpAddr::V6(_) => None,
+            })?;
+        Some((InterfaceId(interface.index), primary_ip))
+    };
+    let parsed = keepalived::parse(&config, by_name)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{error:?}")))?;
+
+    let (inputs, received) = mpsc::channel();
+    let mut transports = BTreeMap::new();
+    let mut instances = BTreeMap::new();
+    let mut config = Vec::new();
+    for keepalived::Instance { name, parameters } in parsed {
+        let interface = interfaces
+            .iter()
+            .find(|interface| InterfaceId(interface.index) == parameters.interface)
+            .expect("resolved while parsing");
+        if let Entry::Vacant(entry) = transports.entry(parameters.interface) {
+            // Frames are received on a socket of their own, so sending never waits for them.
+            let receiver = PnetTransport::open(interface)?;
+            receive(parameters.interface, receiver, inputs.clone());
+            entry.insert(PnetTransport::open(interface)?);
+        }
+        let executor = NetlinkExecutor::new(&interface.name, parameters.virtual_addresses.clone())?
+            .with_arp_sysctls(true);
+        instances.insert(
+            (parameters.interface, parameters.vrid),
+            Instance { name, executor },
+        );
+        // Packet sockets also see the frames sent from them, which would otherwise be taken
+        // for reflected advertisements.
+        config.push(
+            parameters
+                .with_logging(true)
+                .with_drop_own_advertisements(true),
+        );
+    }
+    drop(inputs);
+
+    unsafe {
+        libc::signal(libc::SIGINT, stop as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, stop as *const () as libc::sighandler_t);
+    }
+
+    // Started one by one, so the startup actions are known to be those of the router added
+    // last; `Action::Activate` does not tell which router it is for.
+    let mut routers = RouterSet::new();
+    for count in 1..=config.len() {
+        let parameters = &config[count - 1];
+        let mut io = Io {
+            key: (parameters.interface, parameters.vrid),
+            instances: &mut instances,
+            transports: &mut transports,
+        };
+        let startup = routers
+            .apply_config(Timestamp::now(), config[..count].to_vec())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{error:?}")))?;
+        startup.for_each(|action| io.handle(action));
+    }
+
+    while !STOP.load(Ordering::Relaxed) {
+        let now = Timestamp::now();
+        // Wake up regularly to notice signals.
+        let timeout = routers
+            .next_timer(now)
+            .map_or(POLL_INTERVAL, |next| next.saturating_duration_since(now))
+            .min(POLL_INTERVAL);
+
+        match received.recv_timeout(timeout) {
+            Ok((interface, vrid, packet)) => {
+                let now = Timestamp::now();
+                // ARP packets are for every virtual router on the interface.
+                let vrids = match vrid {
+                    Some(vrid) => vec![vrid],
+                    None => instances
+                        .keys()
+                        .filter(|(id, _)| *id == interface)
+                        .map(|(_, vrid)| *vrid)
+                        .collect(),
+                };
+                for vrid in vrids {
+                    let mut io = Io {
+                        key: (interface, vrid),
+                        instances: &mut instances,
+                        transports: &mut transports,
+                    };
+                    let input = Input::Packet(packet.clone());
+                    routers.dispatch(now, interface, vrid, input, &mut io);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::other("all receivers stopped"));
+            }
+        }
+
+        // Dispatched one by one rather than with `RouterSet::handle_timers`, so the actions
+        // reach the executor of the router they are for.
+        let now = Timestamp::now();
+        let due = instances
+            .keys()
+            .filter(|(interface, vrid)| {
+                routers
+                    .get(*interface, *vrid)
+                    .is_some_and(|router| router.next_timer(now) <= now)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        for (interface, vrid) in due {
+            let mut io = Io {
+                key: (interface, vrid),
+                instances: &mut instances,
+                transports: &mut transports,
+            };
+            routers.dispatch(now, interface, vrid, Input::Timer, &mut io);
+        }
+    }
+
+    let now = Timestamp::now();
+    let keys = instances.keys().copied().collect::<Vec<_>>();
+    for (interface, vrid) in keys {
+        let mut io = Io {
+            key: (interface, vrid),
+            instances: &mut instances,
+            transports: &mut transports,
+        };
+        let input = Command::Shutdown.into();
+        routers.dispatch(now, interface, vrid, input, &mut io);
+    }
+    Ok(())
+}
+
+/// Feeds the advertisements and ARP packets received on the interface to `inputs` from a
+/// thread of its own.
+fn receive(
+    interface: InterfaceId,
+    mut transport: impl Transport + Send + 'static,
+    inputs: Sender<Received>,
+) {
+    thread::spawn(move || {
+        let mut context = wire::ParseContext::default();
+        let mut buffer = [0; 1514];
+        while !STOP.load(Ordering::Relaxed) {
+            let deadline = Timestamp::now() + POLL_INTERVAL;
+            let length = match transport.recv_frame(&mut buffer, deadline) {
+                Ok(Some(length)) => length,
+                Ok(None) => continue,
+                Err(error) => {
+                    eprintln!("receive failed: {error}");
+                    return;
+                }
+            };
+            if let Some((vrid, packet)) = wire::decode_frame(&buffer[..length], &mut context) {
+                if inputs.send((interface, vrid, packet)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+impl Io<'_> {
+    fn name(&self) -> &str {
+        self.instances
+            .get(&self.key)
+            .map_or("vrrpd", |instance| &instance.name)
+    }
+
+    fn execute(&mut self, action: Action) {
+        let Some(instance) = self.instances.get_mut(&self.key) else {
+            return;
+        };
+        match instance.executor.execute(&action) {
+            Ok(()) => eprintln!("{}: {action:?}", instance.name),
+            Err(error) => eprintln!("{}: {action:?} failed: {error}", instance.name),
+        }
+    }
+}
+
+impl ActionHandler for Io<'_> {
+    // Frames are received promiscuously, so there is no group to join.
+    fn join_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+
+    fn leave_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+
+    fn activate(&mut self, interface: InterfaceId, addresses: VirtualAddresses) {
+        self.execute(Action::Activate {
+            interface,
+            addresses,
+        });
+    }
+
+    fn deactivate(&mut self, interface: InterfaceId) {
+        self.execute(Action::Deactivate { interface });
+    }
+
+    fn send(&mut self, interface: InterfaceId, packet: SendPacket) {
+        let frame = match wire::encode_frame(&packet) {
+            Some(Ok(frame)) => frame,
+            Some(Err(error)) => {
+                eprintln!("{}: cannot encode {packet:?}: {error:?}", self.name());
+                return;
+            }
+            // Unicast peers would need their MAC addresses resolved first.
+            None => return,
+        };
+        let name = self.name().to_owned();
+        let Some(transport) = self.transports.get_mut(&interface) else {
+            return;
+        };
+        if let Err(error) = transport.send_frame(&frame) {
+            eprintln!("{name}: send failed: {error}");
+        }
+    }
+
+    // The kernel routes packets, the daemon never sees them.
+    fn route(&mut self, _: RoutePacket) {}
+
+    fn notify(&mut self, event: Event) {
+        eprintln!("{}: {:?}: {event:?}", self.name(), event.severity());
+    }
+
+    fn log(&mut self, level: Level, event: LogEvent) {
+        eprintln!("{}: {level:?}: {event:?}", self.name());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::Timestamp;
+#[cfg(feature = "test-support")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "test-support")]
+use std::time::Duration;
+
+/// The source of the `now` passed to a `Router`, so IO drivers can be run on a fake clock.
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+}
+
+/// The monotonic system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// A clock that only moves when told to, for testing IO drivers without sleeping.
+///
+/// Clones share the same time, so one can be handed to an IO driver, even on another
+/// thread, and advanced from the test.
+#[cfg(feature = "test-support")]
+#[derive(Clone, Debug)]
+pub struct TestClock {
+    now: Arc<Mutex<Timestamp>>,
+}
+
+#[cfg(feature = "test-support")]
+impl TestClock {
+    pub fn new() -> Self {
+        Self::starting_at(Timestamp::now())
+    }
+
+    pub fn starting_at(start: Timestamp) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now = *now + duration;
+    }
+
+    /// Jumps to `at`, staying put if it is in the past.
+    pub fn advance_to(&self, at: Timestamp) {
+        let mut now = self.now.lock().unwrap();
+        *now = (*now).max(at);
+    }
+}
+
+#[cfg(feature = "test-support")]
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "test-support")]
+impl Clock for TestClock {
+    fn now(&self) -> Timestamp {
+        *self.now.lock().unwrap()
+    }
+}
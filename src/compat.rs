@@ -0,0 +1,35 @@
+//! Interoperating with other VRRP implementations: importing their configuration, and
+//! tolerating the ways some of them deviate from the RFC.
+
+use std::net::Ipv4Addr;
+
+pub mod keepalived;
+
+/// Deviations from RFC 9568 tolerated for interop with devices that get the IP header of
+/// advertisements wrong, all off by default. They are checked by `wire::ParseContext`, before
+/// an advertisement is dispatched to a router, and counted there when exercised. See also
+/// `Parameters::zero_interval_as_one_centi`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Compat {
+    /// Accept advertisements from these senders whatever their TTL, for devices that do not
+    /// send them with a TTL of 255.
+    pub accept_any_ttl_from: Vec<Ipv4Addr>,
+    /// Accept advertisements whose checksum leaves out the IP pseudo header, as VRRPv2 did.
+    pub accept_checksum_without_pseudo_header: bool,
+}
+
+impl Compat {
+    pub fn with_accept_any_ttl_from(self, accept_any_ttl_from: Vec<Ipv4Addr>) -> Self {
+        Self {
+            accept_any_ttl_from,
+            ..self
+        }
+    }
+
+    pub fn with_accept_checksum_without_pseudo_header(self, accept: bool) -> Self {
+        Self {
+            accept_checksum_without_pseudo_header: accept,
+            ..self
+        }
+    }
+}
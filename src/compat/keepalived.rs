@@ -0,0 +1,312 @@
+//! Reads the `vrrp_instance` blocks of a keepalived.conf into `Parameters`.
+//!
+//! Only the keywords with an equivalent in this crate are interpreted, everything else,
+//! including nested blocks such as `authentication`, is skipped.
+
+use crate::{
+    BackupMode, ConfigError, Error, InterfaceId, Interval, Mode, Parameters, Priority,
+    VirtualAddress, VRID,
+};
+use std::net::Ipv4Addr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub parameters: Parameters,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeepalivedError {
+    /// Unbalanced braces or a keyword missing its value.
+    Syntax {
+        line: usize,
+    },
+    /// A keyword with a value that could not be parsed or is out of range.
+    Value {
+        line: usize,
+        keyword: String,
+    },
+    /// The interface was not known to the resolver.
+    UnknownInterface(String),
+    /// A `vrrp_instance` without one of the required keywords.
+    Missing {
+        instance: String,
+        keyword: &'static str,
+    },
+    VirtualAddresses {
+        instance: String,
+        error: Error,
+    },
+    Parameters {
+        instance: String,
+        errors: Vec<ConfigError>,
+    },
+}
+
+/// Parses every `vrrp_instance` of the configuration.
+///
+/// `interfaces` resolves an interface name to its id and the primary address advertised
+/// from it, which keepalived looks up itself unless `mcast_src_ip` or `unicast_src_ip`
+/// is set.
+pub fn parse(
+    config: &str,
+    interfaces: impl Fn(&str) -> Option<(InterfaceId, Ipv4Addr)>,
+) -> Result<Vec<Instance>, KeepalivedError> {
+    let tokens = tokenize(config);
+    let mut cursor = Cursor {
+        tokens,
+        position: 0,
+    };
+    let mut instances = Vec::new();
+    while let Some(token) = cursor.next() {
+        match token.text {
+            "vrrp_instance" => {
+                let name = cursor.value(token.line)?.text.to_owned();
+                cursor.open(token.line)?;
+                instances.push(parse_instance(&mut cursor, name, &interfaces)?);
+            }
+            "{" => cursor.skip_block(token.line)?,
+            "}" => return Err(KeepalivedError::Syntax { line: token.line }),
+            _ => {}
+        }
+    }
+    Ok(instances)
+}
+
+fn parse_instance(
+    cursor: &mut Cursor,
+    name: String,
+    interfaces: &impl Fn(&str) -> Option<(InterfaceId, Ipv4Addr)>,
+) -> Result<Instance, KeepalivedError> {
+    let mut interface = None;
+    let mut source_ip = None;
+    let mut vrid = None;
+    // None for the address owner, priority 255.
+    let mut priority = Some(Priority::default());
+    let mut advertisement_interval = Interval::from_secs(1);
+    let mut preempt = true;
+    let mut accept = false;
+    let mut garp_repeat = 0;
+    let mut virtual_addresses = Vec::new();
+
+    loop {
+        let Some(token) = cursor.next() else {
+            return Err(KeepalivedError::Syntax {
+                line: cursor.last_line(),
+            });
+        };
+        let line = token.line;
+        match token.text {
+            "}" => break,
+            "{" => cursor.skip_block(line)?,
+            "interface" => interface = Some(cursor.value(line)?.text),
+            "mcast_src_ip" | "unicast_src_ip" => source_ip = Some(cursor.parse(token)?),
+            "virtual_router_id" => {
+                vrid = Some(VRID::try_from(cursor.parse::<u8>(token)?).map_err(|_| value(token))?)
+            }
+            "priority" => {
+                priority = match cursor.parse::<u8>(token)? {
+                    255 => None,
+                    priority => Some(Priority::try_from(priority).map_err(|_| value(token))?),
+                }
+            }
+            "advert_int" => {
+                let seconds: f64 = cursor.parse(token)?;
+                let centis = (seconds * 100.0).round();
+                if !(1.0..=Interval::MAX_WIRE_CENTIS as f64).contains(&centis) {
+                    return Err(value(token));
+                }
+                advertisement_interval = Interval::from_centis(centis as u16);
+            }
+            "nopreempt" => preempt = false,
+            "accept" => accept = true,
+            "no_accept" => accept = false,
+            // keepalived counts the initial gratuitous ARP, we count the repeats.
+            "garp_master_repeat" => garp_repeat = cursor.parse::<u8>(token)?.saturating_sub(1),
+            "virtual_ipaddress" => {
+                cursor.open(line)?;
+                virtual_addresses = cursor.addresses()?;
+            }
+            _ => cursor.skip_values(line),
+        }
+    }
+
+    let missing = |keyword| KeepalivedError::Missing {
+        instance: name.clone(),
+        keyword,
+    };
+    let interface = interface.ok_or_else(|| missing("interface"))?;
+    let vrid = vrid.ok_or_else(|| missing("virtual_router_id"))?;
+    let (interface_id, interface_ip) = interfaces(interface)
+        .ok_or_else(|| KeepalivedError::UnknownInterface(interface.to_owned()))?;
+    let virtual_addresses = virtual_addresses.try_into().map_err(|error| match error {
+        Error::NoVirtualAddresses => missing("virtual_ipaddress"),
+        error => KeepalivedError::VirtualAddresses {
+            instance: name.clone(),
+            error,
+        },
+    })?;
+
+    let mode = match priority {
+        None => Mode::Owner,
+        Some(priority) => BackupMode::with_primary_ip(source_ip.unwrap_or(interface_ip))
+            .with_priority(priority)
+            .with_preempt(preempt)
+            .with_accept(accept)
+            .into(),
+    };
+    let parameters = Parameters {
+        advertisement_interval,
+        ..Parameters::new(vrid, virtual_addresses, mode)
+    }
+    .with_interface(interface_id)
+    .with_garp_repeat(garp_repeat);
+    parameters
+        .validate()
+        .map_err(|errors| KeepalivedError::Parameters {
+            instance: name.clone(),
+            errors,
+        })?;
+
+    Ok(Instance { name, parameters })
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+fn tokenize(config: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (index, line) in config.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split(['#', '!']).next().unwrap_or_default();
+        for word in line.split_whitespace() {
+            // Braces may be written without surrounding whitespace.
+            let mut rest = word;
+            while !rest.is_empty() {
+                let end = match rest.find(['{', '}']) {
+                    Some(0) => 1,
+                    Some(end) => end,
+                    None => rest.len(),
+                };
+                tokens.push(Token {
+                    text: &rest[..end],
+                    line: line_number,
+                });
+                rest = &rest[end..];
+            }
+        }
+    }
+    tokens
+}
+
+struct Cursor<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get(self.position).copied();
+        self.position += 1;
+        token
+    }
+
+    fn last_line(&self) -> usize {
+        self.tokens.last().map_or(0, |token| token.line)
+    }
+
+    /// The value following a keyword, on the same line.
+    fn value(&mut self, line: usize) -> Result<Token<'a>, KeepalivedError> {
+        match self.tokens.get(self.position) {
+            Some(token) if token.line == line && !matches!(token.text, "{" | "}") => {
+                self.position += 1;
+                Ok(*token)
+            }
+            _ => Err(KeepalivedError::Syntax { line }),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&mut self, keyword: Token) -> Result<T, KeepalivedError> {
+        self.value(keyword.line)?
+            .text
+            .parse()
+            .map_err(|_| value(keyword))
+    }
+
+    fn open(&mut self, line: usize) -> Result<(), KeepalivedError> {
+        match self.next() {
+            Some(token) if token.text == "{" => Ok(()),
+            _ => Err(KeepalivedError::Syntax { line }),
+        }
+    }
+
+    fn skip_values(&mut self, line: usize) {
+        while let Some(token) = self.tokens.get(self.position) {
+            if token.line != line || token.text == "}" {
+                return;
+            }
+            self.position += 1;
+            if token.text == "{" {
+                // Errors surface as unbalanced braces of the enclosing block.
+                let _ = self.skip_block(line);
+                return;
+            }
+        }
+    }
+
+    fn skip_block(&mut self, line: usize) -> Result<(), KeepalivedError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(token) if token.text == "{" => depth += 1,
+                Some(token) if token.text == "}" => depth -= 1,
+                Some(_) => {}
+                None => return Err(KeepalivedError::Syntax { line }),
+            }
+        }
+        Ok(())
+    }
+
+    /// The first word of every line of a `virtual_ipaddress` block, which may be followed
+    /// by options such as `dev` or `label`.
+    fn addresses(&mut self) -> Result<Vec<VirtualAddress>, KeepalivedError> {
+        let mut addresses = Vec::new();
+        loop {
+            let Some(token) = self.next() else {
+                return Err(KeepalivedError::Syntax {
+                    line: self.last_line(),
+                });
+            };
+            if token.text == "}" {
+                return Ok(addresses);
+            }
+            let address = match token.text.split_once('/') {
+                Some((address, prefix_len)) => address
+                    .parse::<Ipv4Addr>()
+                    .ok()
+                    .zip(prefix_len.parse::<u8>().ok())
+                    .and_then(|address| VirtualAddress::try_from(address).ok()),
+                None => token
+                    .text
+                    .parse::<Ipv4Addr>()
+                    .ok()
+                    .map(VirtualAddress::from),
+            };
+            addresses.push(address.ok_or_else(|| KeepalivedError::Value {
+                line: token.line,
+                keyword: "virtual_ipaddress".to_owned(),
+            })?);
+            self.skip_values(token.line);
+        }
+    }
+}
+
+fn value(keyword: Token) -> KeepalivedError {
+    KeepalivedError::Value {
+        line: keyword.line,
+        keyword: keyword.text.to_owned(),
+    }
+}
@@ -0,0 +1,327 @@
+//! A control protocol for querying and commanding the virtual routers of a `RouterSet` from
+//! another process, e.g. a `vrrpctl` CLI talking to a daemon over a Unix socket. The daemon
+//! and the CLI share these types instead of duplicating the schema.
+//!
+//! Every message is a JSON document prefixed with its length as a 4 byte big endian integer.
+//! The client writes a `Request` and reads back one `Response`. Both carry the `VERSION`
+//! they were written with; fields unknown to the reader are ignored.
+
+use crate::{
+    ActionHandler, Command, InterfaceId, Parameters, Router, RouterSet, State, Timestamp, VRID,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::net::Ipv4Addr;
+
+/// Raised on incompatible changes to the schema; messages of another version are rejected.
+pub const VERSION: u32 = 1;
+
+/// Longer messages are rejected, bounding what a misbehaving peer can make us allocate.
+pub const MAX_MESSAGE_LEN: u32 = 1 << 20;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "kebab-case")]
+pub enum Request {
+    /// The status of every virtual router, answered with `Response::Status`.
+    Status,
+    ShowRouter(ShowRouter),
+    ShowPeers(ShowPeers),
+    Command {
+        interface: u32,
+        vrid: u8,
+        command: ControlCommand,
+    },
+    /// See `RouterSet::set_admin_state`.
+    SetAdminState {
+        interface: u32,
+        vrid: u8,
+        up: bool,
+    },
+    ForceFailover(ForceFailover),
+    ReloadConfig(ReloadConfig),
+}
+
+/// The status and counters of one virtual router, answered with `Response::Router`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShowRouter {
+    pub interface: u32,
+    pub vrid: u8,
+}
+
+/// The peers heard by one virtual router, answered with `Response::Peers`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShowPeers {
+    pub interface: u32,
+    pub vrid: u8,
+}
+
+/// Makes an Active router hand over to a Backup with a shutdown advertisement and start
+/// over as a Backup itself.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ForceFailover {
+    pub interface: u32,
+    pub vrid: u8,
+}
+
+/// Reloads the configuration of the daemon, from `path` or where it was loaded from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReloadConfig {
+    pub path: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlCommand {
+    Startup,
+    Shutdown,
+    Restart,
+    InterfaceDown,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "kebab-case")]
+pub enum Response {
+    Status {
+        routers: Vec<RouterStatus>,
+    },
+    Router(RouterDetails),
+    Peers {
+        peers: Vec<PeerStatus>,
+    },
+    /// The command, admin state or configuration was applied.
+    Done,
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RouterStatus {
+    pub interface: u32,
+    pub vrid: u8,
+    /// The `State::name`.
+    pub state: String,
+    pub priority: u8,
+    pub admin_up: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RouterDetails {
+    pub status: RouterStatus,
+    pub stats: RouterStats,
+}
+
+/// The counters of `Stats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterStats {
+    pub transitions: u64,
+    pub advertisements_sent: u64,
+    pub advertisements_received: u64,
+    pub sender_mac_mismatches: u64,
+    pub address_conflicts: u64,
+    pub reflected_advertisements: u64,
+    pub rejected_shutdown_advertisements: u64,
+    pub own_advertisements_dropped: u64,
+    #[serde(default)]
+    pub compat_zero_intervals: u64,
+    #[serde(default)]
+    pub arp_requests_rate_limited: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerStatus {
+    pub primary_ip: Ipv4Addr,
+    /// `None` after a shutdown advertisement.
+    pub priority: Option<u8>,
+    pub advertisements: u64,
+    pub advertisement_interval_centis: u32,
+    pub last_seen_ms_ago: u64,
+}
+
+impl From<ControlCommand> for Command {
+    fn from(command: ControlCommand) -> Self {
+        match command {
+            ControlCommand::Startup => Command::Startup,
+            ControlCommand::Shutdown => Command::Shutdown,
+            ControlCommand::Restart => Command::Restart,
+            ControlCommand::InterfaceDown => Command::InterfaceDown,
+        }
+    }
+}
+
+/// Answers a request from the routers of `set`, passing the actions of commands to `handler`.
+/// A `ReloadConfig` applies the configuration returned by `load`.
+pub fn handle(
+    set: &mut RouterSet,
+    now: Timestamp,
+    request: Request,
+    handler: &mut impl ActionHandler,
+    load: impl FnOnce(Option<&str>) -> Result<Vec<Parameters>, String>,
+) -> Response {
+    let result = match request {
+        Request::Status => Ok(Response::Status {
+            routers: set.iter().map(|router| status(set, router)).collect(),
+        }),
+        Request::ShowRouter(ShowRouter { interface, vrid }) => {
+            router(set, interface, vrid).map(|router| {
+                Response::Router(RouterDetails {
+                    status: status(set, router),
+                    stats: stats(router),
+                })
+            })
+        }
+        Request::ShowPeers(ShowPeers { interface, vrid }) => {
+            router(set, interface, vrid).map(|router| Response::Peers {
+                peers: router
+                    .peers()
+                    .map(|peer| PeerStatus {
+                        primary_ip: peer.primary_ip,
+                        priority: peer.priority.map(u8::from),
+                        advertisements: peer.advertisements,
+                        advertisement_interval_centis: peer.advertisement_interval.as_centis(),
+                        last_seen_ms_ago: now.saturating_duration_since(peer.last_seen).as_millis()
+                            as u64,
+                    })
+                    .collect(),
+            })
+        }
+        Request::Command {
+            interface,
+            vrid,
+            command,
+        } => key(interface, vrid).and_then(|(interface, vrid)| {
+            match set.dispatch(now, interface, vrid, Command::from(command).into(), handler) {
+                true => Ok(Response::Done),
+                false => Err(not_found(interface, vrid)),
+            }
+        }),
+        Request::SetAdminState {
+            interface,
+            vrid,
+            up,
+        } => key(interface, vrid).and_then(|(interface, vrid)| {
+            match set.set_admin_state(now, interface, vrid, up, handler) {
+                true => Ok(Response::Done),
+                false => Err(not_found(interface, vrid)),
+            }
+        }),
+        Request::ForceFailover(ForceFailover { interface, vrid }) => router(set, interface, vrid)
+            .and_then(|router| match router.state() {
+                State::Active { .. } => Ok((router.parameters().interface, router.vrid())),
+                state => Err(error(format!("the router is {}, not Active", state.name()))),
+            })
+            .map(|(interface, vrid)| {
+                set.dispatch(now, interface, vrid, Command::Restart.into(), handler);
+                Response::Done
+            }),
+        Request::ReloadConfig(ReloadConfig { path }) => load(path.as_deref())
+            .map_err(error)
+            .and_then(|config| {
+                set.apply_config(now, config)
+                    .map_err(|errors| error(format!("invalid configuration: {errors:?}")))
+            })
+            .map(|actions| {
+                actions.for_each(|action| handler.handle(action));
+                Response::Done
+            }),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+fn status(set: &RouterSet, router: &Router) -> RouterStatus {
+    let parameters = router.parameters();
+    RouterStatus {
+        interface: parameters.interface.0,
+        vrid: parameters.vrid.into(),
+        state: router.state().name().to_owned(),
+        priority: parameters.mode.priority().get(),
+        admin_up: set.is_admin_up(parameters.interface, parameters.vrid),
+    }
+}
+
+fn stats(router: &Router) -> RouterStats {
+    let stats = router.stats();
+    RouterStats {
+        transitions: stats.transitions,
+        advertisements_sent: stats.advertisements_sent,
+        advertisements_received: stats.advertisements_received,
+        sender_mac_mismatches: stats.sender_mac_mismatches,
+        address_conflicts: stats.address_conflicts,
+        reflected_advertisements: stats.reflected_advertisements,
+        rejected_shutdown_advertisements: stats.rejected_shutdown_advertisements,
+        own_advertisements_dropped: stats.own_advertisements_dropped,
+        compat_zero_intervals: stats.compat_zero_intervals,
+        arp_requests_rate_limited: stats.arp_requests_rate_limited,
+    }
+}
+
+fn key(interface: u32, vrid: u8) -> Result<(InterfaceId, VRID), Response> {
+    match VRID::try_from(vrid) {
+        Ok(vrid) => Ok((InterfaceId(interface), vrid)),
+        Err(invalid) => Err(error(invalid.to_string())),
+    }
+}
+
+fn router(set: &RouterSet, interface: u32, vrid: u8) -> Result<&Router, Response> {
+    let (interface, vrid) = key(interface, vrid)?;
+    set.get(interface, vrid)
+        .ok_or_else(|| not_found(interface, vrid))
+}
+
+fn not_found(interface: InterfaceId, vrid: VRID) -> Response {
+    error(format!(
+        "no virtual router {} on interface {}",
+        u8::from(vrid),
+        interface.0
+    ))
+}
+
+fn error(message: String) -> Response {
+    Response::Error { message }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(flatten)]
+    message: T,
+}
+
+/// Writes a `Request` or `Response` with its length prefix.
+pub fn write_message(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    let json = serde_json::to_vec(&Envelope {
+        version: VERSION,
+        message,
+    })?;
+    let length = u32::try_from(json.len())
+        .ok()
+        .filter(|&length| length <= MAX_MESSAGE_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "message too long"))?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&json)?;
+    writer.flush()
+}
+
+/// Reads a `Request` or `Response` written by `write_message`.
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<T> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length);
+    if length > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too long",
+        ));
+    }
+    let mut json = vec![0; length as usize];
+    reader.read_exact(&mut json)?;
+    let envelope: Envelope<T> = serde_json::from_slice(&json)?;
+    if envelope.version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported protocol version {}", envelope.version),
+        ));
+    }
+    Ok(envelope.message)
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::net::IpAddr;
+
+/// Why a value could not be converted into one of the types of this crate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// Priority 0 is reserved for shutdown advertisements and 255 for the address owner.
+    ReservedPriority(u8),
+    /// A prefix length longer than the address.
+    PrefixLength(u8),
+    /// A virtual router needs at least one virtual address.
+    NoVirtualAddresses,
+    DuplicateVirtualAddress(IpAddr),
+    /// A virtual address of the other family. A virtual router is for a single family, for
+    /// dual-stack run one router per family with the same VRID.
+    AddressFamily(IpAddr),
+    /// An advertisement interval of this many centiseconds, which an advertisement cannot
+    /// carry.
+    AdvertisementInterval(u32),
+    /// An advertisement can carry at most `wire::MAX_ADDRESSES` virtual addresses.
+    TooManyVirtualAddresses(usize),
+    /// Not the name of a `Profile`.
+    UnknownProfile,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ReservedPriority(priority) => write!(f, "priority {priority} is reserved"),
+            Error::PrefixLength(prefix_len) => write!(f, "invalid prefix length /{prefix_len}"),
+            Error::NoVirtualAddresses => f.write_str("no virtual addresses"),
+            Error::DuplicateVirtualAddress(address) => {
+                write!(f, "virtual address {address} is listed twice")
+            }
+            Error::AddressFamily(address) => {
+                write!(
+                    f,
+                    "virtual address {address} is of the other address family"
+                )
+            }
+            Error::AdvertisementInterval(centiseconds) => write!(
+                f,
+                "advertisement interval of {centiseconds} centiseconds is not between 1 and 4095"
+            ),
+            Error::TooManyVirtualAddresses(count) => {
+                write!(
+                    f,
+                    "{count} virtual addresses do not fit in an advertisement"
+                )
+            }
+            Error::UnknownProfile => f.write_str("unknown profile"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
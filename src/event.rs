@@ -0,0 +1,93 @@
+use crate::{AddressFamily, Command, Interval, Level, ReceivedPriority};
+use crate::{Timestamp, TransitionReason};
+use pnet_base::MacAddr;
+use std::net::Ipv4Addr;
+use std::num::NonZeroU8;
+use std::time::Duration;
+
+/// Noteworthy protocol events, emitted as `Action::Notify`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<A: AddressFamily = Ipv4Addr> {
+    /// The router changed between Active and Backup more often than allowed by its
+    /// `FlapDamping` and refuses to preempt until `hold_down_until`.
+    FlapDetected {
+        transitions: u8,
+        hold_down_until: Timestamp,
+    },
+    /// A monitoring router observed a new Active router.
+    NewActivePeer {
+        sender_ip: A,
+        priority: ReceivedPriority,
+    },
+    /// A monitoring router lost the Active router, because it shut down or timed out.
+    LostPeer { sender_ip: A },
+    /// While Active, another host claimed one of the virtual addresses in an ARP packet.
+    AddressConflict { address: A, sender_mac: MacAddr },
+    /// While Active, an advertisement with our own primary address and priority was received.
+    ReflectedAdvertisement { sender_ip: A },
+    /// In Backup, an advertisement of the Active router arrived more than half an interval
+    /// early or late, an early warning before it times out.
+    IrregularAdvertisements { sender_ip: A, jitter: Duration },
+    /// In Backup, an Active router with a lower priority is tolerated because preemption
+    /// is disabled or held down. Emitted when this starts or the reason changes, and at most
+    /// once a minute while it lasts.
+    PreemptSuppressed {
+        sender_ip: A,
+        priority: ReceivedPriority,
+        /// Our current priority, after the priority policy.
+        own_priority: NonZeroU8,
+        reason: PreemptSuppression,
+    },
+    /// While Active as the address owner, an advertisement from another owner was received.
+    OwnerConflict { sender_ip: A },
+    /// While Active, an advertisement with a different advertisement interval was received.
+    /// Repeated only once the sender or its interval changes.
+    ConfigConflict {
+        sender_ip: A,
+        advertisement_interval: Interval,
+    },
+    /// The state changed, see `Parameters::notify_state_changes`. `from` and `to` are
+    /// `State::name`s.
+    StateChanged {
+        from: &'static str,
+        to: &'static str,
+        reason: TransitionReason,
+    },
+    /// A command that does nothing in the current state, e.g. a Shutdown when already
+    /// Initialized, see `Router::accepts` and `Parameters::notify_not_applicable`.
+    NotApplicable {
+        command: Command,
+        state: &'static str,
+    },
+}
+
+/// Why a Backup does not preempt an Active router with a lower priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreemptSuppression {
+    /// Preemption is disabled in the `BackupMode`.
+    Disabled,
+    /// Held down by `Parameters::flap_damping`.
+    FlapDamping,
+    /// Within `Parameters::startup_grace`.
+    StartupGrace,
+    /// Held down after a failover scheduled with `Router::schedule_failover`.
+    FailoverHold,
+}
+
+impl<A: AddressFamily> Event<A> {
+    pub fn severity(&self) -> Level {
+        match self {
+            Event::NewActivePeer { .. }
+            | Event::PreemptSuppressed { .. }
+            | Event::StateChanged { .. }
+            | Event::NotApplicable { .. } => Level::Info,
+            Event::FlapDetected { .. }
+            | Event::LostPeer { .. }
+            | Event::AddressConflict { .. }
+            | Event::ReflectedAdvertisement { .. }
+            | Event::IrregularAdvertisements { .. }
+            | Event::ConfigConflict { .. }
+            | Event::OwnerConflict { .. } => Level::Warning,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Measuring the time from the loss of the Active router to the takeover by a Backup, on the
+//! simulated network of `scenario`, to check advertisement intervals and priorities against
+//! a failover target before deploying them.
+//!
+//! A Backup sends its first advertisement and gratuitous ARPs when it becomes Active, so the
+//! takeover is the time it changed to Active.
+
+use crate::scenario::{Scenario, ScriptedEvent, Transition};
+use crate::{Command, Parameters, State};
+use std::time::Duration;
+
+/// How the Active router is lost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Loss {
+    /// It falls silent, e.g. it crashed or lost its link.
+    Silent,
+    /// It shuts down, sending a priority 0 advertisement.
+    Shutdown,
+}
+
+/// The failover times of the runs of `measure`, sorted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FailoverTimes {
+    pub samples: Vec<Duration>,
+    /// Runs where the first router never became Active, or no Backup took over.
+    pub failed: usize,
+}
+
+impl FailoverTimes {
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.first().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.last().copied()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let total = self.samples.iter().sum::<Duration>();
+        Some(
+            total
+                / u32::try_from(self.samples.len())
+                    .ok()
+                    .filter(|&len| len > 0)?,
+        )
+    }
+
+    /// The sample below which `percent` of the samples fall, nearest rank.
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        let rank = (percent / 100.0 * self.samples.len() as f64).ceil() as usize;
+        self.samples
+            .get(rank.clamp(1, self.samples.len()) - 1)
+            .copied()
+    }
+}
+
+/// Elects `active` among `backups`, loses it and measures how long a Backup takes to take
+/// over, `runs` times. The loss happens at a different point of the advertisement interval
+/// in each run, spreading the samples over the range the failover time can take.
+///
+/// `active` must win the election, e.g. by having the greatest priority.
+pub fn measure(
+    active: &Parameters,
+    backups: &[Parameters],
+    loss: Loss,
+    runs: usize,
+) -> FailoverTimes {
+    let interval = Duration::from(active.advertisement_interval);
+    let takeover_bound = backups
+        .iter()
+        .map(|backup| Duration::from(backup.active_down_interval(active.advertised_interval())))
+        .max()
+        .unwrap_or_default()
+        + interval;
+    let mut times = FailoverTimes::default();
+    for run in 0..runs {
+        let offset = interval.mul_f64(run as f64 / runs as f64);
+        match measure_once(active, backups, loss, offset, takeover_bound) {
+            Some(time) => times.samples.push(time),
+            None => times.failed += 1,
+        }
+    }
+    times.samples.sort();
+    times
+}
+
+fn measure_once(
+    active: &Parameters,
+    backups: &[Parameters],
+    loss: Loss,
+    offset: Duration,
+    takeover_bound: Duration,
+) -> Option<Duration> {
+    let mut scenario = Scenario::new();
+    let first = scenario.add_router(active.clone());
+    for backup in backups {
+        scenario.add_router(backup.clone());
+    }
+
+    // Bounded in case another router wins the election.
+    let step = Duration::from(active.advertisement_interval);
+    for _ in 0..1000 {
+        if matches!(scenario.router(first).state(), State::Active { .. }) {
+            break;
+        }
+        scenario.run_until(scenario.now() + step);
+    }
+    if !matches!(scenario.router(first).state(), State::Active { .. }) {
+        return None;
+    }
+
+    let lost = scenario.now() + offset;
+    let event = match loss {
+        Loss::Silent => ScriptedEvent::Disconnect,
+        Loss::Shutdown => Command::Shutdown.into(),
+    };
+    scenario.at(lost, first, event);
+    scenario.run_until(lost + takeover_bound);
+    scenario
+        .transitions()
+        .iter()
+        .find(|Transition { at, router, state }| {
+            *at >= lost && *router != first && *state == "Active"
+        })
+        .map(|transition| transition.at - lost)
+}
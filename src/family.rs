@@ -0,0 +1,218 @@
+use crate::send::{VRRP_MULTICAST_GROUP, VRRP_MULTICAST_MAC};
+use crate::{wire, Parameters, SendPacket, VRID};
+use pnet_base::MacAddr;
+use std::fmt;
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// An IP version virtual routers can be run for, implemented by its address type. `Router`,
+/// `Parameters` and `SendPacket` are generic over it, so both versions share one state
+/// machine; the family supplies what differs between them.
+///
+/// Only the state machine is generic: the wire codec, `RouterSet`, the address executors
+/// and vrrpd handle IPv4 alone, IPv6 routers need an IO driver of their own.
+pub trait AddressFamily:
+    Copy + fmt::Debug + fmt::Display + Eq + Ord + Hash + Into<IpAddr> + Send + Sync + 'static
+{
+    /// The address type of the ARP variants of `ReceivedPacket` and `SendPacket`: the
+    /// address itself for IPv4, `Unsupported` for IPv6 so they cannot be built there.
+    type ArpAddr: FamilyAddr<Self>;
+    /// The address type of their Neighbor Discovery variants, likewise only IPv6 has them.
+    type NdAddr: FamilyAddr<Self>;
+
+    const UNSPECIFIED: Self;
+    /// The prefix length of a single address.
+    const MAX_PREFIX_LEN: u8;
+    /// The multicast group advertisements are sent to.
+    const MULTICAST_GROUP: Self;
+    /// The Ethernet address `MULTICAST_GROUP` maps to.
+    const MULTICAST_MAC: MacAddr;
+
+    /// `ip` if it is of this family.
+    fn from_ip(ip: IpAddr) -> Option<Self>;
+
+    /// The virtual router MAC address of `vrid`.
+    fn mac_address(vrid: VRID) -> MacAddr;
+
+    /// Whether the first `prefix_len` bits of both addresses are equal.
+    fn same_prefix(self, other: Self, prefix_len: u8) -> bool;
+
+    /// The length of the IP packet of an advertisement with `count` addresses.
+    fn packet_len(count: usize) -> usize;
+
+    /// The packet announcing after becoming Active that `sender_ip` is now at the virtual
+    /// router MAC address.
+    fn announcement(parameters: &Parameters<Self>, sender_ip: Self) -> SendPacket<Self>;
+
+    /// How many times the announcements are repeated after the first.
+    fn announcement_repeat(parameters: &Parameters<Self>) -> u8;
+
+    /// The answer to a request from `target_ip` at `target_mac` that `sender_ip` is at
+    /// `sender_mac`. An unspecified `target_ip` is a probe checking whether the address is in
+    /// use.
+    fn reply(
+        parameters: &Parameters<Self>,
+        sender_mac: MacAddr,
+        sender_ip: Self,
+        target_mac: MacAddr,
+        target_ip: Self,
+    ) -> SendPacket<Self>;
+}
+
+/// The bounds of `AddressFamily::ArpAddr` and `AddressFamily::NdAddr`.
+pub trait FamilyAddr<A>:
+    Copy + fmt::Debug + fmt::Display + Eq + Ord + Hash + Into<A> + Send + Sync + 'static
+{
+}
+
+impl<A, T> FamilyAddr<A> for T where
+    T: Copy + fmt::Debug + fmt::Display + Eq + Ord + Hash + Into<A> + Send + Sync + 'static
+{
+}
+
+/// The address type of packets the family does not have. It has no values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unsupported {}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl From<Unsupported> for Ipv4Addr {
+    fn from(unsupported: Unsupported) -> Self {
+        match unsupported {}
+    }
+}
+
+impl From<Unsupported> for Ipv6Addr {
+    fn from(unsupported: Unsupported) -> Self {
+        match unsupported {}
+    }
+}
+
+impl AddressFamily for Ipv4Addr {
+    type ArpAddr = Self;
+    type NdAddr = Unsupported;
+    const UNSPECIFIED: Self = Ipv4Addr::UNSPECIFIED;
+    const MAX_PREFIX_LEN: u8 = 32;
+    const MULTICAST_GROUP: Self = VRRP_MULTICAST_GROUP;
+    const MULTICAST_MAC: MacAddr = VRRP_MULTICAST_MAC;
+
+    fn from_ip(ip: IpAddr) -> Option<Self> {
+        match ip {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        }
+    }
+
+    fn mac_address(vrid: VRID) -> MacAddr {
+        vrid.into_mac_address()
+    }
+
+    fn same_prefix(self, other: Self, prefix_len: u8) -> bool {
+        let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+        u32::from(self) & mask == u32::from(other) & mask
+    }
+
+    fn packet_len(count: usize) -> usize {
+        wire::packet_len(count)
+    }
+
+    fn announcement(parameters: &Parameters<Self>, sender_ip: Self) -> SendPacket<Self> {
+        SendPacket::GratuitousARP {
+            sender_mac: parameters.mac_address(),
+            sender_ip,
+        }
+    }
+
+    fn announcement_repeat(parameters: &Parameters<Self>) -> u8 {
+        parameters.garp_repeat
+    }
+
+    fn reply(
+        parameters: &Parameters<Self>,
+        sender_mac: MacAddr,
+        sender_ip: Self,
+        target_mac: MacAddr,
+        target_ip: Self,
+    ) -> SendPacket<Self> {
+        SendPacket::ReplyARP {
+            sender_mac,
+            sender_ip,
+            target_mac,
+            target_ip,
+            broadcast: parameters.broadcast_arp_replies,
+        }
+    }
+}
+
+impl AddressFamily for Ipv6Addr {
+    type ArpAddr = Unsupported;
+    type NdAddr = Self;
+    const UNSPECIFIED: Self = Ipv6Addr::UNSPECIFIED;
+    const MAX_PREFIX_LEN: u8 = 128;
+    // https://datatracker.ietf.org/doc/html/rfc9568#section-5.1.2.2
+    const MULTICAST_GROUP: Self = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x12);
+    const MULTICAST_MAC: MacAddr = MacAddr(0x33, 0x33, 0x00, 0x00, 0x00, 0x12);
+
+    fn from_ip(ip: IpAddr) -> Option<Self> {
+        match ip {
+            IpAddr::V4(_) => None,
+            IpAddr::V6(ip) => Some(ip),
+        }
+    }
+
+    fn mac_address(vrid: VRID) -> MacAddr {
+        // The IPv6 block, see `VRID::into_mac_address`.
+        MacAddr(0x00, 0x00, 0x5e, 0x00, 0x02, u8::from(vrid))
+    }
+
+    fn same_prefix(self, other: Self, prefix_len: u8) -> bool {
+        let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+        u128::from(self) & mask == u128::from(other) & mask
+    }
+
+    fn packet_len(count: usize) -> usize {
+        // The fixed IPv6 header, without extension headers.
+        40 + wire::HEADER_LEN + 16 * count
+    }
+
+    fn announcement(parameters: &Parameters<Self>, sender_ip: Self) -> SendPacket<Self> {
+        SendPacket::NeighborAdvertisement {
+            sender_mac: parameters.mac_address(),
+            sender_ip,
+            flags: parameters.neighbor_flags,
+        }
+    }
+
+    fn announcement_repeat(parameters: &Parameters<Self>) -> u8 {
+        parameters.na_repeat.unwrap_or(parameters.garp_repeat)
+    }
+
+    fn reply(
+        parameters: &Parameters<Self>,
+        sender_mac: MacAddr,
+        sender_ip: Self,
+        target_mac: MacAddr,
+        target_ip: Self,
+    ) -> SendPacket<Self> {
+        // A probe comes from the unspecified address, so the answer goes to all nodes,
+        //  https://datatracker.ietf.org/doc/html/rfc4861#section-7.2.4
+        if target_ip.is_unspecified() {
+            return SendPacket::NeighborAdvertisement {
+                sender_mac,
+                sender_ip,
+                flags: parameters.neighbor_flags,
+            };
+        }
+        SendPacket::SolicitedNeighborAdvertisement {
+            sender_mac,
+            sender_ip,
+            target_mac,
+            target_ip,
+            flags: parameters.neighbor_flags,
+        }
+    }
+}
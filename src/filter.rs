@@ -0,0 +1,143 @@
+//! Capture filters passing only the traffic a set of virtual routers needs: advertisements
+//! for their VRIDs and ARP packets concerning their virtual addresses.
+
+use crate::{Parameters, VRID};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::net::Ipv4Addr;
+
+// https://www.kernel.org/doc/html/latest/networking/filter.html
+const LD_W_ABS: u16 = 0x20;
+const LD_H_ABS: u16 = 0x28;
+const LD_B_ABS: u16 = 0x30;
+const LD_B_IND: u16 = 0x50;
+const LDX_B_MSH: u16 = 0xb1;
+const JA: u16 = 0x05;
+const JEQ_K: u16 = 0x15;
+const RET_K: u16 = 0x06;
+
+const ETHERNET_HEADER_LEN: u32 = 14;
+const ETHERTYPE: u32 = 12;
+const ETHERTYPE_IPV4: u32 = 0x0800;
+const ETHERTYPE_ARP: u32 = 0x0806;
+const IPPROTO_VRRP: u32 = 112;
+const ACCEPT: u32 = 0x40000;
+
+/// A classic BPF instruction, laid out like the kernel's `struct sock_filter`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CaptureFilter {
+    vrids: BTreeSet<VRID>,
+    addresses: BTreeSet<Ipv4Addr>,
+}
+
+impl CaptureFilter {
+    pub fn new<'a>(parameters: impl IntoIterator<Item = &'a Parameters>) -> Self {
+        let mut filter = Self::default();
+        for parameters in parameters {
+            filter.vrids.insert(parameters.vrid);
+            filter
+                .addresses
+                .extend(parameters.virtual_addresses.iter().map(|a| a.address));
+        }
+        filter
+    }
+
+    /// The filter in pcap syntax, e.g. for `tcpdump` or `pcap_compile`.
+    pub fn expression(&self) -> String {
+        let mut expression = String::new();
+        if !self.vrids.is_empty() {
+            // The VRID is the second octet of the VRRP header, after any IPv4 options.
+            expression.push_str("(ip proto 112 and (");
+            for (index, vrid) in self.vrids.iter().enumerate() {
+                if index > 0 {
+                    expression.push_str(" or ");
+                }
+                let _ = write!(
+                    expression,
+                    "ip[((ip[0] & 0xf) << 2) + 1] = {}",
+                    u8::from(*vrid)
+                );
+            }
+            expression.push_str("))");
+        }
+        if !self.addresses.is_empty() {
+            if !expression.is_empty() {
+                expression.push_str(" or ");
+            }
+            expression.push_str("(arp and (");
+            for (index, address) in self.addresses.iter().enumerate() {
+                if index > 0 {
+                    expression.push_str(" or ");
+                }
+                let _ = write!(expression, "host {address}");
+            }
+            expression.push_str("))");
+        }
+        match expression.is_empty() {
+            true => "less 0".to_owned(),
+            false => expression,
+        }
+    }
+
+    /// The filter as a classic BPF program, e.g. for `SO_ATTACH_FILTER`.
+    pub fn program(&self) -> Vec<Instruction> {
+        let mut program = vec![
+            instruction(LD_H_ABS, 0, 0, ETHERTYPE),
+            instruction(JEQ_K, 1, 0, ETHERTYPE_ARP),
+            // Patched below to jump over the ARP part.
+            instruction(JA, 0, 0, 0),
+        ];
+
+        // ARP, the target or sender protocol address is a virtual address.
+        let addresses = self.addresses.iter().map(|&address| u32::from(address));
+        program.push(instruction(LD_W_ABS, 0, 0, ETHERNET_HEADER_LEN + 24));
+        any_of(&mut program, addresses.clone());
+        program.push(instruction(LD_W_ABS, 0, 0, ETHERNET_HEADER_LEN + 14));
+        any_of(&mut program, addresses);
+        program.push(instruction(RET_K, 0, 0, 0));
+        program[2].k = (program.len() - 3) as u32;
+
+        // IPv4 carrying VRRP for one of the VRIDs.
+        program.extend([
+            instruction(JEQ_K, 1, 0, ETHERTYPE_IPV4),
+            instruction(RET_K, 0, 0, 0),
+            instruction(LD_B_ABS, 0, 0, ETHERNET_HEADER_LEN + 9),
+            instruction(JEQ_K, 1, 0, IPPROTO_VRRP),
+            instruction(RET_K, 0, 0, 0),
+            instruction(LDX_B_MSH, 0, 0, ETHERNET_HEADER_LEN),
+            instruction(LD_B_IND, 0, 0, ETHERNET_HEADER_LEN + 1),
+        ]);
+        any_of(
+            &mut program,
+            self.vrids.iter().map(|&vrid| u8::from(vrid).into()),
+        );
+        program.push(instruction(RET_K, 0, 0, 0));
+        program
+    }
+}
+
+fn instruction(code: u16, jt: u8, jf: u8, k: u32) -> Instruction {
+    Instruction { code, jt, jf, k }
+}
+
+// Accepts the packet if the accumulator equals one of `values`, falls through otherwise.
+// Values are compared in chunks as a conditional jump reaches at most 255 instructions.
+fn any_of(program: &mut Vec<Instruction>, values: impl Iterator<Item = u32>) {
+    let values = values.collect::<Vec<_>>();
+    for chunk in values.chunks(u8::MAX as usize) {
+        for (index, &value) in chunk.iter().enumerate() {
+            program.push(instruction(JEQ_K, (chunk.len() - index) as u8, 0, value));
+        }
+        program.push(instruction(JA, 0, 0, 1));
+        program.push(instruction(RET_K, 0, 0, ACCEPT));
+    }
+}
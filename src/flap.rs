@@ -0,0 +1,57 @@
+use crate::Timestamp;
+use crate::{AddressFamily, Event, Interval};
+use std::collections::VecDeque;
+
+/// Protects the network from oscillation: after more than `max_transitions` changes between
+/// Active and Backup within `window`, the router refuses to preempt for `hold_down`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlapDamping {
+    pub max_transitions: u8,
+    pub window: Interval,
+    pub hold_down: Interval,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct FlapDetector {
+    transitions: VecDeque<Timestamp>,
+    hold_down_until: Option<Timestamp>,
+}
+
+impl FlapDetector {
+    /// Records a transition between Active and Backup, returning the event to emit
+    /// if the router starts to hold down.
+    pub(crate) fn record<A: AddressFamily>(
+        &mut self,
+        now: Timestamp,
+        damping: &FlapDamping,
+    ) -> Option<Event<A>> {
+        if self.is_holding_down(now) {
+            return None;
+        }
+
+        self.transitions.push_back(now);
+        while let Some(&oldest) = self.transitions.front() {
+            if oldest + damping.window > now {
+                break;
+            }
+            self.transitions.pop_front();
+        }
+        if self.transitions.len() <= damping.max_transitions as usize {
+            return None;
+        }
+
+        let transitions = self.transitions.len().min(u8::MAX as usize) as u8;
+        let hold_down_until = now + damping.hold_down;
+        self.transitions.clear();
+        self.hold_down_until = Some(hold_down_until);
+        Some(Event::FlapDetected {
+            transitions,
+            hold_down_until,
+        })
+    }
+
+    pub(crate) fn is_holding_down(&self, now: Timestamp) -> bool {
+        self.hold_down_until
+            .is_some_and(|hold_down_until| now < hold_down_until)
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{
+    Action, AddressFamily, Event, InterfaceId, Level, LogEvent, RoutePacket, SendPacket,
+    VirtualAddresses,
+};
+use std::net::Ipv4Addr;
+
+/// Carries out the actions emitted by a `Router`, see `Router::dispatch`.
+pub trait ActionHandler<A: AddressFamily = Ipv4Addr> {
+    fn join_multicast(&mut self, interface: InterfaceId, group: A);
+
+    fn leave_multicast(&mut self, interface: InterfaceId, group: A);
+
+    fn activate(&mut self, interface: InterfaceId, addresses: VirtualAddresses<A>);
+
+    fn deactivate(&mut self, interface: InterfaceId);
+
+    /// See `Parameters::prepare_activate`. Ignored unless implemented.
+    fn prepare_activate(&mut self, _interface: InterfaceId, _addresses: VirtualAddresses<A>) {}
+
+    fn cancel_prepare(&mut self, _interface: InterfaceId) {}
+
+    fn send(&mut self, interface: InterfaceId, packet: SendPacket<A>);
+
+    fn route(&mut self, decision: RoutePacket);
+
+    fn notify(&mut self, event: Event<A>);
+
+    fn log(&mut self, level: Level, event: LogEvent<A>);
+
+    fn handle(&mut self, action: Action<A>) {
+        match action {
+            Action::JoinMulticast { interface, group } => self.join_multicast(interface, group),
+            Action::LeaveMulticast { interface, group } => self.leave_multicast(interface, group),
+            Action::Activate {
+                interface,
+                addresses,
+            } => self.activate(interface, addresses),
+            Action::Deactivate { interface } => self.deactivate(interface),
+            Action::PrepareActivate {
+                interface,
+                addresses,
+            } => self.prepare_activate(interface, addresses),
+            Action::CancelPrepare { interface } => self.cancel_prepare(interface),
+            Action::Send { interface, packet } => self.send(interface, packet),
+            Action::Route(decision) => self.route(decision),
+            Action::Notify(event) => self.notify(event),
+            Action::Log(level, event) => self.log(level, event),
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct MockHandler {
+    pub joined: Vec<Ipv4Addr>,
+    pub left: Vec<Ipv4Addr>,
+    pub activated: usize,
+    pub deactivated: usize,
+    pub sent: Vec<String>,
+    pub routed: Vec<RoutePacket>,
+    pub events: Vec<Event>,
+    pub logged: Vec<(Level, LogEvent)>,
+}
+
+#[cfg(test)]
+impl ActionHandler for MockHandler {
+    fn join_multicast(&mut self, _: InterfaceId, group: Ipv4Addr) {
+        self.joined.push(group);
+    }
+
+    fn leave_multicast(&mut self, _: InterfaceId, group: Ipv4Addr) {
+        self.left.push(group);
+    }
+
+    fn activate(&mut self, _: InterfaceId, _: VirtualAddresses) {
+        self.activated += 1;
+    }
+
+    fn deactivate(&mut self, _: InterfaceId) {
+        self.deactivated += 1;
+    }
+
+    fn send(&mut self, _: InterfaceId, packet: SendPacket) {
+        self.sent.push(format!("{packet:?}"));
+    }
+
+    fn route(&mut self, decision: RoutePacket) {
+        self.routed.push(decision);
+    }
+
+    fn notify(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    fn log(&mut self, level: Level, event: LogEvent) {
+        self.logged.push((level, event));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{AddressFamily, Command, Input, ReceivedPacket, ReceivedPriority, Timestamp};
+use std::collections::VecDeque;
+use std::net::Ipv4Addr;
+
+// Enough to explain a failover without growing with a flapping router.
+const MAX_TRANSITIONS: usize = 16;
+
+/// Why the state changed, see `Event::StateChanged`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionReason {
+    /// The Backup heard no advertisement within the Active Down Interval.
+    ActiveDownTimeout,
+    /// The Active router abdicated with a priority 0 advertisement.
+    ShutdownReceived,
+    /// An advertisement with a greater priority or primary address was received.
+    Preempted,
+    /// Preempted after the priority policy lowered our priority, see
+    /// `Router::with_priority_policy`.
+    TrackingFailure,
+    /// A Startup, Shutdown or Restart command.
+    Command,
+    /// `Command::InterfaceDown`.
+    InterfaceDown,
+}
+
+/// A change of state, kept in `Router::history`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition<A: AddressFamily = Ipv4Addr> {
+    pub at: Timestamp,
+    /// The `State::name` before and after.
+    pub from: &'static str,
+    pub to: &'static str,
+    pub cause: TransitionCause<A>,
+}
+
+/// The input that caused a `Transition`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransitionCause<A: AddressFamily = Ipv4Addr> {
+    Command(Command),
+    Timer,
+    Advertisement {
+        sender_ip: A,
+        priority: ReceivedPriority,
+    },
+    ShutdownAdvertisement {
+        sender_ip: A,
+    },
+    /// An ARP packet from another host claiming a virtual address.
+    Arp {
+        sender_ip: A,
+    },
+    Packet,
+}
+
+impl<A: AddressFamily> From<&Input<A>> for TransitionCause<A> {
+    fn from(input: &Input<A>) -> Self {
+        match *input {
+            Input::Command(command) => TransitionCause::Command(command),
+            Input::Timer => TransitionCause::Timer,
+            Input::Packet(ref packet) | Input::LatePacket { ref packet, .. } => match *packet {
+                ReceivedPacket::Advertisement {
+                    sender_ip,
+                    priority,
+                    ..
+                }
+                | ReceivedPacket::TakeoverIntent {
+                    sender_ip,
+                    priority,
+                    ..
+                } => TransitionCause::Advertisement {
+                    sender_ip,
+                    priority,
+                },
+                ReceivedPacket::ShutdownAdvertisement { sender_ip, .. } => {
+                    TransitionCause::ShutdownAdvertisement { sender_ip }
+                }
+                ReceivedPacket::RequestARP { .. }
+                | ReceivedPacket::ReplyARP { .. }
+                | ReceivedPacket::GratuitousARP { .. }
+                | ReceivedPacket::NeighborSolicitation { .. }
+                | ReceivedPacket::NeighborAdvertisement { .. } => TransitionCause::Arp {
+                    sender_ip: packet.neighbor().expect("ARP or ND").sender_ip(),
+                },
+                ReceivedPacket::IP { .. } => TransitionCause::Packet,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct History<A: AddressFamily> {
+    transitions: VecDeque<Transition<A>>,
+}
+
+impl<A: AddressFamily> Default for History<A> {
+    fn default() -> Self {
+        Self {
+            transitions: VecDeque::new(),
+        }
+    }
+}
+
+impl<A: AddressFamily> History<A> {
+    pub(crate) fn record(&mut self, transition: Transition<A>) {
+        if self.transitions.len() == MAX_TRANSITIONS {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(transition);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Transition<A>> {
+        self.transitions.iter()
+    }
+}
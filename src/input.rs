@@ -1,26 +1,53 @@
-use crate::ReceivedPacket;
+use crate::{AddressFamily, ReceivedPacket, Timestamp};
+use std::net::Ipv4Addr;
 
-#[derive(Debug, PartialEq)]
-pub enum Input {
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input<A: AddressFamily = Ipv4Addr> {
     Command(Command),
-    Packet(ReceivedPacket),
+    Packet(ReceivedPacket<A>),
+    /// A packet handled later than it was received, e.g. when a burst is drained after a
+    /// scheduling delay. It is evaluated at `received_at`, so a down timer expiring in
+    /// between does not fire before the advertisement is taken into account.
+    LatePacket {
+        packet: ReceivedPacket<A>,
+        received_at: Timestamp,
+    },
     Timer,
 }
 
-#[derive(Debug, PartialEq)]
+impl<A: AddressFamily> Input<A> {
+    /// Replaces a late packet with the packet and the time it is evaluated at, which is
+    /// never after `now`.
+    pub(crate) fn resolve(self, now: Timestamp) -> (Timestamp, Input<A>) {
+        match self {
+            Input::LatePacket {
+                packet,
+                received_at,
+            } => (received_at.min(now), Input::Packet(packet)),
+            input => (now, input),
+        }
+    }
+}
+
+/// Commands are idempotent: Startup is ignored unless Initialized, Shutdown when Initialized.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Command {
     Startup,
     Shutdown,
+    /// Shutdown followed by Startup, as one stream of actions; a Startup when Initialized.
+    Restart,
+    /// A Shutdown because the interface went down, told apart in `TransitionReason`.
+    InterfaceDown,
 }
 
-impl From<Command> for Input {
+impl<A: AddressFamily> From<Command> for Input<A> {
     fn from(command: Command) -> Self {
         Self::Command(command)
     }
 }
 
-impl From<ReceivedPacket> for Input {
-    fn from(oacket: ReceivedPacket) -> Self {
+impl<A: AddressFamily> From<ReceivedPacket<A>> for Input<A> {
+    fn from(oacket: ReceivedPacket<A>) -> Self {
         Self::Packet(oacket)
     }
 }
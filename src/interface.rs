@@ -0,0 +1,6 @@
+/// Identifies the interface a virtual router is bound to, e.g. by its interface index.
+///
+/// It is carried by every action concerning the interface so routers bound to different
+/// interfaces can share one action loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InterfaceId(pub u32);
@@ -1,22 +1,93 @@
+use crate::Error;
+use std::fmt;
 use std::ops::{Add, Div, Mul};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Interval(u16);
+pub struct Interval(Duration);
 
 impl Interval {
+    /// The largest value of the 12-bit Max Advertise Interval field, in centiseconds.
+    pub const MAX_WIRE_CENTIS: u16 = 0xfff;
+    /// The longest interval an advertisement can carry.
+    pub const MAX: Self = Self::from_centis(Self::MAX_WIRE_CENTIS);
+
     pub const fn from_secs(seconds: u16) -> Self {
-        Self::from_centis(10 * seconds)
+        Self(Duration::from_secs(seconds as u64))
     }
 
     pub const fn from_centis(centiseconds: u16) -> Self {
-        Self(centiseconds)
+        Self(Duration::from_millis(10 * centiseconds as u64))
+    }
+
+    /// Like `from_centis`, but rejects intervals an advertisement cannot carry.
+    pub fn try_from_centis(centiseconds: u16) -> Result<Self, Error> {
+        match centiseconds {
+            1..=Self::MAX_WIRE_CENTIS => Ok(Self::from_centis(centiseconds)),
+            _ => Err(Error::AdvertisementInterval(centiseconds.into())),
+        }
+    }
+
+    /// Like `from_secs`, but rejects intervals an advertisement cannot carry.
+    pub fn try_from_secs(seconds: u16) -> Result<Self, Error> {
+        let centiseconds = 100 * u32::from(seconds);
+        let centiseconds =
+            u16::try_from(centiseconds).map_err(|_| Error::AdvertisementInterval(centiseconds))?;
+        Self::try_from_centis(centiseconds)
+    }
+
+    /// Non-standard sub-centisecond intervals for testing fast failover on dedicated links.
+    /// Advertisements still carry a valid Max Advertise Interval, see `as_wire_centis`.
+    #[cfg(feature = "fast-mode")]
+    pub const fn from_micros(microseconds: u32) -> Self {
+        Self(Duration::from_micros(microseconds as u64))
+    }
+
+    /// Whole centiseconds, rounded down.
+    pub const fn as_centis(self) -> u32 {
+        (self.0.as_millis() / 10) as u32
+    }
+
+    /// The interval as carried in the Max Advertise Interval field of an advertisement,
+    /// clamped to the 1..=4095 centiseconds the protocol can express.
+    pub fn as_wire_centis(self) -> u16 {
+        self.as_centis().clamp(1, Self::MAX_WIRE_CENTIS as u32) as u16
+    }
+
+    pub fn checked_add(self, rhs: Interval) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn saturating_add(self, rhs: Interval) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn checked_mul(self, rhs: u32) -> Option<Self> {
+        self.0.checked_mul(rhs).map(Self)
+    }
+
+    pub fn saturating_mul(self, rhs: u32) -> Self {
+        Self(self.0.saturating_mul(rhs))
+    }
+
+    pub(crate) const fn from_duration(duration: Duration) -> Self {
+        Self(duration)
+    }
+
+    pub(crate) fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
     }
 }
 
-impl Into<Duration> for Interval {
-    fn into(self) -> Duration {
-        Duration::from_millis(self.0 as u64 * 10)
+impl From<Interval> for Duration {
+    fn from(interval: Interval) -> Self {
+        interval.0
     }
 }
 
@@ -24,15 +95,16 @@ impl Add<Interval> for Instant {
     type Output = Instant;
 
     fn add(self, rhs: Interval) -> Self::Output {
-        self + <Interval as Into<Duration>>::into(rhs)
+        self + rhs.0
     }
 }
 
+// The operators saturate rather than panic, timers computed from any configuration are valid.
 impl Add<Interval> for Interval {
     type Output = Interval;
 
     fn add(self, rhs: Interval) -> Self::Output {
-        Self(self.0 + rhs.0)
+        self.saturating_add(rhs)
     }
 }
 
@@ -40,7 +112,7 @@ impl Mul<Interval> for u16 {
     type Output = Interval;
 
     fn mul(self, rhs: Interval) -> Self::Output {
-        Interval(self * rhs.0)
+        rhs.saturating_mul(self as u32)
     }
 }
 
@@ -48,6 +120,6 @@ impl Div<u16> for Interval {
     type Output = Interval;
 
     fn div(self, rhs: u16) -> Self::Output {
-        Interval(self.0 / rhs)
+        Interval(self.0 / rhs as u32)
     }
 }
@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How far the arrivals of the Active router's advertisements deviate from the interval
+/// it advertises, over the most recent advertisements received in Backup.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Jitter {
+    samples: VecDeque<Duration>,
+    irregular: bool,
+}
+
+impl Jitter {
+    const SAMPLES: usize = 64;
+
+    /// The jitter below which the given percentage of the recent samples fall,
+    /// `None` before two advertisements from the same Active router were received.
+    pub fn percentile(&self, percentile: u8) -> Option<Duration> {
+        let mut samples: Vec<_> = self.samples.iter().copied().collect();
+        samples.sort();
+        let rank = (samples.len() * percentile.min(100) as usize).div_ceil(100);
+        samples.get(rank.saturating_sub(1)).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Whether the last advertisement arrived more than half an interval early or late.
+    pub fn is_irregular(&self) -> bool {
+        self.irregular
+    }
+
+    /// Records the time between two advertisements, returning the jitter if the
+    /// advertisements just became irregular.
+    pub(crate) fn record(&mut self, elapsed: Duration, interval: Duration) -> Option<Duration> {
+        let jitter = elapsed.abs_diff(interval);
+        if self.samples.len() == Self::SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(jitter);
+
+        let was_irregular = std::mem::replace(&mut self.irregular, jitter > interval / 2);
+        (self.irregular && !was_irregular).then_some(jitter)
+    }
+}
@@ -1,55 +1,140 @@
 mod actions;
 mod addresses;
+mod allocator;
+mod arp_limit;
+mod clock;
+pub mod compat;
+#[cfg(feature = "control")]
+pub mod control;
+mod error;
+mod event;
+#[cfg(feature = "scenario")]
+pub mod failover;
+mod family;
+pub mod filter;
+mod flap;
+mod handler;
+mod history;
 mod input;
+mod interface;
 mod interval;
+mod jitter;
+#[cfg(feature = "linux")]
+mod linux;
+mod log;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod mode;
+#[cfg(feature = "model")]
+pub mod model;
 mod parameters;
+mod peers;
+#[cfg(feature = "pnet")]
+mod pnet;
+mod policy;
 mod priority;
+mod profile;
+mod queue;
 mod received;
+pub mod replay;
 mod router;
+mod router_set;
+#[cfg(feature = "scenario")]
+pub mod scenario;
 mod send;
+mod sharded;
+#[cfg(feature = "statechart")]
+mod statechart;
+mod stats;
+#[cfg(unix)]
+pub mod testio;
+mod timestamp;
+pub mod timing;
+pub mod transport;
 mod vrid;
+#[cfg(all(windows, feature = "windows"))]
+mod windows;
+pub mod wire;
 
 pub use actions::{Action, RoutePacket};
-pub use addresses::VirtualAddresses;
+#[cfg(feature = "smallvec")]
+pub use addresses::INLINE_ADDRESSES;
+pub use addresses::{VirtualAddress, VirtualAddresses};
+pub use allocator::{AllocationError, VridAllocator};
+pub use arp_limit::ArpReplyLimit;
+#[cfg(feature = "test-support")]
+pub use clock::TestClock;
+pub use clock::{Clock, SystemClock};
+pub use error::Error;
+pub use event::{Event, PreemptSuppression};
+pub use family::{AddressFamily, FamilyAddr, Unsupported};
+pub use flap::FlapDamping;
+pub use handler::ActionHandler;
+pub use history::{Transition, TransitionCause, TransitionReason};
 pub use input::{Command, Input};
+pub use interface::InterfaceId;
 pub use interval::Interval;
-pub use mode::{BackupMode, Mode};
-pub use parameters::Parameters;
-pub use priority::Priority;
+pub use jitter::Jitter;
+#[cfg(feature = "linux")]
+pub use linux::{Activation, NetlinkExecutor};
+pub use log::{Level, LogEvent};
+pub use mode::{AcceptException, BackupMode, Mode, OwnerConflict};
+pub use parameters::{ConfigError, ConfigPolicy, Parameters};
+pub use peers::Peer;
+#[cfg(feature = "pnet")]
+pub use pnet::PacketError;
+pub use policy::{IpPacketMeta, PriorityPolicy, RoutePolicy};
+pub use priority::{Priority, ReceivedPriority, WirePriority};
+pub use profile::Profile;
+pub use queue::{InputQueue, InputSender};
 pub use received::ReceivedPacket;
-pub use router::{Router, State};
-pub use send::SendPacket;
+pub use router::{GarpTimer, ObservedActive, Router, State};
+pub use router_set::{InvariantViolation, RouterSet};
+pub use send::{Destination, NeighborFlags, SendPacket, VRRP_MULTICAST_GROUP, VRRP_MULTICAST_MAC};
+pub use sharded::ShardedRouterSet;
+pub use stats::{InterfaceStats, Stats};
+pub use timestamp::Timestamp;
 pub use vrid::VRID;
+#[cfg(all(windows, feature = "windows"))]
+pub use windows::IpHelperExecutor;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::handler::MockHandler;
     use pnet_base::MacAddr;
     use pretty_assertions::assert_eq;
-    use std::net::Ipv4Addr;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use std::num::NonZeroU8;
-    use std::time::Instant;
 
     const TEST_PRIMARY_IP: Ipv4Addr = Ipv4Addr::new(42, 42, 42, 42);
     const TEST_SENDER_IP: Ipv4Addr = Ipv4Addr::new(24, 24, 24, 24);
     const TEST_VIRTUAL_IP_1: Ipv4Addr = Ipv4Addr::new(1, 1, 1, 1);
     const TEST_VIRTUAL_IP_2: Ipv4Addr = Ipv4Addr::new(2, 2, 2, 2);
     const TEST_SENDER_MAC: MacAddr = MacAddr(2, 5, 2, 5, 2, 5);
+    const TEST_INTERFACE: InterfaceId = InterfaceId(3);
+    const TEST_VIRTUAL_MAC: MacAddr = MacAddr(0x00, 0x00, 0x5E, 0x00, 0x01, 1);
+
+    fn send(packet: SendPacket) -> Action {
+        Action::Send {
+            interface: TEST_INTERFACE,
+            packet,
+        }
+    }
 
     fn default_mode() -> BackupMode {
         BackupMode::with_primary_ip(TEST_PRIMARY_IP)
     }
 
-    fn startup_in(mode: impl Into<Mode>) -> (Router, Parameters, Instant) {
+    fn startup_in(mode: impl Into<Mode>) -> (Router, Parameters, Timestamp) {
         let (mut router, parameters) = router_in(mode);
-        let now = Instant::now();
+        let now = Timestamp::now();
         let _ = router.handle_input(now, Command::Startup.into());
 
         (router, parameters, now)
     }
 
-    fn active_in(mode: impl Into<Mode>) -> (Router, Parameters, Instant) {
+    fn active_in(mode: impl Into<Mode>) -> (Router, Parameters, Timestamp) {
         let (mut router, parameters, now) = startup_in(mode);
 
         let now = now + Interval::from_secs(10);
@@ -64,7 +149,8 @@ mod tests {
             VRID::try_from(1).unwrap(),
             ip_addresses.try_into().unwrap(),
             mode,
-        );
+        )
+        .with_interface(TEST_INTERFACE);
 
         let router = Router::new(parameters.clone());
 
@@ -81,12 +167,19 @@ mod tests {
             "all routers should begin in the initialized state"
         );
 
-        let now = Instant::now();
+        let now = Timestamp::now();
         let actions = router
             .handle_input(now, Command::Startup.into())
             .collect::<Vec<_>>();
 
-        assert_eq!(actions, vec![]);
+        assert_eq!(
+            actions,
+            vec![Action::JoinMulticast {
+                interface: TEST_INTERFACE,
+                group: VRRP_MULTICAST_GROUP,
+            }],
+            "it should join the VRRP multicast group"
+        );
         assert_eq!(
             *router.state(),
             State::Backup {
@@ -111,26 +204,39 @@ mod tests {
 
         // On Startup
         // If the router owns the IP address(es) associated with the virtual router
-        let now = Instant::now();
+        let now = Timestamp::now();
         let actions = router
             .handle_input(now, Command::Startup.into())
             .collect::<Vec<_>>();
 
         assert_eq!(
             actions[0],
-            Action::Activate,
-            "it should Activate the virtual address on the router interface"
+            Action::JoinMulticast {
+                interface: TEST_INTERFACE,
+                group: VRRP_MULTICAST_GROUP,
+            },
+            "it should join the VRRP multicast group"
         );
         assert_eq!(
             actions[1],
-            SendPacket::Advertisement(&p).into(),
+            Action::Activate {
+                interface: TEST_INTERFACE,
+                addresses: p.virtual_addresses.clone(),
+            },
+            "it should Activate the virtual address on the router interface"
+        );
+        assert_eq!(
+            actions[2],
+            send(SendPacket::advertisement(p.clone())),
             "it should Send an ADVERTISEMENT"
         );
-        assert_eq!(vec![actions[2], actions[3]], vec![SendPacket::GratuitousARP { sender_mac: p.mac_address(), sender_ip: TEST_VIRTUAL_IP_1 }.into(), SendPacket::GratuitousARP { sender_mac: p.mac_address(), sender_ip: TEST_VIRTUAL_IP_2 }.into()], "for each IP address associated with the virtual router, it should broadcast a gratuitous ARP request containing the virtual router MAC address");
+        assert_eq!(actions[3..5], vec![send(SendPacket::GratuitousARP { sender_mac: p.mac_address(), sender_ip: TEST_VIRTUAL_IP_1 }), send(SendPacket::GratuitousARP { sender_mac: p.mac_address(), sender_ip: TEST_VIRTUAL_IP_2 })], "for each IP address associated with the virtual router, it should broadcast a gratuitous ARP request containing the virtual router MAC address");
         assert_eq!(
             *router.state(),
             State::Active {
-                adver_timer: now + p.advertisement_interval
+                adver_timer: now + p.advertisement_interval,
+                garp_timer: None,
+                accept_after: None,
             },
             "after startup, an owned router should transition to the Active state"
         );
@@ -145,15 +251,18 @@ mod tests {
 
         assert_eq!(
             actions[0],
-            Action::Activate,
+            Action::Activate {
+                interface: TEST_INTERFACE,
+                addresses: p.virtual_addresses.clone(),
+            },
             "it should Activate the virtual addresses on the router interface"
         );
         assert_eq!(
             actions[1],
-            SendPacket::Advertisement(&p).into(),
+            send(SendPacket::advertisement(p.clone())),
             "it should Send an ADVERTISEMENT"
         );
-        assert_eq!(*router.state(), State::Active { adver_timer: now + p.advertisement_interval }, "it should transition to the Active state and set the Adver_Timer to Advertisement_Interval");
+        assert_eq!(*router.state(), State::Active { adver_timer: now + p.advertisement_interval, garp_timer: None, accept_after: None }, "it should transition to the Active state and set the Adver_Timer to Advertisement_Interval");
     }
 
     #[test]
@@ -166,8 +275,11 @@ mod tests {
 
         assert_eq!(
             actions,
-            vec![],
-            "router should be doing nothing but it is not"
+            vec![Action::LeaveMulticast {
+                interface: TEST_INTERFACE,
+                group: VRRP_MULTICAST_GROUP,
+            }],
+            "it should leave the VRRP multicast group"
         );
         assert_eq!(
             *router.state(),
@@ -187,8 +299,14 @@ mod tests {
         assert_eq!(
             actions,
             vec![
-                SendPacket::ShutdownAdvertisement(&p).into(),
-                Action::Deactivate,
+                send(SendPacket::shutdown_advertisement(p.clone())),
+                Action::Deactivate {
+                    interface: TEST_INTERFACE,
+                },
+                Action::LeaveMulticast {
+                    interface: TEST_INTERFACE,
+                    group: VRRP_MULTICAST_GROUP,
+                },
             ]
         );
         assert_eq!(
@@ -207,6 +325,7 @@ mod tests {
             .handle_input(
                 now,
                 ReceivedPacket::ShutdownAdvertisement {
+                    sender_ip: TEST_SENDER_IP,
                     max_advertise_interval: expected_max_advertise_interval,
                 }
                 .into(),
@@ -234,6 +353,7 @@ mod tests {
             .handle_input(
                 now,
                 ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
                     sender_ip: TEST_SENDER_IP,
                     priority: of(201).into(),
                     max_advertise_interval: expected_max_advertise_interval,
@@ -265,8 +385,9 @@ mod tests {
             .handle_input(
                 now,
                 ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
                     sender_ip: TEST_SENDER_IP,
-                    priority: NonZeroU8::new(1).unwrap(),
+                    priority: ReceivedPriority::try_from(1).unwrap(),
                     max_advertise_interval: Interval::from_secs(5),
                 }
                 .into(),
@@ -294,15 +415,25 @@ mod tests {
             .handle_input(
                 now,
                 ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
                     sender_ip: TEST_SENDER_IP,
-                    priority: NonZeroU8::new(1).unwrap(),
+                    priority: ReceivedPriority::try_from(1).unwrap(),
                     max_advertise_interval: expected_max_advertise_interval,
                 }
                 .into(),
             )
             .collect::<Vec<_>>();
 
-        assert_eq!(actions, vec![]);
+        assert_eq!(
+            actions,
+            vec![Action::Notify(Event::PreemptSuppressed {
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(1).unwrap(),
+                own_priority: p.mode.priority(),
+                reason: PreemptSuppression::Disabled,
+            })],
+            "it should report that it does not preempt"
+        );
         assert_eq!(
             *router.state(),
             State::Backup {
@@ -310,6 +441,26 @@ mod tests {
                 active_adver_interval: expected_max_advertise_interval,
             }
         );
+
+        let advertisement = Input::from(ReceivedPacket::advertisement(
+            TEST_VIRTUAL_MAC,
+            TEST_SENDER_IP,
+            1,
+            expected_max_advertise_interval,
+        ));
+        let notified = |router: &mut Router, at| {
+            router
+                .handle_input(at, advertisement.clone())
+                .any(|action| matches!(action, Action::Notify(Event::PreemptSuppressed { .. })))
+        };
+        assert!(
+            !notified(&mut router, now + Interval::from_secs(5)),
+            "it should not repeat the reason with every advertisement"
+        );
+        assert!(
+            notified(&mut router, now + Interval::from_secs(60)),
+            "it should remind while preemption stays suppressed"
+        );
     }
 
     #[test]
@@ -321,17 +472,20 @@ mod tests {
             .handle_input(
                 now,
                 ReceivedPacket::ShutdownAdvertisement {
+                    sender_ip: TEST_SENDER_IP,
                     max_advertise_interval: expected_max_advertise_interval,
                 }
                 .into(),
             )
             .collect::<Vec<_>>();
 
-        assert_eq!(actions, vec![SendPacket::Advertisement(&p).into()]);
+        assert_eq!(actions, vec![send(SendPacket::advertisement(p.clone()))]);
         assert_eq!(
             *router.state(),
             State::Active {
                 adver_timer: now + p.advertisement_interval,
+                garp_timer: None,
+                accept_after: None,
             }
         );
     }
@@ -351,6 +505,7 @@ mod tests {
                 .handle_input(
                     now,
                     ReceivedPacket::Advertisement {
+                        sender_mac: TEST_VIRTUAL_MAC,
                         sender_ip,
                         priority: sender_priority.into(),
                         max_advertise_interval: expected_max_advertise_interval,
@@ -361,7 +516,15 @@ mod tests {
 
             assert_eq!(
                 actions,
-                vec![Action::Deactivate],
+                vec![
+                    Action::Deactivate {
+                        interface: TEST_INTERFACE,
+                    },
+                    Action::Notify(Event::ConfigConflict {
+                        sender_ip,
+                        advertisement_interval: expected_max_advertise_interval,
+                    }),
+                ],
                 "({sender_priority:?}, {sender_ip:?})"
             );
             assert_eq!(
@@ -391,6 +554,7 @@ mod tests {
                 .handle_input(
                     now,
                     ReceivedPacket::Advertisement {
+                        sender_mac: TEST_VIRTUAL_MAC,
                         sender_ip: TEST_SENDER_IP,
                         priority: sender_priority.into(),
                         max_advertise_interval: expected_max_advertise_interval,
@@ -399,7 +563,13 @@ mod tests {
                 )
                 .collect::<Vec<_>>();
 
-            assert_eq!(actions, vec![SendPacket::Advertisement(&p).into()],
+            assert_eq!(actions, vec![
+                    send(SendPacket::advertisement(p.clone())),
+                    Action::Notify(Event::ConfigConflict {
+                        sender_ip: TEST_SENDER_IP,
+                        advertisement_interval: expected_max_advertise_interval,
+                    }),
+                ],
                 "it should Send an ADVERTISEMENT immediately to assert the Active state to the sending VRRP Router \
                 and to update any learning bridges with the correct Active VRRP Router path."
             );
@@ -408,6 +578,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn active_notifies_config_conflict_once_per_interval() {
+        let (mut router, p, now) = active_in(default_mode());
+        let advertisement = |max_advertise_interval| {
+            Input::from(ReceivedPacket::Advertisement {
+                sender_mac: TEST_VIRTUAL_MAC,
+                sender_ip: TEST_SENDER_IP,
+                priority: Priority::default().into(),
+                max_advertise_interval,
+            })
+        };
+        let conflicts = |router: &mut Router, interval| {
+            router
+                .handle_input(now, advertisement(interval))
+                .filter(|action| matches!(action, Action::Notify(Event::ConfigConflict { .. })))
+                .count()
+        };
+
+        assert_eq!(conflicts(&mut router, Interval::from_secs(10)), 1);
+        assert_eq!(
+            conflicts(&mut router, Interval::from_secs(10)),
+            0,
+            "it should not repeat the same conflict"
+        );
+        assert_eq!(conflicts(&mut router, Interval::from_secs(5)), 1);
+        assert_eq!(conflicts(&mut router, p.advertisement_interval), 0);
+        assert_eq!(
+            conflicts(&mut router, Interval::from_secs(5)),
+            1,
+            "it should notify again once the conflict was resolved in between"
+        );
+    }
+
     #[test]
     fn active_adver_timer_fires() {
         let (mut router, p, now) = startup_in(Mode::Owner);
@@ -419,11 +622,13 @@ mod tests {
         let now = now + p.advertisement_interval;
         let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
 
-        assert_eq!(actions, vec![SendPacket::Advertisement(&p).into()]);
+        assert_eq!(actions, vec![send(SendPacket::advertisement(p.clone()))]);
         assert_eq!(
             *router.state(),
             State::Active {
                 adver_timer: now + p.advertisement_interval,
+                garp_timer: None,
+                accept_after: None,
             },
             "it should Reset the Adver_Timer to Advertisement_Interval"
         );
@@ -447,14 +652,111 @@ mod tests {
 
         assert_eq!(
             actions,
-            vec![SendPacket::ReplyARP {
+            vec![send(SendPacket::ReplyARP {
                 sender_mac: p.mac_address(),
                 sender_ip: TEST_VIRTUAL_IP_1,
                 target_mac: TEST_SENDER_MAC,
                 target_ip: TEST_SENDER_IP,
-            }
-            .into()]
+                broadcast: false,
+            })]
+        );
+    }
+
+    #[test]
+    fn active_broadcasts_arp_replies_when_configured() {
+        let (_, p) = router_in(Mode::Owner);
+        let mut router = Router::new(p.with_broadcast_arp_replies(true));
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::RequestARP {
+                    sender_mac: TEST_SENDER_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    target_ip: TEST_VIRTUAL_IP_1,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            actions[..],
+            [Action::Send {
+                packet: SendPacket::ReplyARP {
+                    target_mac: TEST_SENDER_MAC,
+                    broadcast: true,
+                    ..
+                },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn active_rate_limits_arp_replies_per_requester() {
+        let (_, p) = router_in(Mode::Owner);
+        let limit = ArpReplyLimit {
+            max_replies: 2,
+            window: Interval::from_secs(1),
+        };
+        let mut router = Router::new(p.with_arp_reply_limit(limit));
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+
+        let mut replies = |at, sender_mac| {
+            router
+                .handle_input(
+                    at,
+                    ReceivedPacket::RequestARP {
+                        sender_mac,
+                        sender_ip: TEST_SENDER_IP,
+                        target_ip: TEST_VIRTUAL_IP_1,
+                    }
+                    .into(),
+                )
+                .count()
+        };
+        assert_eq!(replies(now, TEST_SENDER_MAC), 1);
+        assert_eq!(replies(now, TEST_SENDER_MAC), 1);
+        assert_eq!(
+            replies(now, TEST_SENDER_MAC),
+            0,
+            "it should drop the excess"
+        );
+        assert_eq!(
+            replies(now, MacAddr::new(0x02, 0, 0, 0, 0, 1)),
+            1,
+            "other requesters should still be answered"
+        );
+        assert_eq!(replies(now + limit.window, TEST_SENDER_MAC), 1);
+        assert_eq!(router.stats().arp_requests_rate_limited, 1);
+    }
+
+    #[test]
+    fn send_packets_resolve_their_ethernet_destination() {
+        let (_, p) = router_in(Mode::Owner);
+        assert_eq!(
+            SendPacket::advertisement(p.clone()).destination_mac(),
+            Some(VRRP_MULTICAST_MAC)
         );
+        let unicast = p.clone().with_unicast_peers(vec![TEST_SENDER_IP]);
+        assert_eq!(SendPacket::advertisement(unicast).destination_mac(), None);
+
+        let gratuitous = SendPacket::<Ipv4Addr>::GratuitousARP {
+            sender_mac: p.mac_address(),
+            sender_ip: TEST_VIRTUAL_IP_1,
+        };
+        assert_eq!(gratuitous.destination_mac(), Some(MacAddr::broadcast()));
+        let reply = |broadcast| SendPacket::<Ipv4Addr>::ReplyARP {
+            sender_mac: p.mac_address(),
+            sender_ip: TEST_VIRTUAL_IP_1,
+            target_mac: TEST_SENDER_MAC,
+            target_ip: TEST_SENDER_IP,
+            broadcast,
+        };
+        assert_eq!(reply(false).destination_mac(), Some(TEST_SENDER_MAC));
+        assert_eq!(reply(true).destination_mac(), Some(MacAddr::broadcast()));
     }
 
     #[test]
@@ -468,6 +770,8 @@ mod tests {
                 ReceivedPacket::IP {
                     target_mac: p.mac_address(),
                     target_ip,
+                    protocol: 6,
+                    destination_port: Some(80),
                 }
                 .into(),
             )
@@ -478,6 +782,51 @@ mod tests {
             vec![RoutePacket::Forward.into()], "MUST forward packets with a destination link-layer MAC address equal to the virtual router MAC address.");
     }
 
+    #[test]
+    fn route_policy_overrides_the_routing_decision() {
+        let (_, p) = router_in(Mode::Owner);
+        // Drops SSH to the virtual addresses, leaving every other decision alone.
+        let policy = |packet: &IpPacketMeta| match packet {
+            IpPacketMeta {
+                protocol: 6,
+                destination_port: Some(22),
+                decision: RoutePacket::Accept,
+                ..
+            } => RoutePacket::Reject,
+            packet => packet.decision,
+        };
+        let mut router = Router::new(p.clone()).with_route_policy(policy);
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+
+        let mut route = |target_ip, destination_port| {
+            router
+                .handle_input(
+                    now,
+                    ReceivedPacket::IP {
+                        target_mac: p.mac_address(),
+                        target_ip,
+                        protocol: 6,
+                        destination_port: Some(destination_port),
+                    }
+                    .into(),
+                )
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            route(TEST_VIRTUAL_IP_1, 22),
+            vec![RoutePacket::Reject.into()]
+        );
+        assert_eq!(
+            route(TEST_VIRTUAL_IP_1, 80),
+            vec![RoutePacket::Accept.into()]
+        );
+        assert_eq!(
+            route(Ipv4Addr::new(5, 2, 5, 2), 22),
+            vec![RoutePacket::Forward.into()]
+        );
+    }
+
     #[test]
     fn active_receives_ip_packet_accepted() {
         let (mut router, p, now) = startup_in(Mode::Owner);
@@ -488,6 +837,8 @@ mod tests {
                 ReceivedPacket::IP {
                     target_mac: p.mac_address(),
                     target_ip: TEST_VIRTUAL_IP_1,
+                    protocol: 6,
+                    destination_port: Some(80),
                 }
                 .into(),
             )
@@ -508,6 +859,8 @@ mod tests {
                 ReceivedPacket::IP {
                     target_mac: p.mac_address(),
                     target_ip: TEST_VIRTUAL_IP_1,
+                    protocol: 6,
+                    destination_port: Some(80),
                 }
                 .into(),
             )
@@ -528,6 +881,8 @@ mod tests {
                 ReceivedPacket::IP {
                     target_mac: TEST_SENDER_MAC,
                     target_ip: TEST_SENDER_IP,
+                    protocol: 6,
+                    destination_port: Some(80),
                 }
                 .into(),
             )
@@ -537,4 +892,3531 @@ mod tests {
             actions,
             vec![],"MUST not forward or accept packets with a destination link-layer MAC address not equal to the virtual router MAC address.");
     }
+
+    #[cfg(feature = "linux")]
+    #[test]
+    fn netlink_attributes_are_padded_to_four_bytes() {
+        let ne = |value: u16| value.to_ne_bytes();
+
+        let attribute = linux::attribute(3, b"eth0\0");
+        assert_eq!(attribute.len(), 12);
+        assert_eq!(attribute[0..2], ne(9));
+        assert_eq!(attribute[2..4], ne(3));
+        assert_eq!(&attribute[4..9], b"eth0\0");
+        assert_eq!(attribute[9..], [0, 0, 0]);
+
+        let attribute = linux::attribute(1, &[192, 0, 2, 1]);
+        assert_eq!(attribute.len(), 8);
+        assert_eq!(attribute[0..2], ne(8));
+        assert_eq!(attribute[4..], [192, 0, 2, 1]);
+    }
+
+    #[cfg(feature = "linux")]
+    #[test]
+    fn netlink_address_payload_carries_the_virtual_address() {
+        let virtual_address = VirtualAddress {
+            address: Ipv4Addr::new(192, 0, 2, 1),
+            prefix_len: 24,
+            arp_subnet_only: false,
+        };
+        let payload = linux::address_payload(7, &virtual_address);
+
+        let mut expected = vec![libc::AF_INET as u8, 24, 0, 0];
+        expected.extend_from_slice(&7u32.to_ne_bytes());
+        for kind in [2u16, 1] {
+            expected.extend_from_slice(&8u16.to_ne_bytes());
+            expected.extend_from_slice(&kind.to_ne_bytes());
+            expected.extend_from_slice(&[192, 0, 2, 1]);
+        }
+        assert_eq!(payload, expected);
+    }
+
+    #[cfg(feature = "linux")]
+    #[test]
+    fn netlink_link_info_brings_the_interface_up() {
+        let mut expected = vec![libc::AF_UNSPEC as u8, 0, 0, 0];
+        expected.extend_from_slice(&7u32.to_ne_bytes());
+        expected.extend_from_slice(&1u32.to_ne_bytes());
+        expected.extend_from_slice(&1u32.to_ne_bytes());
+        assert_eq!(linux::link_info(7), expected);
+    }
+
+    #[test]
+    fn active_accepts_exceptions_without_accept_mode() {
+        let mode = default_mode()
+            .with_accept_exception(AcceptException::ICMP)
+            .with_accept_exception(AcceptException::tcp(179));
+        let (mut router, p, now) = active_in(mode);
+
+        let mut route = |protocol, destination_port| {
+            router
+                .handle_input(
+                    now,
+                    ReceivedPacket::IP {
+                        target_mac: p.mac_address(),
+                        target_ip: TEST_VIRTUAL_IP_1,
+                        protocol,
+                        destination_port,
+                    }
+                    .into(),
+                )
+                .map(|action| match action {
+                    Action::Route(decision) => decision,
+                    action => panic!("unexpected {action:?}"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(route(1, None), vec![RoutePacket::Accept]);
+        assert_eq!(route(6, Some(179)), vec![RoutePacket::Accept]);
+        assert_eq!(
+            route(6, Some(22)),
+            vec![RoutePacket::Forward],
+            "packets not matching an exception are not accepted"
+        );
+    }
+
+    #[test]
+    fn active_repeats_gratuitous_arps() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = p
+            .with_garp_repeat(2)
+            .with_garp_spacing(Interval::from_centis(50));
+        let mut router = Router::new(p.clone());
+        let garps = || -> Vec<Action> {
+            vec![
+                send(SendPacket::GratuitousARP {
+                    sender_mac: p.mac_address(),
+                    sender_ip: TEST_VIRTUAL_IP_1,
+                }),
+                send(SendPacket::GratuitousARP {
+                    sender_mac: p.mac_address(),
+                    sender_ip: TEST_VIRTUAL_IP_2,
+                }),
+            ]
+        };
+
+        let start = Timestamp::now();
+        let _ = router.handle_input(start, Command::Startup.into());
+        assert_eq!(router.next_timer(start), start + p.garp_spacing);
+
+        let now = start + p.garp_spacing;
+        let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
+        assert_eq!(actions, garps(), "it should repeat the gratuitous ARPs");
+        assert_eq!(
+            *router.state(),
+            State::Active {
+                adver_timer: start + p.advertisement_interval,
+                garp_timer: Some(GarpTimer {
+                    at: now + p.garp_spacing,
+                    remaining: 1,
+                }),
+                accept_after: None,
+            }
+        );
+
+        let now = now + p.garp_spacing;
+        let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            [vec![send(SendPacket::advertisement(p.clone()))], garps()].concat(),
+            "it should advertise and repeat the gratuitous ARPs when both timers fire"
+        );
+        assert_eq!(
+            *router.state(),
+            State::Active {
+                adver_timer: now + p.advertisement_interval,
+                garp_timer: None,
+                accept_after: None,
+            },
+            "it should stop repeating after garp_repeat announcements"
+        );
+    }
+
+    #[test]
+    fn virtual_addresses_preserve_order_and_reject_duplicates() {
+        let addresses = VirtualAddresses::try_from(vec![
+            VirtualAddress::try_from((TEST_VIRTUAL_IP_2, 24)).unwrap(),
+            TEST_VIRTUAL_IP_1.into(),
+        ])
+        .unwrap();
+
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(
+            addresses.iter().map(|a| a.address).collect::<Vec<_>>(),
+            vec![TEST_VIRTUAL_IP_2, TEST_VIRTUAL_IP_1]
+        );
+        assert_eq!(addresses[0].prefix_len, 24);
+        assert_eq!(addresses[1].prefix_len, 32);
+
+        assert_eq!(
+            VirtualAddresses::try_from(vec![TEST_VIRTUAL_IP_1, TEST_VIRTUAL_IP_1]),
+            Err(Error::DuplicateVirtualAddress(TEST_VIRTUAL_IP_1.into()))
+        );
+        assert_eq!(
+            VirtualAddresses::try_from(Vec::<Ipv4Addr>::new()),
+            Err(Error::NoVirtualAddresses)
+        );
+        assert_eq!(
+            VirtualAddress::try_from((TEST_VIRTUAL_IP_1, 33)),
+            Err(Error::PrefixLength(33))
+        );
+    }
+
+    #[test]
+    fn virtual_addresses_of_the_other_family_are_rejected() {
+        let ipv6 = IpAddr::from(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let mixed = vec![IpAddr::from(TEST_VIRTUAL_IP_1), ipv6];
+        assert_eq!(
+            VirtualAddresses::<Ipv4Addr>::try_from(mixed.clone()),
+            Err(Error::AddressFamily(ipv6))
+        );
+        assert_eq!(
+            VirtualAddresses::<Ipv6Addr>::try_from(mixed),
+            Err(Error::AddressFamily(TEST_VIRTUAL_IP_1.into()))
+        );
+        let addresses = VirtualAddresses::<Ipv6Addr>::try_from(vec![ipv6]).unwrap();
+        assert!(addresses.contains(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+    }
+
+    #[test]
+    fn advertised_interval_differs_from_the_send_interval() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = p.with_advertised_interval(Interval::from_secs(3));
+        assert_eq!(p.validate(), Ok(()));
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+
+        let actions: Vec<_> = router.handle_input(now, Command::Startup.into()).collect();
+        let Some(Action::Send { packet, .. }) = actions
+            .into_iter()
+            .find(|action| matches!(action, Action::Send { .. }))
+        else {
+            panic!("no advertisement sent");
+        };
+        assert_eq!(
+            packet.max_advertise_interval(),
+            Some(Interval::from_secs(3))
+        );
+        let message = wire::encode_packet(&packet).unwrap().unwrap();
+        assert_eq!(
+            wire::decode(&message).unwrap().max_advertise_interval,
+            Interval::from_secs(3)
+        );
+        assert_eq!(router.next_timer(now), now + Interval::from_secs(1));
+
+        assert_eq!(
+            p.with_advertised_interval(Interval::from_centis(50))
+                .validate(),
+            Err(vec![ConfigError::AdvertisedInterval]),
+            "peers would expect advertisements more often than they are sent"
+        );
+    }
+
+    #[test]
+    fn update_parameters() {
+        let (mut router, p, now) = startup_in(Mode::Owner);
+        assert_eq!(*router.parameters(), p);
+
+        let invalid = Parameters {
+            advertisement_interval: Interval::from_centis(0),
+            ..p.clone()
+        };
+        assert_eq!(
+            router.update_parameters(now, invalid).err(),
+            Some(vec![ConfigError::AdvertisementInterval])
+        );
+        assert_eq!(
+            *router.parameters(),
+            p,
+            "it should keep the previous parameters"
+        );
+
+        let updated = Parameters {
+            vrid: VRID::try_from(2).unwrap(),
+            ..p
+        };
+        let announcement = router
+            .update_parameters(now, updated.clone())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            announcement,
+            vec![
+                send(SendPacket::advertisement(updated.clone())),
+                send(SendPacket::GratuitousARP {
+                    sender_mac: updated.mac_address(),
+                    sender_ip: TEST_VIRTUAL_IP_1,
+                }),
+                send(SendPacket::GratuitousARP {
+                    sender_mac: updated.mac_address(),
+                    sender_ip: TEST_VIRTUAL_IP_2,
+                }),
+            ],
+            "it should announce the MAC address of the new VRID"
+        );
+        assert_eq!(*router.parameters(), updated);
+        assert_eq!(
+            router
+                .update_parameters(now, updated.clone().with_logging(true))
+                .unwrap()
+                .count(),
+            0,
+            "it should not announce again while the MAC address is unchanged"
+        );
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::RequestARP {
+                    sender_mac: TEST_SENDER_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    target_ip: TEST_VIRTUAL_IP_1,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![send(SendPacket::ReplyARP {
+                sender_mac: updated.mac_address(),
+                sender_ip: TEST_VIRTUAL_IP_1,
+                target_mac: TEST_SENDER_MAC,
+                target_ip: TEST_SENDER_IP,
+                broadcast: false,
+            })],
+            "it should answer with the MAC address of the new VRID"
+        );
+    }
+
+    #[test]
+    fn backup_reconfigured_as_owner_becomes_active_at_once() {
+        let (mut router, p, now) = startup_in(default_mode());
+        assert!(matches!(router.state(), State::Backup { .. }));
+
+        let owner = Parameters {
+            mode: Mode::Owner,
+            ..p
+        };
+        let actions = router
+            .update_parameters(now, owner.clone())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions[..2],
+            [
+                Action::Activate {
+                    interface: TEST_INTERFACE,
+                    addresses: owner.virtual_addresses.clone(),
+                },
+                send(SendPacket::advertisement(owner)),
+            ],
+            "it should not wait out the down timer"
+        );
+        assert!(matches!(router.state(), State::Active { .. }));
+    }
+
+    #[test]
+    fn active_reconfigured_as_monitor_releases_its_addresses() {
+        let (mut router, p, now) = active_in(default_mode());
+        assert!(matches!(router.state(), State::Active { .. }));
+
+        let monitor = Parameters {
+            mode: Mode::Monitor,
+            ..p
+        };
+        let actions = router
+            .update_parameters(now, monitor.clone())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                send(SendPacket::shutdown_advertisement(monitor.clone())),
+                Action::Deactivate {
+                    interface: TEST_INTERFACE
+                },
+            ]
+        );
+        assert!(matches!(router.state(), State::Monitoring { active: None }));
+    }
+
+    #[test]
+    fn backup_receives_advertisement_from_foreign_mac() {
+        for verify_sender_mac in [false, true] {
+            let (_, p) = router_in(default_mode());
+            let p = p.with_verify_sender_mac(verify_sender_mac);
+            let mut router = Router::new(p.clone());
+            let now = Timestamp::now();
+            let _ = router.handle_input(now, Command::Startup.into());
+            let initial_state = router.state().clone();
+
+            let expected_max_advertise_interval = Interval::from_secs(5);
+            let actions = router
+                .handle_input(
+                    now,
+                    ReceivedPacket::Advertisement {
+                        sender_mac: TEST_SENDER_MAC,
+                        sender_ip: TEST_SENDER_IP,
+                        priority: ReceivedPriority::try_from(200).unwrap(),
+                        max_advertise_interval: expected_max_advertise_interval,
+                    }
+                    .into(),
+                )
+                .collect::<Vec<_>>();
+
+            assert_eq!(actions, vec![]);
+            assert_eq!(router.stats().sender_mac_mismatches, 1);
+            if verify_sender_mac {
+                assert_eq!(
+                    *router.state(),
+                    initial_state,
+                    "it should drop the advertisement"
+                );
+            } else {
+                assert_eq!(
+                    *router.state(),
+                    State::Backup {
+                        active_down_timer: now
+                            + p.active_down_interval(expected_max_advertise_interval),
+                        active_adver_interval: expected_max_advertise_interval,
+                    },
+                    "it should count but still process the advertisement"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interval_wire_encoding_is_clamped() {
+        assert_eq!(Interval::from_centis(100).as_wire_centis(), 100);
+        assert_eq!(Interval::from_centis(0).as_wire_centis(), 1);
+        assert_eq!(
+            Interval::from_secs(60).as_wire_centis(),
+            Interval::MAX_WIRE_CENTIS
+        );
+    }
+
+    #[cfg(feature = "fast-mode")]
+    #[test]
+    fn fast_mode_sub_centisecond_interval() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = Parameters {
+            advertisement_interval: Interval::from_micros(2_000),
+            ..p
+        };
+        let mut router = Router::new(p.clone());
+
+        let now = Timestamp::now();
+        let _ = router.handle_input(now, Command::Startup.into());
+
+        assert_eq!(
+            router.next_timer(now),
+            now + std::time::Duration::from_millis(2)
+        );
+        assert_eq!(p.advertisement_interval.as_wire_centis(), 1);
+    }
+
+    #[test]
+    fn priority_zero_advertisement_is_shutdown() {
+        let interval = Interval::from_secs(1);
+        assert_eq!(
+            ReceivedPacket::advertisement(TEST_VIRTUAL_MAC, TEST_SENDER_IP, 0, interval),
+            ReceivedPacket::ShutdownAdvertisement {
+                sender_ip: TEST_SENDER_IP,
+                max_advertise_interval: interval,
+            }
+        );
+        assert_eq!(
+            ReceivedPacket::advertisement(TEST_VIRTUAL_MAC, TEST_SENDER_IP, 100, interval),
+            ReceivedPacket::Advertisement {
+                sender_mac: TEST_VIRTUAL_MAC,
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(100).unwrap(),
+                max_advertise_interval: interval,
+            }
+        );
+    }
+
+    #[test]
+    fn flapping_router_holds_down_preemption() {
+        let (_, p) = router_in(default_mode());
+        let damping = FlapDamping {
+            max_transitions: 2,
+            window: Interval::from_secs(60),
+            hold_down: Interval::from_secs(120),
+        };
+        let p = p.with_flap_damping(damping);
+        let mut router = Router::new(p.clone());
+        let greater_priority = ReceivedPacket::Advertisement {
+            sender_mac: TEST_VIRTUAL_MAC,
+            sender_ip: TEST_SENDER_IP,
+            priority: ReceivedPriority::try_from(200).unwrap(),
+            max_advertise_interval: p.advertisement_interval,
+        };
+
+        let mut now = Timestamp::now();
+        let _ = router.handle_input(now, Command::Startup.into());
+
+        now = router.next_timer(now);
+        let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
+        assert!(!actions.iter().any(|a| matches!(a, Action::Notify(_))));
+
+        let actions = router
+            .handle_input(now, greater_priority.clone().into())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::Deactivate {
+                interface: TEST_INTERFACE
+            }]
+        );
+
+        now = router.next_timer(now);
+        let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
+        assert_eq!(
+            actions.last(),
+            Some(&Action::Notify(Event::FlapDetected {
+                transitions: 3,
+                hold_down_until: now + damping.hold_down,
+            })),
+            "it should notify once the transitions exceed the threshold"
+        );
+
+        let _ = router.handle_input(now, greater_priority.into());
+        now = now + p.advertisement_interval;
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    priority: ReceivedPriority::try_from(1).unwrap(),
+                    max_advertise_interval: p.advertisement_interval,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::Notify(Event::PreemptSuppressed {
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(1).unwrap(),
+                own_priority: p.mode.priority(),
+                reason: PreemptSuppression::FlapDamping,
+            })]
+        );
+        assert_eq!(
+            *router.state(),
+            State::Backup {
+                active_down_timer: now + p.active_down_interval(p.advertisement_interval),
+                active_adver_interval: p.advertisement_interval,
+            },
+            "it should not preempt a lower priority router while holding down"
+        );
+    }
+
+    #[test]
+    fn timing_does_not_overflow() {
+        let max_interval = Interval::from_centis(Interval::MAX_WIRE_CENTIS);
+        for priority in 1..=u8::MAX {
+            let priority = NonZeroU8::new(priority).unwrap();
+            for centis in (0..=Interval::MAX_WIRE_CENTIS).step_by(7) {
+                let interval = Interval::from_centis(centis);
+                let skew_time = timing::skew_time(priority, interval);
+                assert!(skew_time.as_centis() <= interval.as_centis());
+                assert!(
+                    timing::active_down_interval(priority, interval).as_centis()
+                        <= 4 * max_interval.as_centis()
+                );
+            }
+        }
+        assert_eq!(
+            timing::skew_time(NonZeroU8::MAX, Interval::from_secs(1)),
+            Interval::from_secs(1) / 256
+        );
+        assert_eq!(
+            timing::active_down_interval(NonZeroU8::new(100).unwrap(), Interval::from_secs(1)),
+            Interval::from_centis(300) + 156 * Interval::from_secs(1) / 256
+        );
+    }
+
+    #[cfg(feature = "statechart")]
+    #[test]
+    fn dot_highlights_current_state() {
+        let (router, _, _) = startup_in(default_mode().with_preempt(false));
+
+        let dot = router.dot();
+
+        assert!(dot.starts_with("digraph vrid_1 {"));
+        assert!(dot.contains("label=\"VRID 1 priority 100 (no preempt)\""));
+        assert!(dot.contains("Backup [shape=box style=filled fillcolor=lightblue];"));
+        assert!(dot.contains("Active [shape=box];"));
+        assert!(dot.contains("Backup -> Backup [label=\"lower priority ADVERTISEMENT\"];"));
+    }
+
+    #[test]
+    fn monitor_tracks_active_router() {
+        let (mut router, p, now) = startup_in(Mode::Monitor);
+        assert_eq!(*router.state(), State::Monitoring { active: None });
+
+        let interval = Interval::from_secs(1);
+        let advertisement = ReceivedPacket::Advertisement {
+            sender_mac: TEST_VIRTUAL_MAC,
+            sender_ip: TEST_SENDER_IP,
+            priority: ReceivedPriority::try_from(150).unwrap(),
+            max_advertise_interval: interval,
+        };
+        let actions = router
+            .handle_input(now, advertisement.clone().into())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::Notify(Event::NewActivePeer {
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(150).unwrap(),
+            })]
+        );
+
+        let now = now + interval;
+        let actions = router
+            .handle_input(now, advertisement.into())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![],
+            "it should only notify about new Active routers"
+        );
+        let active_down_timer = now + timing::active_down_interval(NonZeroU8::MIN, interval);
+        assert_eq!(
+            *router.state(),
+            State::Monitoring {
+                active: Some(ObservedActive {
+                    sender_ip: TEST_SENDER_IP,
+                    priority: ReceivedPriority::try_from(150).unwrap(),
+                    max_advertise_interval: interval,
+                    active_down_timer,
+                })
+            }
+        );
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::IP {
+                    target_mac: p.mac_address(),
+                    target_ip: TEST_VIRTUAL_IP_1,
+                    protocol: 6,
+                    destination_port: Some(80),
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(actions, vec![RoutePacket::Reject.into()]);
+
+        let actions = router
+            .handle_input(active_down_timer, Input::Timer)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::Notify(Event::LostPeer {
+                sender_ip: TEST_SENDER_IP
+            })],
+            "it should never become Active itself"
+        );
+        assert_eq!(*router.state(), State::Monitoring { active: None });
+    }
+
+    #[test]
+    fn active_detects_address_conflict() {
+        let (mut router, p, now) = startup_in(Mode::Owner);
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::GratuitousARP {
+                    sender_mac: p.mac_address(),
+                    sender_ip: TEST_VIRTUAL_IP_1,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(actions, vec![], "its own announcements are no conflict");
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::ReplyARP {
+                    sender_mac: TEST_SENDER_MAC,
+                    sender_ip: TEST_VIRTUAL_IP_2,
+                    target_mac: p.mac_address(),
+                    target_ip: TEST_VIRTUAL_IP_1,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::Notify(Event::AddressConflict {
+                address: TEST_VIRTUAL_IP_2,
+                sender_mac: TEST_SENDER_MAC,
+            })]
+        );
+        assert_eq!(router.stats().address_conflicts, 1);
+
+        let (mut router, _, now) = startup_in(default_mode());
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::GratuitousARP {
+                    sender_mac: TEST_SENDER_MAC,
+                    sender_ip: TEST_VIRTUAL_IP_1,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![],
+            "the Active router announcing the address is no conflict for a Backup"
+        );
+    }
+
+    #[test]
+    fn active_detects_reflected_advertisement() {
+        let (mut router, p, now) = active_in(default_mode());
+        let initial_state = router.state().clone();
+        let reflected = ReceivedPacket::Advertisement {
+            sender_mac: p.mac_address(),
+            sender_ip: TEST_PRIMARY_IP,
+            priority: Priority::default().into(),
+            max_advertise_interval: p.advertisement_interval,
+        };
+
+        let actions = router
+            .handle_input(now, reflected.into())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            actions,
+            vec![Action::Notify(Event::ReflectedAdvertisement {
+                sender_ip: TEST_PRIMARY_IP
+            })]
+        );
+        assert_eq!(router.stats().reflected_advertisements, 1);
+        assert_eq!(*router.state(), initial_state, "it should NOT change state");
+    }
+
+    #[test]
+    fn active_drops_own_advertisements() {
+        let (_, p) = router_in(default_mode());
+        let own = ReceivedPacket::Advertisement {
+            sender_mac: p.mac_address(),
+            sender_ip: TEST_PRIMARY_IP,
+            priority: Priority::default().into(),
+            max_advertise_interval: p.advertisement_interval,
+        };
+        let now = Timestamp::now();
+
+        let mut router = Router::new(p.with_drop_own_advertisements(true));
+        let _ = router.handle_input(now, Command::Startup.into());
+        let now = now + Interval::from_secs(10);
+        let _ = router.handle_input(now, Input::Timer);
+        assert!(matches!(router.state(), State::Active { .. }));
+        assert_eq!(router.handle_input(now, own.into()).count(), 0);
+        assert_eq!(router.stats().own_advertisements_dropped, 1);
+        assert_eq!(router.stats().reflected_advertisements, 0);
+        assert_eq!(router.stats().advertisements_received, 0);
+    }
+
+    #[test]
+    fn router_set_applies_config_diff() {
+        let (_, p1) = router_in(default_mode());
+        let p2 = Parameters {
+            vrid: VRID::try_from(2).unwrap(),
+            ..p1.clone()
+        };
+        let now = Timestamp::now();
+
+        let mut set = RouterSet::new();
+        let actions = set
+            .apply_config(now, vec![p1.clone(), p2.clone()])
+            .unwrap()
+            .count();
+        assert_eq!(
+            actions, 2,
+            "both new routers should join the multicast group"
+        );
+        assert_eq!(set.len(), 2);
+
+        let updated = p1.clone().with_garp_repeat(2);
+        let actions = set
+            .apply_config(now, vec![updated.clone()])
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::LeaveMulticast {
+                interface: TEST_INTERFACE,
+                group: VRRP_MULTICAST_GROUP,
+            }],
+            "the removed router should be shut down"
+        );
+        assert!(set.get(TEST_INTERFACE, p2.vrid).is_none());
+        assert_eq!(
+            *set.get(TEST_INTERFACE, p1.vrid).unwrap().parameters(),
+            updated,
+            "the remaining router should be updated in place"
+        );
+        assert!(matches!(
+            set.get(TEST_INTERFACE, p1.vrid).unwrap().state(),
+            State::Backup { .. }
+        ));
+
+        assert_eq!(
+            set.apply_config(now, vec![p1.clone(), p1.clone()]).err(),
+            Some(vec![ConfigError::DuplicateVirtualRouter {
+                interface: TEST_INTERFACE,
+                vrid: p1.vrid
+            }])
+        );
+        assert_eq!(
+            set.len(),
+            1,
+            "an invalid config should leave the set untouched"
+        );
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_actions() {
+        let (mut router, p) = router_in(default_mode());
+        let now = Timestamp::now();
+
+        let mut recorder = replay::Recorder::new();
+        let mut handler = MockHandler::default();
+        let inputs = [
+            Command::Startup.into(),
+            ReceivedPacket::advertisement(
+                TEST_SENDER_MAC,
+                TEST_SENDER_IP,
+                200,
+                p.advertisement_interval,
+            )
+            .into(),
+            Input::Timer,
+        ];
+        for (seconds, input) in inputs.into_iter().enumerate() {
+            let at = now + Interval::from_secs(seconds as u16 * 5);
+            recorder.dispatch(&mut router, at, input, &mut handler);
+        }
+
+        let log = recorder.into_log();
+        assert_eq!(log.to_string().parse(), Ok(log.clone()));
+
+        let (mut fresh, _) = router_in(default_mode());
+        assert_eq!(log.replay(&mut fresh, Timestamp::now()), Ok(()));
+
+        let (mut owner, _) = router_in(Mode::Owner);
+        assert_eq!(
+            log.replay(&mut owner, Timestamp::now()).unwrap_err().entry,
+            0,
+            "an owner should diverge on startup"
+        );
+        assert_eq!(
+            "0 bogus".parse::<replay::ReplayLog>(),
+            Err(replay::ParseError { line: 1 })
+        );
+    }
+
+    #[test]
+    fn backup_learns_active_interval() {
+        let (_, p) = router_in(default_mode());
+        let p = p.with_learn_master_interval(true);
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        let _ = router.handle_input(now, Command::Startup.into());
+
+        let assumed = Interval::from_centis(Interval::MAX_WIRE_CENTIS);
+        assert_eq!(
+            *router.state(),
+            State::Backup {
+                active_down_timer: now + p.active_down_interval(assumed),
+                active_adver_interval: assumed,
+            },
+            "it should not take over after our own, shorter, down interval"
+        );
+
+        let learned = Interval::from_secs(3);
+        let now = now + Interval::from_secs(1);
+        let _ = router.handle_input(
+            now,
+            ReceivedPacket::advertisement(TEST_SENDER_MAC, TEST_SENDER_IP, 50, learned).into(),
+        );
+        assert_eq!(
+            *router.state(),
+            State::Backup {
+                active_down_timer: now + p.active_down_interval(learned),
+                active_adver_interval: learned,
+            },
+            "the down timer should be armed from the learned interval"
+        );
+    }
+
+    #[test]
+    fn received_priority_handles_owner_explicitly() {
+        assert_eq!(
+            ReceivedPriority::try_from(255),
+            Ok(ReceivedPriority::Owner255)
+        );
+        assert_eq!(
+            ReceivedPriority::try_from(0),
+            Err(Error::ReservedPriority(0))
+        );
+        assert_eq!(Priority::try_from(255), Err(Error::ReservedPriority(255)));
+        assert_eq!(u8::from(ReceivedPriority::Owner255), 255);
+        assert!(ReceivedPriority::Owner255 > Priority::try_from(254).unwrap().into());
+        assert_eq!(
+            Priority::default().saturating_sub(150),
+            Priority::try_from(1).unwrap()
+        );
+        assert_eq!(Priority::default().saturating_add(200), Priority::MAX);
+        assert_eq!(Priority::default().saturating_sub(40).as_u16(), 60);
+
+        let (mut router, _, now) = active_in(Mode::Owner);
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::advertisement(
+                    TEST_SENDER_MAC,
+                    Ipv4Addr::new(0, 0, 0, 1),
+                    255,
+                    Interval::from_secs(1),
+                )
+                .into(),
+            )
+            .count();
+        assert_eq!(
+            actions, 2,
+            "an owner with a greater address should stay Active and report the conflict"
+        );
+        assert!(matches!(router.state(), State::Active { .. }));
+    }
+
+    #[test]
+    fn wire_format_validates_address_count() {
+        let (_, p) = router_in(default_mode());
+        let addresses = |count: u32| {
+            VirtualAddresses::try_from(
+                (1..=count)
+                    .map(|n| Ipv4Addr::from(0x0a00_0000 + n))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let many = Parameters {
+            virtual_addresses: addresses(21).unwrap(),
+            ..p.clone()
+        };
+        let message = wire::encode(&many, Priority::default().into()).unwrap();
+        assert_eq!(message.len(), wire::HEADER_LEN + 4 * 21);
+        let advertisement = wire::decode(&message).unwrap();
+        assert_eq!(advertisement.addresses.len(), 21);
+        assert_eq!(
+            advertisement.max_advertise_interval,
+            p.advertisement_interval
+        );
+
+        assert_eq!(
+            wire::decode(&message[..message.len() - 1]),
+            Err(wire::WireError::Length {
+                count: 21,
+                length: message.len() - 1
+            })
+        );
+        assert_eq!(
+            wire::decode(&message[..4]),
+            Err(wire::WireError::Truncated { length: 4 })
+        );
+        let mut version_2 = message.clone();
+        version_2[0] = 0x21;
+        assert_eq!(wire::decode(&version_2), Err(wire::WireError::Version(2)));
+
+        assert_eq!(addresses(255).unwrap().len(), wire::MAX_ADDRESSES);
+        assert_eq!(addresses(256), Err(Error::TooManyVirtualAddresses(256)));
+
+        // 20 + 8 + 4 * 255 = 1048 bytes.
+        let crowded = Parameters {
+            virtual_addresses: addresses(255).unwrap(),
+            ..p
+        };
+        assert_eq!(
+            crowded.clone().with_mtu(1500).validate(),
+            Ok(()),
+            "every address count fits in an Ethernet MTU"
+        );
+        let tunnel = crowded.with_mtu(1024);
+        assert_eq!(
+            tunnel.validate(),
+            Err(vec![ConfigError::AdvertisementExceedsMtu {
+                length: 1048,
+                mtu: 1024
+            }])
+        );
+        assert_eq!(
+            wire::encode(&tunnel, Priority::default().into()),
+            Err(wire::WireError::ExceedsMtu {
+                length: 1048,
+                mtu: 1024
+            })
+        );
+    }
+
+    #[test]
+    fn logging_is_rate_limited_per_event() {
+        let (_, p) = router_in(default_mode());
+        let mut router = Router::new(p.with_logging(true));
+        let now = Timestamp::now();
+        let mut handler = MockHandler::default();
+
+        router.dispatch(now, Command::Startup.into(), &mut handler);
+        let now = now + Interval::from_secs(10);
+        router.dispatch(now, Input::Timer, &mut handler);
+        let higher = |sender_ip| {
+            ReceivedPacket::advertisement(TEST_VIRTUAL_MAC, sender_ip, 200, Interval::from_secs(1))
+        };
+        router.dispatch(now, higher(TEST_SENDER_IP).into(), &mut handler);
+        router.dispatch(now, higher(TEST_SENDER_IP).into(), &mut handler);
+        router.dispatch(now, higher(TEST_PRIMARY_IP).into(), &mut handler);
+        // Changing back within a second is not logged again.
+        router.dispatch(now, higher(TEST_SENDER_IP).into(), &mut handler);
+
+        let now = now + Interval::from_secs(10);
+        router.dispatch(now, Input::Timer, &mut handler);
+
+        assert_eq!(
+            handler.logged,
+            vec![
+                (Level::Info, LogEvent::BecameActive),
+                (
+                    Level::Info,
+                    LogEvent::LostElection {
+                        sender_ip: TEST_SENDER_IP
+                    }
+                ),
+                (
+                    Level::Info,
+                    LogEvent::PeerChanged {
+                        sender_ip: TEST_PRIMARY_IP,
+                        priority: ReceivedPriority::try_from(200).unwrap(),
+                    }
+                ),
+                (Level::Info, LogEvent::BecameActive),
+            ]
+        );
+    }
+
+    #[test]
+    fn sharded_router_set_runs_routers_on_workers() {
+        use std::sync::mpsc;
+
+        struct Forward(mpsc::Sender<String>);
+
+        impl ActionHandler for Forward {
+            fn join_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+            fn leave_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+            fn activate(&mut self, interface: InterfaceId, _: VirtualAddresses) {
+                let _ = self.0.send(format!("activate {}", interface.0));
+            }
+            fn deactivate(&mut self, interface: InterfaceId) {
+                let _ = self.0.send(format!("deactivate {}", interface.0));
+            }
+            fn send(&mut self, _: InterfaceId, _: SendPacket) {}
+            fn route(&mut self, _: RoutePacket) {}
+            fn notify(&mut self, _: Event) {}
+            fn log(&mut self, _: Level, _: LogEvent) {}
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let set = ShardedRouterSet::new(2, |_| Forward(sender.clone()));
+        drop(sender);
+
+        let (_, p) = router_in(Mode::Owner);
+        let config = (1..=3)
+            .map(|interface| p.clone().with_interface(InterfaceId(interface)))
+            .collect::<Vec<_>>();
+        set.apply_config(config).unwrap();
+        set.shutdown();
+
+        let mut actions = receiver.iter().collect::<Vec<_>>();
+        actions.sort();
+        assert_eq!(
+            actions,
+            vec![
+                "activate 1",
+                "activate 2",
+                "activate 3",
+                "deactivate 1",
+                "deactivate 2",
+                "deactivate 3"
+            ]
+        );
+    }
+
+    #[cfg(feature = "scenario")]
+    #[test]
+    fn scenario_scripts_priority_loss() {
+        use scenario::{Scenario, ScriptedEvent, Transition};
+        use std::time::Duration;
+
+        let backup = |primary_ip, priority| {
+            let mode = BackupMode::with_primary_ip(primary_ip)
+                .with_priority(Priority::try_from(priority).unwrap());
+            router_in(mode).1
+        };
+        let high = backup(Ipv4Addr::new(10, 0, 0, 1), 200);
+        let low = backup(Ipv4Addr::new(10, 0, 0, 2), 100);
+
+        let mut scenario = Scenario::new();
+        let h = scenario.add_router(high.clone());
+        let l = scenario.add_router(low);
+        // The high priority router loses a tracked route.
+        let degraded = Parameters {
+            mode: BackupMode::with_primary_ip(Ipv4Addr::new(10, 0, 0, 1))
+                .with_priority(Priority::try_from(50).unwrap())
+                .into(),
+            ..high
+        };
+        scenario.at(
+            Duration::from_secs(10),
+            h,
+            ScriptedEvent::UpdateParameters(Box::new(degraded)),
+        );
+        scenario.run_until(Duration::from_secs(20));
+
+        let states = scenario
+            .transitions()
+            .iter()
+            .map(|Transition { router, state, .. }| (*router, *state))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            vec![
+                (h, "Backup"),
+                (l, "Backup"),
+                (h, "Active"),
+                (l, "Active"),
+                (h, "Backup"),
+            ]
+        );
+        let takeover = &scenario.transitions()[3];
+        assert!(takeover.at > Duration::from_secs(10));
+    }
+
+    #[cfg(feature = "scenario")]
+    #[test]
+    fn scenario_delivers_advertisements_of_parameter_updates() {
+        use scenario::{Scenario, ScriptedEvent};
+        use std::time::Duration;
+
+        let backup = |primary_ip, priority| {
+            let mode = BackupMode::with_primary_ip(primary_ip)
+                .with_priority(Priority::try_from(priority).unwrap());
+            router_in(mode).1
+        };
+        let high = backup(Ipv4Addr::new(10, 0, 0, 1), 200);
+        let low = backup(Ipv4Addr::new(10, 0, 0, 2), 100);
+
+        let mut scenario = Scenario::new();
+        let h = scenario.add_router(high);
+        let l = scenario.add_router(low.clone());
+        let owner = Parameters {
+            mode: Mode::Owner,
+            ..low
+        };
+        scenario.at(
+            Duration::from_millis(10_500),
+            l,
+            ScriptedEvent::UpdateParameters(Box::new(owner)),
+        );
+        scenario.run_until(Duration::from_millis(10_500));
+
+        // The advertisement of the new owner reached the Active peer at once.
+        assert_eq!(scenario.router(h).state().name(), "Backup");
+        assert_eq!(scenario.router(l).state().name(), "Active");
+    }
+
+    #[cfg(feature = "scenario")]
+    #[test]
+    fn failover_times_are_measured_across_the_interval() {
+        use failover::{measure, Loss};
+        use std::time::Duration;
+
+        let backup = |primary_ip, priority| {
+            let mode = BackupMode::with_primary_ip(primary_ip)
+                .with_priority(Priority::try_from(priority).unwrap());
+            router_in(mode).1
+        };
+        let high = backup(Ipv4Addr::new(10, 0, 0, 1), 200);
+        let low = backup(Ipv4Addr::new(10, 0, 0, 2), 100);
+        let active_down = Duration::from(low.active_down_interval(high.advertisement_interval));
+        let skew = Duration::from(low.skew_time(high.advertisement_interval));
+
+        let backups = [low];
+        let silent = measure(&high, &backups, Loss::Silent, 10);
+        assert_eq!((silent.samples.len(), silent.failed), (10, 0));
+        assert!(silent.max().unwrap() <= active_down);
+        assert!(silent.min().unwrap() > active_down - Duration::from(high.advertisement_interval));
+        assert!(
+            silent.min() < silent.max(),
+            "the loss should vary within the interval"
+        );
+        assert!(silent.percentile(50.0) <= silent.percentile(99.0));
+
+        let shutdown = measure(&high, &backups, Loss::Shutdown, 4);
+        assert_eq!(
+            shutdown.mean(),
+            Some(skew),
+            "a shutdown should only wait the skew time"
+        );
+    }
+
+    #[test]
+    fn frames_round_trip_through_the_wire_codec() {
+        let (_, p) = router_in(default_mode());
+        let mut context = wire::ParseContext::default();
+
+        let frame = wire::encode_frame(&SendPacket::advertisement(p.clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            frame.len(),
+            14 + wire::IPV4_HEADER_LEN + wire::HEADER_LEN + 4 * 2
+        );
+        let header = &frame[14..14 + wire::IPV4_HEADER_LEN];
+        assert_eq!(wire::internet_checksum(header.iter().copied()), 0);
+        let (vrid, packet) = wire::decode_frame(&frame, &mut context).unwrap();
+        assert_eq!(vrid, Some(p.vrid));
+        assert_eq!(
+            packet,
+            ReceivedPacket::advertisement(
+                p.mac_address(),
+                p.primary_ip(),
+                100,
+                p.advertisement_interval,
+            )
+        );
+
+        let reply = SendPacket::ReplyARP {
+            sender_mac: p.mac_address(),
+            sender_ip: TEST_VIRTUAL_IP_1,
+            target_mac: TEST_SENDER_MAC,
+            target_ip: TEST_SENDER_IP,
+            broadcast: false,
+        };
+        let frame = wire::encode_frame(&reply).unwrap().unwrap();
+        assert_eq!(
+            wire::decode_frame(&frame, &mut context),
+            Some((
+                None,
+                ReceivedPacket::ReplyARP {
+                    sender_mac: p.mac_address(),
+                    sender_ip: TEST_VIRTUAL_IP_1,
+                    target_mac: TEST_SENDER_MAC,
+                    target_ip: TEST_SENDER_IP,
+                }
+            ))
+        );
+
+        let mut corrupted = wire::encode_frame(&SendPacket::advertisement(p.clone()))
+            .unwrap()
+            .unwrap();
+        corrupted[14 + wire::IPV4_HEADER_LEN + 2] ^= 1;
+        assert_eq!(wire::decode_frame(&corrupted, &mut context), None);
+
+        let unicast = SendPacket::Advertisement {
+            parameters: p.clone().into(),
+            source_ip: p.primary_ip(),
+            destination: Destination::Unicast(vec![TEST_SENDER_IP]),
+        };
+        assert!(wire::encode_frame(&unicast).is_none());
+    }
+
+    #[test]
+    fn keepalived_vrrp_instance_import() {
+        use compat::keepalived::{self, KeepalivedError};
+
+        let config = r#"
+            global_defs {
+                router_id LVS_DEVEL
+            }
+
+            vrrp_instance VI_1 {
+                state BACKUP
+                interface eth0
+                virtual_router_id 51
+                priority 150
+                advert_int 0.5
+                nopreempt
+                garp_master_repeat 3
+                authentication {
+                    auth_type PASS
+                    auth_pass secret
+                }
+                virtual_ipaddress {
+                    1.1.1.1/24 dev eth0 label eth0:1
+                    2.2.2.2 # a comment
+                }
+            }
+        "#;
+        let interfaces = |name: &str| (name == "eth0").then_some((TEST_INTERFACE, TEST_PRIMARY_IP));
+
+        let instances = keepalived::parse(config, interfaces).unwrap();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].name, "VI_1");
+        let mode = default_mode()
+            .with_priority(Priority::try_from(150).unwrap())
+            .with_preempt(false);
+        let expected = Parameters {
+            advertisement_interval: Interval::from_centis(50),
+            virtual_addresses: vec![
+                VirtualAddress::try_from((TEST_VIRTUAL_IP_1, 24)).unwrap(),
+                TEST_VIRTUAL_IP_2.into(),
+            ]
+            .try_into()
+            .unwrap(),
+            ..Parameters::new(
+                VRID::try_from(51).unwrap(),
+                vec![TEST_VIRTUAL_IP_1].try_into().unwrap(),
+                mode,
+            )
+        }
+        .with_interface(TEST_INTERFACE)
+        .with_garp_repeat(2);
+        assert_eq!(instances[0].parameters, expected);
+
+        let unknown = config.replace("eth0", "eth1");
+        assert_eq!(
+            keepalived::parse(&unknown, interfaces),
+            Err(KeepalivedError::UnknownInterface("eth1".to_owned()))
+        );
+        let invalid = config.replace("priority 150", "priority 0");
+        assert!(matches!(
+            keepalived::parse(&invalid, interfaces),
+            Err(KeepalivedError::Value { line: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn stats_count_transitions_and_advertisements() {
+        let (mut router, p, now) = active_in(default_mode());
+        let _ = router.handle_input(now + p.advertisement_interval, Input::Timer);
+        let _ = router.handle_input(
+            now,
+            ReceivedPacket::advertisement(
+                TEST_VIRTUAL_MAC,
+                TEST_SENDER_IP,
+                200,
+                p.advertisement_interval,
+            )
+            .into(),
+        );
+
+        let stats = router.stats();
+        assert_eq!(stats.transitions, 2, "Backup to Active and back");
+        assert_eq!(stats.advertisements_sent, 2);
+        assert_eq!(stats.advertisements_received, 1);
+    }
+
+    #[test]
+    fn validate_reports_every_config_error() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = Parameters {
+            advertisement_interval: Interval::from_centis(0),
+            ..p.with_interface_addresses(vec![TEST_VIRTUAL_IP_1, TEST_PRIMARY_IP])
+        };
+        assert_eq!(
+            p.validate(),
+            Err(vec![
+                ConfigError::AdvertisementInterval,
+                ConfigError::OwnerAddressNotLocal(TEST_VIRTUAL_IP_2),
+            ])
+        );
+
+        let (_, backup) = router_in(default_mode());
+        let backup = backup.with_interface_addresses(vec![TEST_SENDER_IP]);
+        assert_eq!(
+            RouterSet::new().validate_config(&[backup.clone(), backup]),
+            Err(vec![
+                ConfigError::PrimaryAddressNotLocal(TEST_PRIMARY_IP),
+                ConfigError::PrimaryAddressNotLocal(TEST_PRIMARY_IP),
+                ConfigError::DuplicateVirtualRouter {
+                    interface: TEST_INTERFACE,
+                    vrid: VRID::try_from(1).unwrap()
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn config_policy_forbids_owners() {
+        let policy = ConfigPolicy { forbid_owner: true };
+        let (_, owner) = router_in(Mode::Owner);
+        let (_, backup) = router_in(default_mode());
+        assert_eq!(owner.validate(), Ok(()));
+        assert_eq!(
+            owner.validate_with(policy),
+            Err(vec![ConfigError::OwnerForbidden])
+        );
+        assert_eq!(backup.validate_with(policy), Ok(()));
+
+        let mut set = RouterSet::new().with_config_policy(policy);
+        let now = Timestamp::now();
+        assert_eq!(
+            set.validate_config(&[owner]),
+            Err(vec![ConfigError::OwnerForbidden])
+        );
+        assert!(set.apply_config(now, vec![backup.clone()]).is_ok());
+
+        // An update to a forbidden owner is rejected as a whole, before any router is touched.
+        let other = Parameters {
+            vrid: VRID::try_from(2).unwrap(),
+            ..backup.clone()
+        };
+        let forbidden = Parameters {
+            mode: Mode::Owner,
+            ..backup.clone()
+        };
+        assert!(matches!(
+            set.apply_config(now, vec![forbidden, other]),
+            Err(errors) if errors == [ConfigError::OwnerForbidden]
+        ));
+        assert_eq!(set.len(), 1);
+        assert_eq!(
+            *set.get(TEST_INTERFACE, backup.vrid).unwrap().parameters(),
+            backup
+        );
+    }
+
+    #[test]
+    fn backup_tracks_advertisement_jitter() {
+        let (mut router, p, now) = startup_in(default_mode());
+        let interval = p.advertisement_interval;
+        let mut advertise = |at: Timestamp| {
+            router
+                .handle_input(
+                    at,
+                    ReceivedPacket::advertisement(TEST_VIRTUAL_MAC, TEST_SENDER_IP, 50, interval)
+                        .into(),
+                )
+                .filter_map(|action| match action {
+                    Action::Notify(event) => Some(event),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(advertise(now), vec![]);
+        assert_eq!(advertise(now + interval), vec![]);
+        let late = now + interval + Interval::from_centis(180);
+        assert_eq!(
+            advertise(late),
+            vec![Event::IrregularAdvertisements {
+                sender_ip: TEST_SENDER_IP,
+                jitter: Interval::from_centis(80).into(),
+            }],
+            "it should warn before the Active router times out"
+        );
+        assert_eq!(advertise(late + Interval::from_centis(190)), vec![]);
+
+        let jitter = &router.stats().advertisement_jitter;
+        assert_eq!(jitter.len(), 3);
+        assert_eq!(
+            jitter.percentile(50),
+            Some(Interval::from_centis(80).into())
+        );
+        assert_eq!(
+            jitter.percentile(99),
+            Some(Interval::from_centis(90).into())
+        );
+    }
+
+    #[test]
+    fn router_set_coalesces_nearby_timers() {
+        use std::time::Duration;
+
+        let (_, p) = router_in(default_mode());
+        let with_vrid = |vrid| Parameters {
+            vrid: VRID::try_from(vrid).unwrap(),
+            ..p.clone()
+        };
+        let now = Timestamp::now();
+        let mut set = RouterSet::new();
+        let mut config = Vec::new();
+        for (vrid, offset) in [(1, 0), (2, 5), (3, 30)] {
+            config.push(with_vrid(vrid));
+            let started = now + Duration::from_millis(offset);
+            set.apply_config(started, config.clone()).unwrap().count();
+        }
+
+        let earliest = set.next_timer(now).unwrap();
+        assert_eq!(
+            set.next_coalesced_timer(now, Duration::ZERO),
+            Some(earliest)
+        );
+        assert_eq!(
+            set.next_coalesced_timer(now, Duration::from_millis(10)),
+            Some(earliest + Duration::from_millis(5)),
+            "the timer due 5ms later should share the wakeup, the one 30ms later should not"
+        );
+        assert_eq!(
+            RouterSet::new().next_coalesced_timer(now, Duration::from_millis(10)),
+            None
+        );
+    }
+
+    #[test]
+    fn backup_ignores_shutdown_advertisement_from_unknown_sender() {
+        let (mut router, _, now) = startup_in(default_mode());
+        let interval = Interval::from_secs(1);
+        router
+            .handle_input(
+                now,
+                ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    priority: ReceivedPriority::try_from(200).unwrap(),
+                    max_advertise_interval: interval,
+                }
+                .into(),
+            )
+            .count();
+        let state = router.state().clone();
+
+        let shutdown = |sender_ip| -> Input {
+            ReceivedPacket::ShutdownAdvertisement {
+                sender_ip,
+                max_advertise_interval: interval,
+            }
+            .into()
+        };
+        router
+            .handle_input(now, shutdown(Ipv4Addr::new(10, 0, 0, 99)))
+            .count();
+        assert_eq!(*router.state(), state, "a spoofed abdication is ignored");
+        assert_eq!(router.stats().rejected_shutdown_advertisements, 1);
+
+        router.handle_input(now, shutdown(TEST_SENDER_IP)).count();
+        assert_eq!(
+            *router.state(),
+            State::Backup {
+                active_down_timer: now + 156 * interval / 256,
+                active_adver_interval: interval,
+            },
+            "the Active router itself may abdicate"
+        );
+        assert_eq!(router.stats().rejected_shutdown_advertisements, 1);
+    }
+
+    #[cfg(feature = "test-support")]
+    #[test]
+    fn test_clock_drives_router_timers() {
+        let clock = TestClock::new();
+        let mut router = Router::new(router_in(default_mode()).1);
+        let mut handler = MockHandler::default();
+        router.dispatch(clock.now(), Command::Startup.into(), &mut handler);
+
+        let start = clock.now();
+        let timer = router.next_timer(start);
+        clock.advance(std::time::Duration::from_millis(1));
+        assert!(clock.now() < timer);
+        clock.advance_to(timer);
+        clock.advance_to(start);
+        assert_eq!(clock.now(), timer, "it should not go back in time");
+
+        router.dispatch(clock.now(), Input::Timer, &mut handler);
+        assert!(matches!(router.state(), State::Active { .. }));
+    }
+
+    #[test]
+    fn backup_forgets_the_active_router_on_restart() {
+        let (mut router, _, now) = startup_in(default_mode());
+        let interval = Interval::from_secs(1);
+        router
+            .handle_input(
+                now,
+                ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    priority: ReceivedPriority::try_from(200).unwrap(),
+                    max_advertise_interval: interval,
+                }
+                .into(),
+            )
+            .count();
+        router
+            .handle_input(now, Input::Command(Command::Shutdown))
+            .count();
+        router
+            .handle_input(now, Input::Command(Command::Startup))
+            .count();
+
+        router
+            .handle_input(
+                now,
+                ReceivedPacket::ShutdownAdvertisement {
+                    sender_ip: Ipv4Addr::new(10, 0, 0, 99),
+                    max_advertise_interval: interval,
+                }
+                .into(),
+            )
+            .count();
+        assert_eq!(
+            router.stats().rejected_shutdown_advertisements,
+            0,
+            "the Active router heard before the restart is no longer tracked"
+        );
+    }
+
+    #[cfg(feature = "test-support")]
+    #[test]
+    fn test_clock_drives_io_drivers() {
+        use transport::{MemoryTransport, Transport};
+
+        let clock = TestClock::new();
+        let mut queue = InputQueue::with_clock(clock.clone());
+        let sender = queue.sender();
+        let start = clock.now();
+        std::thread::spawn(move || sender.push(Input::Timer))
+            .join()
+            .unwrap();
+        clock.advance(std::time::Duration::from_secs(1));
+        queue.push(Input::Timer);
+        assert_eq!(
+            queue
+                .drain(clock.now())
+                .map(|(at, _)| at)
+                .collect::<Vec<_>>(),
+            vec![start, start + Interval::from_secs(1)]
+        );
+
+        let (left, right) = MemoryTransport::pair();
+        let mut left = left.with_clock(clock.clone());
+        let mut right = right.with_clock(clock.clone());
+        left.send_frame(&[1]).unwrap();
+        let mut buffer = [0; 1];
+        let deadline = clock.now() + Interval::from_secs(60);
+        assert_eq!(right.recv_frame(&mut buffer, deadline).unwrap(), Some(1));
+        clock.advance_to(deadline);
+        assert_eq!(
+            right.recv_frame(&mut buffer, deadline).unwrap(),
+            None,
+            "it should not wait once the clock passed the deadline"
+        );
+    }
+
+    #[test]
+    fn active_defends_addresses_against_arp_probes() {
+        let (_, p) = router_in(Mode::Owner);
+        for defend in [true, false] {
+            let mut router = Router::new(p.clone().with_defend_addresses(defend));
+            let now = Timestamp::now();
+            router.handle_input(now, Command::Startup.into()).count();
+
+            let actions = router
+                .handle_input(
+                    now,
+                    ReceivedPacket::RequestARP {
+                        sender_mac: TEST_SENDER_MAC,
+                        sender_ip: Ipv4Addr::UNSPECIFIED,
+                        target_ip: TEST_VIRTUAL_IP_1,
+                    }
+                    .into(),
+                )
+                .collect::<Vec<_>>();
+
+            let defense = vec![send(SendPacket::ReplyARP {
+                sender_mac: p.mac_address(),
+                sender_ip: TEST_VIRTUAL_IP_1,
+                target_mac: TEST_SENDER_MAC,
+                target_ip: Ipv4Addr::UNSPECIFIED,
+                broadcast: false,
+            })];
+            match defend {
+                true => assert_eq!(actions, defense),
+                false => assert_eq!(actions, vec![], "the probe should go unanswered"),
+            }
+        }
+    }
+
+    #[test]
+    fn router_set_detects_misused_routers() {
+        let (_, p1) = router_in(Mode::Owner);
+        let other_ip = Ipv4Addr::new(3, 3, 3, 3);
+        let p2 = Parameters {
+            vrid: VRID::try_from(2).unwrap(),
+            virtual_addresses: vec![other_ip].try_into().unwrap(),
+            ..p1.clone()
+        };
+        let now = Timestamp::now();
+        let mut set = RouterSet::new();
+        set.apply_config(now, vec![p1.clone(), p2.clone()])
+            .unwrap()
+            .count();
+        assert_eq!(set.check_invariants(), Ok(()));
+
+        let overlapping = Parameters {
+            virtual_addresses: vec![TEST_VIRTUAL_IP_1, other_ip].try_into().unwrap(),
+            ..p1.clone()
+        };
+        set.get_mut(TEST_INTERFACE, p1.vrid)
+            .unwrap()
+            .update_parameters(now, overlapping)
+            .unwrap()
+            .count();
+        assert_eq!(
+            set.check_invariants(),
+            Err(vec![InvariantViolation::DuplicateActiveAddress {
+                interface: TEST_INTERFACE,
+                address: other_ip,
+            }])
+        );
+
+        set.get_mut(TEST_INTERFACE, p1.vrid)
+            .unwrap()
+            .update_parameters(now, p2.clone())
+            .unwrap()
+            .count();
+        assert_eq!(
+            set.check_invariants(),
+            Err(vec![
+                InvariantViolation::KeyMismatch {
+                    interface: TEST_INTERFACE,
+                    vrid: p1.vrid,
+                },
+                InvariantViolation::DuplicateActiveAddress {
+                    interface: TEST_INTERFACE,
+                    address: other_ip,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn priority_policy_lowers_priority() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let degraded = Arc::new(AtomicBool::new(false));
+        let signal = degraded.clone();
+        let (_, p) = router_in(default_mode());
+        let interval = p.advertisement_interval;
+        let mut router = Router::new(p).with_priority_policy(move |configured: Priority| {
+            match signal.load(Ordering::Relaxed) {
+                true => configured.saturating_sub(50),
+                false => configured,
+            }
+        });
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+        let now = now + Interval::from_secs(10);
+        router.handle_input(now, Input::Timer).count();
+
+        let advertisement = || -> Input {
+            ReceivedPacket::Advertisement {
+                sender_mac: TEST_VIRTUAL_MAC,
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(80).unwrap(),
+                max_advertise_interval: interval,
+            }
+            .into()
+        };
+        router.handle_input(now, advertisement()).count();
+        assert!(matches!(router.state(), State::Active { .. }));
+
+        degraded.store(true, Ordering::Relaxed);
+        router.handle_input(now, advertisement()).count();
+        assert_eq!(
+            router.parameters().mode.priority(),
+            NonZeroU8::new(50).unwrap()
+        );
+        assert!(
+            matches!(router.state(), State::Backup { .. }),
+            "it should lose the election with the lowered priority"
+        );
+    }
+
+    #[test]
+    fn ttl_check_is_relaxed_for_unicast_peers_only() {
+        let peer = Ipv4Addr::new(192, 0, 2, 1);
+        let local = Ipv4Addr::new(198, 51, 100, 1);
+        let mut strict = wire::ParseContext::default().with_unicast_peers(vec![peer]);
+        assert!(strict.check_ttl(peer, local, wire::TTL));
+        assert!(!strict.check_ttl(peer, local, 254));
+
+        let mut relaxed = strict.with_insecure_accept_any_ttl_from_unicast_peers(true);
+        assert!(relaxed.check_ttl(peer, local, 254));
+        assert!(!relaxed.check_ttl(TEST_SENDER_IP, local, 254));
+        assert!(
+            !relaxed.check_ttl(peer, VRRP_MULTICAST_GROUP, 254),
+            "multicast advertisements must still have TTL 255"
+        );
+        assert_eq!(relaxed.relaxed_ttl_acceptances, 1);
+    }
+
+    #[test]
+    fn compat_quirks_are_opt_in_and_counted() {
+        let peer = Ipv4Addr::new(192, 0, 2, 1);
+        let local = Ipv4Addr::new(198, 51, 100, 1);
+        let compat = compat::Compat::default()
+            .with_accept_any_ttl_from(vec![peer])
+            .with_accept_checksum_without_pseudo_header(true);
+
+        let mut strict = wire::ParseContext::default();
+        let mut lenient = wire::ParseContext::default().with_compat(compat);
+        assert!(!strict.check_ttl(peer, local, 64));
+        assert!(lenient.check_ttl(peer, local, 64));
+        assert!(!lenient.check_ttl(TEST_SENDER_IP, local, 64));
+        assert_eq!(lenient.compat_ttl_acceptances, 1);
+
+        let (_, parameters) = router_in(default_mode());
+        let mut message = wire::encode(&parameters, WirePriority::from(100)).unwrap();
+        let checksum = wire::checksum(peer, VRRP_MULTICAST_GROUP, &message);
+        message[6..8].copy_from_slice(&checksum.to_be_bytes());
+        assert!(strict.check_checksum(peer, VRRP_MULTICAST_GROUP, &message));
+        assert!(lenient.check_checksum(peer, VRRP_MULTICAST_GROUP, &message));
+        assert_eq!(lenient.compat_checksum_acceptances, 0);
+
+        // VRRPv2 checksums cover the message alone.
+        message[6..8].copy_from_slice(&[0, 0]);
+        let sum = message
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as u32)
+            .sum::<u32>();
+        let sum = (sum & 0xffff) + (sum >> 16);
+        let checksum = !((sum & 0xffff) + (sum >> 16)) as u16;
+        message[6..8].copy_from_slice(&checksum.to_be_bytes());
+        assert!(!strict.check_checksum(peer, VRRP_MULTICAST_GROUP, &message));
+        assert!(lenient.check_checksum(peer, VRRP_MULTICAST_GROUP, &message));
+        assert_eq!(lenient.compat_checksum_acceptances, 1);
+
+        let zero_interval = || {
+            ReceivedPacket::advertisement(
+                TEST_SENDER_MAC,
+                TEST_SENDER_IP,
+                200,
+                Interval::from_centis(0),
+            )
+        };
+        let (mut router, _, now) = startup_in(default_mode());
+        let _ = router.handle_input(now, zero_interval().into());
+        assert_eq!(router.stats().compat_zero_intervals, 0);
+
+        let mut router = Router::new(parameters.with_zero_interval_as_one_centi(true));
+        let _ = router.handle_input(now, Command::Startup.into());
+        let _ = router.handle_input(now, zero_interval().into());
+        assert_eq!(router.stats().compat_zero_intervals, 1);
+        assert_eq!(
+            router
+                .peers()
+                .next()
+                .map(|peer| peer.advertisement_interval),
+            Some(Interval::from_centis(1))
+        );
+    }
+
+    #[test]
+    fn actions_and_packets_display_for_operators() {
+        let (router, p) = router_in(default_mode());
+        assert_eq!(
+            send(SendPacket::advertisement(p.clone())).to_string(),
+            format!(
+                "send ADVERTISEMENT vrid=1 prio=100 interval=1s on interface {}",
+                TEST_INTERFACE.0
+            )
+        );
+        assert_eq!(
+            ReceivedPacket::<Ipv4Addr>::RequestARP {
+                sender_mac: TEST_SENDER_MAC,
+                sender_ip: TEST_SENDER_IP,
+                target_ip: TEST_VIRTUAL_IP_1,
+            }
+            .to_string(),
+            format!("ARP who-has {TEST_VIRTUAL_IP_1} tell {TEST_SENDER_IP} ({TEST_SENDER_MAC})")
+        );
+        assert_eq!(router.state().to_string(), "Initialized");
+        assert_eq!(
+            State::<Ipv4Addr>::Backup {
+                active_down_timer: Timestamp::now(),
+                active_adver_interval: Interval::from_centis(150),
+            }
+            .to_string(),
+            "Backup active_interval=1.5s"
+        );
+    }
+
+    #[cfg(feature = "pnet")]
+    #[test]
+    fn received_packet_from_pnet_frames() {
+        use pnet_packet::ethernet::EthernetPacket;
+
+        let (_, p) = router_in(default_mode());
+        let frame = |ttl: u8, protocol: u8, payload: &[u8]| -> Vec<u8> {
+            let mut frame = Vec::new();
+            frame.extend_from_slice(&[0x01, 0x00, 0x5e, 0, 0, 0x12]);
+            frame.extend_from_slice(&TEST_VIRTUAL_MAC.octets());
+            frame.extend_from_slice(&0x0800u16.to_be_bytes());
+            frame.extend_from_slice(&[0x45, 0]);
+            frame.extend_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+            frame.extend_from_slice(&[0, 0, 0, 0, ttl, protocol, 0, 0]);
+            frame.extend_from_slice(&TEST_SENDER_IP.octets());
+            frame.extend_from_slice(&VRRP_MULTICAST_GROUP.octets());
+            frame.extend_from_slice(payload);
+            frame
+        };
+        let parse = |frame: &[u8]| ReceivedPacket::try_from(&EthernetPacket::new(frame).unwrap());
+
+        let advertisement = wire::encode(&p, Priority::default().into()).unwrap();
+        assert_eq!(
+            parse(&frame(255, 112, &advertisement)),
+            Ok(ReceivedPacket::Advertisement {
+                sender_mac: TEST_VIRTUAL_MAC,
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(100).unwrap(),
+                max_advertise_interval: p.advertisement_interval,
+            })
+        );
+        assert_eq!(
+            parse(&frame(64, 112, &advertisement)),
+            Err(PacketError::Ttl(64))
+        );
+        assert_eq!(
+            parse(&frame(64, 6, &[0, 1, 0, 22])),
+            Ok(ReceivedPacket::IP {
+                target_mac: MacAddr::new(0x01, 0x00, 0x5e, 0, 0, 0x12),
+                target_ip: VRRP_MULTICAST_GROUP,
+                protocol: 6,
+                destination_port: Some(22),
+            })
+        );
+    }
+
+    #[test]
+    fn backup_does_not_preempt_during_startup_grace() {
+        let (_, p) = router_in(default_mode().with_priority(Priority::try_from(200).unwrap()));
+        let p = p.with_startup_grace(Interval::from_secs(30));
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+
+        let advertisement = || -> Input {
+            ReceivedPacket::Advertisement {
+                sender_mac: TEST_VIRTUAL_MAC,
+                sender_ip: TEST_SENDER_IP,
+                priority: ReceivedPriority::try_from(100).unwrap(),
+                max_advertise_interval: p.advertisement_interval,
+            }
+            .into()
+        };
+        let during = now + Interval::from_secs(1);
+        let actions = router
+            .handle_input(during, advertisement())
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            actions[..],
+            [Action::Notify(Event::PreemptSuppressed { .. })]
+        ));
+        let armed = State::Backup {
+            active_down_timer: during + p.active_down_interval(p.advertisement_interval),
+            active_adver_interval: p.advertisement_interval,
+        };
+        assert_eq!(*router.state(), armed, "it should follow the incumbent");
+
+        let after = now + Interval::from_secs(31);
+        router.handle_input(after, advertisement()).count();
+        assert_eq!(
+            *router.state(),
+            armed,
+            "it should let the down timer run out to preempt"
+        );
+    }
+
+    #[test]
+    fn interval_constructors_and_arithmetic_are_checked() {
+        assert_eq!(Interval::try_from_centis(100), Ok(Interval::from_secs(1)));
+        assert_eq!(Interval::try_from_secs(40), Ok(Interval::from_secs(40)));
+        for (invalid, centiseconds) in [
+            (Interval::try_from_centis(0), 0),
+            (
+                Interval::try_from_centis(Interval::MAX_WIRE_CENTIS + 1),
+                4096,
+            ),
+            (Interval::try_from_secs(41), 4100),
+            (Interval::try_from_secs(u16::MAX), 6_553_500),
+        ] {
+            assert_eq!(invalid, Err(Error::AdvertisementInterval(centiseconds)));
+        }
+        assert_eq!(Interval::MAX.as_centis(), Interval::MAX_WIRE_CENTIS as u32);
+
+        let huge = Interval::from_secs(u16::MAX)
+            .saturating_mul(u32::MAX)
+            .saturating_mul(u32::MAX);
+        assert_eq!(huge.checked_mul(2), None);
+        assert_eq!(huge.checked_add(huge), None);
+        assert_eq!(huge + huge, huge.saturating_add(huge));
+        assert_eq!(u16::MAX * huge, huge.saturating_mul(u16::MAX as u32));
+    }
+
+    #[test]
+    fn advertisements_carry_their_addressing() {
+        let (_, p) = router_in(Mode::Owner);
+        assert_eq!(
+            SendPacket::advertisement(p.clone()),
+            SendPacket::Advertisement {
+                parameters: p.clone().into(),
+                source_ip: TEST_VIRTUAL_IP_1,
+                destination: Destination::Multicast,
+            }
+        );
+
+        let peers = vec![Ipv4Addr::new(192, 0, 2, 2), Ipv4Addr::new(192, 0, 2, 3)];
+        let p = p
+            .with_source_ip(TEST_PRIMARY_IP)
+            .with_unicast_peers(peers.clone());
+        let mut router = Router::new(p.clone());
+        let actions = router
+            .handle_input(Timestamp::now(), Command::Startup.into())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions[2],
+            send(SendPacket::Advertisement {
+                parameters: p.into(),
+                source_ip: TEST_PRIMARY_IP,
+                destination: Destination::Unicast(peers),
+            })
+        );
+    }
+
+    #[test]
+    fn router_set_paces_gratuitous_arps_per_interface() {
+        use std::time::Duration;
+
+        let (_, p) = router_in(default_mode());
+        let config = [1, 2]
+            .map(|vrid| Parameters {
+                vrid: VRID::try_from(vrid).unwrap(),
+                virtual_addresses: vec![
+                    Ipv4Addr::new(10, 1, vrid, 1),
+                    Ipv4Addr::new(10, 1, vrid, 2),
+                ]
+                .try_into()
+                .unwrap(),
+                ..p.clone()
+            })
+            .to_vec();
+        let pacing = Duration::from_millis(10);
+        let mut set = RouterSet::new().with_garp_pacing(pacing);
+        let now = Timestamp::now();
+        set.apply_config(now, config).unwrap().count();
+
+        let garps = |handler: &MockHandler| {
+            handler
+                .sent
+                .iter()
+                .filter(|sent| sent.starts_with("GratuitousARP"))
+                .count()
+        };
+        let mut handler = MockHandler::default();
+        let mut now = set.next_timer(now).unwrap();
+        set.handle_timers(now, &mut handler);
+        assert_eq!(handler.activated, 2, "both routers should become Active");
+        assert_eq!(garps(&handler), 1, "only one GARP should go out at once");
+
+        for sent in 2..=4 {
+            let next = set.next_timer(now).unwrap();
+            assert_eq!(next, now + pacing);
+            now = next;
+            set.handle_timers(now, &mut handler);
+            assert_eq!(garps(&handler), sent);
+        }
+    }
+
+    #[test]
+    fn router_set_drops_paced_garps_of_preempted_routers() {
+        use std::time::Duration;
+
+        let (_, p) = router_in(default_mode());
+        let config = [1, 2]
+            .map(|vrid| Parameters {
+                vrid: VRID::try_from(vrid).unwrap(),
+                virtual_addresses: vec![
+                    Ipv4Addr::new(10, 1, vrid, 1),
+                    Ipv4Addr::new(10, 1, vrid, 2),
+                ]
+                .try_into()
+                .unwrap(),
+                ..p.clone()
+            })
+            .to_vec();
+        let pacing = Duration::from_millis(10);
+        let mut set = RouterSet::new().with_garp_pacing(pacing);
+        let now = Timestamp::now();
+        set.apply_config(now, config.clone()).unwrap().count();
+
+        let mut handler = MockHandler::default();
+        let mut now = set.next_timer(now).unwrap();
+        set.handle_timers(now, &mut handler);
+        assert_eq!(handler.sent.len(), 3, "two advertisements and one GARP");
+
+        let preempted = config[0].vrid;
+        let advertisement = ReceivedPacket::advertisement(
+            TEST_SENDER_MAC,
+            TEST_SENDER_IP,
+            254,
+            p.advertisement_interval,
+        );
+        set.dispatch(
+            now,
+            TEST_INTERFACE,
+            preempted,
+            advertisement.into(),
+            &mut handler,
+        );
+        assert_eq!(handler.deactivated, 1);
+
+        handler.sent.clear();
+        while let Some(next) = set
+            .next_timer(now)
+            .filter(|next| *next <= now + 10 * pacing)
+        {
+            now = next;
+            set.handle_timers(now, &mut handler);
+        }
+        let garps = handler
+            .sent
+            .iter()
+            .filter(|sent| sent.starts_with("GratuitousARP"))
+            .collect::<Vec<_>>();
+        let preempted_mac = format!("{:?}", preempted.into_mac_address());
+        assert_eq!(
+            garps.len(),
+            2,
+            "only the GARPs of the Active router should remain"
+        );
+        assert!(
+            garps.iter().all(|garp| !garp.contains(&preempted_mac)),
+            "the preempted router must not claim its addresses: {garps:?}"
+        );
+    }
+
+    #[test]
+    fn router_set_spreads_advertisements_across_the_interval() {
+        use std::time::Duration;
+
+        let (_, p) = router_in(default_mode());
+        let config = [1, 2, 3, 4]
+            .map(|vrid| Parameters {
+                vrid: VRID::try_from(vrid).unwrap(),
+                virtual_addresses: vec![
+                    Ipv4Addr::new(10, 1, vrid, 1),
+                    Ipv4Addr::new(10, 1, vrid, 2),
+                ]
+                .try_into()
+                .unwrap(),
+                ..p.clone()
+            })
+            .to_vec();
+        let mut set = RouterSet::new().with_advertisement_pacing();
+        let now = Timestamp::now();
+        set.apply_config(now, config).unwrap().count();
+
+        let mut handler = MockHandler::default();
+        let now = set.next_timer(now).unwrap();
+        set.handle_timers(now, &mut handler);
+        assert_eq!(handler.activated, 4);
+
+        let mut timers: Vec<_> = set
+            .iter()
+            .map(|router| match router.state() {
+                State::Active { adver_timer, .. } => *adver_timer,
+                state => panic!("unexpected state {state:?}"),
+            })
+            .collect();
+        timers.sort();
+        let interval = Duration::from(p.advertisement_interval);
+        assert!(timers[0] > now && timers[3] <= now + interval);
+        for pair in timers.windows(2) {
+            assert_eq!(pair[1].saturating_duration_since(pair[0]), interval / 4);
+        }
+
+        let stats = set.interface_stats(p.interface);
+        assert_eq!((stats.routers, stats.active), (4, 4));
+        assert_eq!(stats.advertisements_sent, 4);
+        assert_eq!(stats.advertisements_per_second, 4.0);
+    }
+
+    #[test]
+    fn commands_are_idempotent_and_restart_is_one_stream() {
+        let commands = |router: &mut Router, now, commands: &[Command]| {
+            commands
+                .iter()
+                .map(|&command| router.handle_input(now, command.into()).count())
+                .collect::<Vec<_>>()
+        };
+        let now = Timestamp::now();
+
+        let (_, p) = router_in(default_mode());
+        let mut backup = Router::new(p);
+        let counts = commands(
+            &mut backup,
+            now,
+            &[Command::Shutdown, Command::Startup, Command::Startup],
+        );
+        assert_eq!(counts, vec![0, 1, 0]);
+        assert!(matches!(backup.state(), State::Backup { .. }));
+        let restart: Vec<_> = backup
+            .handle_input(now, Command::Restart.into())
+            .map(|action| format!("{action:?}"))
+            .collect();
+        assert_eq!(restart.len(), 2);
+        assert!(restart[0].starts_with("LeaveMulticast"));
+        assert!(restart[1].starts_with("JoinMulticast"));
+        assert_eq!(
+            commands(&mut backup, now, &[Command::Shutdown, Command::Shutdown]),
+            vec![1, 0]
+        );
+
+        let (_, p) = router_in(Mode::Owner);
+        let mut owner = Router::new(p);
+        assert_eq!(
+            commands(&mut owner, now, &[Command::Startup, Command::Startup])[1],
+            0
+        );
+        let restart: Vec<_> = owner
+            .handle_input(now, Command::Restart.into())
+            .map(|action| format!("{action:?}"))
+            .collect();
+        let leave = restart.iter().position(|a| a.starts_with("LeaveMulticast"));
+        let join = restart.iter().position(|a| a.starts_with("JoinMulticast"));
+        assert!(restart[0].contains("ShutdownAdvertisement"));
+        assert!(leave < join && leave.is_some());
+        assert!(matches!(owner.state(), State::Active { .. }));
+    }
+
+    #[test]
+    fn vrid_allocator_suggests_free_vrids() {
+        let (_, p) = router_in(default_mode());
+        let mut allocator = VridAllocator::from_parameters([&p]).unwrap();
+        let other = InterfaceId(2);
+
+        assert!(allocator.is_used(p.interface, p.vrid));
+        assert_eq!(
+            allocator.reserve(p.interface, p.vrid),
+            Err(AllocationError::Collision {
+                interface: p.interface,
+                vrid: p.vrid
+            })
+        );
+        let vrid = allocator.allocate(p.interface).unwrap();
+        assert_ne!(vrid, p.vrid);
+        assert_eq!(allocator.suggest(other), Ok(VRID::try_from(1).unwrap()));
+
+        for _ in 0..255 {
+            allocator.allocate(other).unwrap();
+        }
+        assert_eq!(
+            allocator.allocate(other),
+            Err(AllocationError::Exhausted { interface: other })
+        );
+        assert!(allocator.release(other, vrid));
+        assert!(!allocator.release(other, vrid));
+        assert_eq!(allocator.suggest(other), Ok(vrid));
+    }
+
+    #[test]
+    fn router_exposes_its_identity() {
+        let (router, p) = router_in(default_mode());
+        assert_eq!(router.vrid(), p.vrid);
+        assert_eq!(router.mac_address(), p.vrid.into_mac_address());
+        assert_eq!(router.virtual_addresses(), &p.virtual_addresses);
+    }
+
+    #[test]
+    fn accept_is_deferred_while_takeover_settles() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = p.with_accept_settle(Interval::from_secs(2));
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        let _ = router.handle_input(now, Command::Startup.into());
+
+        let mut route = |now| {
+            router
+                .handle_input(
+                    now,
+                    ReceivedPacket::IP {
+                        target_mac: p.mac_address(),
+                        target_ip: TEST_VIRTUAL_IP_1,
+                        protocol: 6,
+                        destination_port: Some(80),
+                    }
+                    .into(),
+                )
+                .map(|action| match action {
+                    Action::Route(decision) => decision,
+                    action => panic!("unexpected {action:?}"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(route(now), vec![RoutePacket::Reject]);
+        assert_eq!(
+            route(now + Interval::from_secs(2)),
+            vec![RoutePacket::Accept]
+        );
+    }
+
+    #[test]
+    fn memory_transport_carries_frames_until_the_deadline() {
+        use transport::{MemoryTransport, Transport};
+
+        let (mut left, mut right) = MemoryTransport::pair();
+        let mut buffer = [0; 4];
+        left.send_frame(&[1, 2, 3, 4, 5]).unwrap();
+
+        let deadline = Timestamp::now() + Interval::from_centis(1);
+        assert_eq!(right.recv_frame(&mut buffer, deadline).unwrap(), Some(4));
+        assert_eq!(buffer, [1, 2, 3, 4]);
+        assert_eq!(right.recv_frame(&mut buffer, deadline).unwrap(), None);
+        assert_eq!(left.recv_frame(&mut buffer, deadline).unwrap(), None);
+    }
+
+    #[test]
+    fn capture_filter_matches_configured_routers() {
+        let (_, p) = router_in(default_mode());
+        let mut set = RouterSet::new();
+        let _ = set.apply_config(Timestamp::now(), vec![p]);
+
+        let filter = set.capture_filter(TEST_INTERFACE);
+        assert_eq!(
+            filter.expression(),
+            "(ip proto 112 and (ip[((ip[0] & 0xf) << 2) + 1] = 1)) \
+             or (arp and (host 1.1.1.1 or host 2.2.2.2))"
+        );
+        let program = filter.program();
+        assert_eq!(program.len(), 25);
+        assert_eq!(
+            program.last().map(|i| i.k),
+            Some(0),
+            "it should drop the rest"
+        );
+
+        let other = set.capture_filter(InterfaceId(TEST_INTERFACE.0 + 1));
+        assert_eq!(other.expression(), "less 0");
+    }
+
+    #[test]
+    fn active_advertises_lowered_priority_at_once() {
+        let (mut router, p, now) = active_in(default_mode());
+        let lowered = |priority| {
+            p.clone().with_mode(
+                default_mode()
+                    .with_priority(Priority::try_from(priority).unwrap())
+                    .into(),
+            )
+        };
+
+        let actions = router.update_parameters(now, lowered(50)).unwrap().count();
+        assert_eq!(
+            actions, 0,
+            "it should wait for the next advertisement by default"
+        );
+
+        let p = lowered(40).with_advertise_priority_drop(true);
+        let actions = router
+            .update_parameters(now, p.clone())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(actions, vec![send(SendPacket::advertisement(p.clone()))]);
+        assert!(
+            matches!(router.state(), State::Active { adver_timer, .. } if *adver_timer == now + p.advertisement_interval)
+        );
+
+        let raised = lowered(60).with_advertise_priority_drop(true);
+        assert_eq!(router.update_parameters(now, raised).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn router_tracks_peers() {
+        let (mut router, p, now) = startup_in(default_mode());
+        let advertisement = |sender_ip, priority| {
+            Input::from(ReceivedPacket::advertisement(
+                TEST_SENDER_MAC,
+                sender_ip,
+                priority,
+                Interval::from_secs(2),
+            ))
+        };
+
+        let _ = router.handle_input(now, advertisement(TEST_SENDER_IP, 200));
+        let later = now + Interval::from_secs(1);
+        let _ = router.handle_input(later, advertisement(TEST_SENDER_IP, 150));
+        let _ = router.handle_input(later, advertisement(TEST_VIRTUAL_IP_2, 0));
+        let _ = router.handle_input(later, advertisement(p.primary_ip(), 100));
+
+        let peers = router.peers().copied().collect::<Vec<_>>();
+        assert_eq!(
+            peers,
+            vec![
+                Peer {
+                    primary_ip: TEST_VIRTUAL_IP_2,
+                    sender_mac: None,
+                    priority: None,
+                    last_seen: later,
+                    advertisements: 1,
+                    advertisement_interval: Interval::from_secs(2),
+                },
+                Peer {
+                    primary_ip: TEST_SENDER_IP,
+                    sender_mac: Some(TEST_SENDER_MAC),
+                    priority: Some(ReceivedPriority::try_from(150).unwrap()),
+                    last_seen: later,
+                    advertisements: 2,
+                    advertisement_interval: Interval::from_secs(2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn late_packet_is_evaluated_when_received() {
+        let (mut router, _, now) = startup_in(default_mode());
+        let advertisement = ReceivedPacket::advertisement(
+            TEST_SENDER_MAC,
+            TEST_SENDER_IP,
+            200,
+            Interval::from_secs(1),
+        );
+        let down_timer = |router: &Router| match router.state() {
+            State::Backup {
+                active_down_timer, ..
+            } => *active_down_timer,
+            state => panic!("unexpected {state:?}"),
+        };
+        let received_at = now + Interval::from_secs(1);
+        let _ = router.handle_input(received_at, advertisement.clone().into());
+        let expected = down_timer(&router);
+
+        let (mut router, _, _) = startup_in(default_mode());
+        let late = Input::LatePacket {
+            packet: advertisement.clone(),
+            received_at,
+        };
+        let _ = router.handle_input(now + Interval::from_secs(5), late);
+        assert_eq!(
+            down_timer(&router),
+            expected,
+            "it arrived before the down timer expired"
+        );
+
+        let (mut router, _, _) = startup_in(default_mode());
+        let early = Input::LatePacket {
+            packet: advertisement,
+            received_at: now + Interval::from_secs(3),
+        };
+        let _ = router.handle_input(received_at, early);
+        assert_eq!(
+            down_timer(&router),
+            expected,
+            "it should not be evaluated in the future"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "model")]
+    fn model_elections_converge() {
+        use model::{Model, Violation};
+
+        let (_, p) = router_in(default_mode());
+        let backup = |primary_ip, priority| {
+            let mode = BackupMode::with_primary_ip(primary_ip)
+                .with_priority(Priority::try_from(priority).unwrap());
+            p.clone().with_mode(mode.into())
+        };
+        let pair = vec![p.clone(), backup(TEST_SENDER_IP, 200)];
+        assert!(Model::new(pair).check().unwrap() > 1);
+
+        let three = vec![
+            p.clone(),
+            backup(TEST_SENDER_IP, 100),
+            p.clone().with_mode(Mode::Owner),
+        ];
+        assert!(Model::new(three).with_depth(6).check().is_ok());
+
+        // Two routers sharing a primary address both believe they are Active.
+        let clash = Model::new(vec![p.clone(), p]).with_depth(2).check();
+        assert!(matches!(
+            clash.map_err(|counterexample| counterexample.violation),
+            Err(Violation::DualActive(_))
+        ));
+    }
+
+    #[test]
+    fn owner_conflict_policy() {
+        let outcome = |policy| {
+            let (_, p) = router_in(Mode::Owner);
+            let mut router = Router::new(p.with_owner_conflict(policy));
+            let now = Timestamp::now();
+            let _ = router.handle_input(now, Command::Startup.into());
+            let actions = router
+                .handle_input(
+                    now,
+                    ReceivedPacket::advertisement(
+                        TEST_SENDER_MAC,
+                        Ipv4Addr::new(0, 0, 0, 1),
+                        255,
+                        Interval::from_secs(1),
+                    )
+                    .into(),
+                )
+                .map(|action| format!("{action:?}"))
+                .collect::<Vec<_>>();
+            assert!(actions.last().unwrap().contains("OwnerConflict"));
+            (router.state().name(), actions.len())
+        };
+
+        assert_eq!(outcome(OwnerConflict::TieBreak), ("Active", 2));
+        assert_eq!(outcome(OwnerConflict::Defend), ("Active", 2));
+        assert_eq!(outcome(OwnerConflict::AlarmOnly), ("Active", 1));
+        assert_eq!(outcome(OwnerConflict::Yield).0, "Backup");
+    }
+
+    #[test]
+    fn owner_stepping_down_chains_every_action() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = p
+            .with_owner_conflict(OwnerConflict::Yield)
+            .with_logging(true)
+            .with_notify_state_changes(true)
+            .with_flap_damping(FlapDamping {
+                max_transitions: 0,
+                window: Interval::from_secs(60),
+                hold_down: Interval::from_secs(120),
+            });
+        let mut router = Router::new(p);
+        let now = Timestamp::now();
+        let _ = router.handle_input(now, Command::Startup.into());
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::advertisement(
+                    TEST_SENDER_MAC,
+                    Ipv4Addr::new(0, 0, 0, 1),
+                    255,
+                    Interval::from_secs(2),
+                )
+                .into(),
+            )
+            .map(|action| match action {
+                Action::Notify(event) => format!("{event:?}"),
+                action => format!("{action:?}"),
+            })
+            .collect::<Vec<_>>();
+        let kinds = [
+            "Deactivate",
+            "FlapDetected",
+            "Log",
+            "OwnerConflict",
+            "ConfigConflict",
+            "StateChanged",
+        ];
+        assert_eq!(actions.len(), kinds.len(), "{actions:?}");
+        for (action, kind) in actions.iter().zip(kinds) {
+            assert!(action.starts_with(kind), "{action} should be {kind}");
+        }
+    }
+
+    #[test]
+    fn actions_outlive_the_router_that_produced_them() {
+        let (mut router, p) = router_in(Mode::Owner);
+        let actions = router.handle_input(Timestamp::now(), Command::Startup.into());
+        let updated = p.clone().with_garp_repeat(3);
+        router
+            .update_parameters(Timestamp::now(), updated)
+            .unwrap()
+            .for_each(drop);
+        drop(router);
+
+        let actions = actions.collect::<Vec<_>>();
+        assert_eq!(actions[2], send(SendPacket::advertisement(p)));
+    }
+
+    #[test]
+    fn shutdown_advertisement_is_repeated_before_deactivating() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = p.with_shutdown_repeat(2);
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+
+        let actions = router
+            .handle_input(now, Command::Shutdown.into())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![send(SendPacket::shutdown_advertisement(p.clone()))]
+        );
+        let Action::Send { packet, .. } = &actions[0] else {
+            unreachable!()
+        };
+        assert_eq!(
+            packet.max_advertise_interval(),
+            Some(p.advertisement_interval)
+        );
+        let message = wire::encode(&p, WirePriority::Shutdown).unwrap();
+        let advertisement = wire::decode(&message).unwrap();
+        assert_eq!(
+            advertisement.max_advertise_interval,
+            p.advertisement_interval
+        );
+
+        let now = router.next_timer(now);
+        assert_eq!(router.handle_input(now, Input::Timer).count(), 1);
+        assert_eq!(router.state().name(), "ShuttingDown");
+        let now = router.next_timer(now);
+        let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
+        assert_eq!(actions.len(), 3);
+        assert_eq!(
+            actions[1],
+            Action::Deactivate {
+                interface: TEST_INTERFACE
+            }
+        );
+        assert_eq!(router.state(), &State::Initialized);
+        assert_eq!(router.stats().advertisements_sent, 4);
+    }
+
+    #[test]
+    fn shutting_down_router_still_answers_for_its_addresses() {
+        let (_, p) = router_in(Mode::Owner);
+        let p = p.with_shutdown_repeat(2);
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+        router.handle_input(now, Command::Shutdown.into()).count();
+        assert_eq!(router.state().name(), "ShuttingDown");
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::RequestARP {
+                    sender_mac: TEST_SENDER_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    target_ip: TEST_VIRTUAL_IP_1,
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![send(SendPacket::ReplyARP {
+                sender_mac: p.mac_address(),
+                sender_ip: TEST_VIRTUAL_IP_1,
+                target_mac: TEST_SENDER_MAC,
+                target_ip: TEST_SENDER_IP,
+                broadcast: false,
+            })]
+        );
+
+        let actions = router
+            .handle_input(
+                now,
+                ReceivedPacket::IP {
+                    target_mac: p.mac_address(),
+                    target_ip: TEST_VIRTUAL_IP_1,
+                    protocol: 6,
+                    destination_port: Some(80),
+                }
+                .into(),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(actions, vec![RoutePacket::Accept.into()]);
+    }
+
+    #[test]
+    fn profiles_set_timings_that_builders_override() {
+        let (_, p) = router_in(default_mode());
+        assert_eq!(p.clone().with_profile(Profile::RfcDefault), p);
+
+        let fast = p.clone().with_profile("fast-failover".parse().unwrap());
+        assert_eq!(fast.advertisement_interval, Interval::from_centis(10));
+        assert_eq!(fast.mode, p.mode);
+        assert!(fast.validate().is_ok());
+
+        let tuned = p.with_profile(Profile::Conservative).with_garp_repeat(0);
+        assert_eq!(tuned.advertisement_interval, Interval::from_secs(2));
+        assert_eq!(tuned.garp_repeat, 0);
+        assert_eq!("slow".parse::<Profile>(), Err(Error::UnknownProfile));
+    }
+
+    #[test]
+    fn history_records_transitions_and_their_causes() {
+        let (mut router, _, now) = active_in(default_mode());
+        let priority = ReceivedPriority::try_from(254).unwrap();
+        router
+            .handle_input(
+                now,
+                ReceivedPacket::Advertisement {
+                    sender_mac: TEST_VIRTUAL_MAC,
+                    sender_ip: TEST_SENDER_IP,
+                    priority,
+                    max_advertise_interval: Interval::from_secs(1),
+                }
+                .into(),
+            )
+            .count();
+        router.handle_input(now, Input::Timer).count();
+
+        let history = router
+            .history()
+            .map(|transition| (transition.from, transition.to, transition.cause))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                (
+                    "Initialized",
+                    "Backup",
+                    TransitionCause::Command(Command::Startup)
+                ),
+                ("Backup", "Active", TransitionCause::Timer),
+                (
+                    "Active",
+                    "Backup",
+                    TransitionCause::Advertisement {
+                        sender_ip: TEST_SENDER_IP,
+                        priority
+                    }
+                ),
+            ]
+        );
+        assert_eq!(router.history().last().unwrap().at, now);
+    }
+
+    #[test]
+    fn arp_replies_can_be_scoped_to_the_subnet() {
+        let scoped = VirtualAddress::try_from((TEST_VIRTUAL_IP_1, 24))
+            .unwrap()
+            .with_arp_subnet_only(true);
+        let addresses = vec![scoped, TEST_VIRTUAL_IP_2.into()];
+        let p = Parameters::new(
+            VRID::try_from(1).unwrap(),
+            addresses.try_into().unwrap(),
+            Mode::Owner,
+        );
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        let activate = router
+            .handle_input(now, Command::Startup.into())
+            .nth(1)
+            .unwrap();
+        assert_eq!(
+            activate.to_string(),
+            "activate interface 0 1.1.1.1/24 2.2.2.2/32"
+        );
+
+        let mut replies = |sender_ip, target_ip| {
+            let request = ReceivedPacket::RequestARP {
+                sender_mac: TEST_SENDER_MAC,
+                sender_ip,
+                target_ip,
+            };
+            router.handle_input(now, request.into()).count()
+        };
+        assert_eq!(replies(Ipv4Addr::new(1, 1, 1, 7), TEST_VIRTUAL_IP_1), 1);
+        assert_eq!(replies(TEST_SENDER_IP, TEST_VIRTUAL_IP_1), 0);
+        assert_eq!(replies(TEST_SENDER_IP, TEST_VIRTUAL_IP_2), 1);
+    }
+
+    #[test]
+    fn mac_tiebreak_resolves_colliding_primary_addresses() {
+        let local_mac = MacAddr(2, 0, 0, 0, 0, 5);
+        let (_, p) = router_in(default_mode());
+        let p = p.with_mac_tiebreak(local_mac);
+        let advertisement = |sender_mac| {
+            Input::from(ReceivedPacket::Advertisement {
+                sender_mac,
+                sender_ip: TEST_PRIMARY_IP,
+                priority: Priority::default().into(),
+                max_advertise_interval: p.advertisement_interval,
+            })
+        };
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+        let now = router.next_timer(now);
+        router.handle_input(now, Input::Timer).count();
+
+        let reflected = router
+            .handle_input(now, advertisement(local_mac))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reflected,
+            vec![Action::Notify(Event::ReflectedAdvertisement {
+                sender_ip: TEST_PRIMARY_IP
+            })]
+        );
+        let lower = router.handle_input(now, advertisement(MacAddr(2, 0, 0, 0, 0, 4)));
+        assert_eq!(
+            lower.collect::<Vec<_>>(),
+            vec![send(SendPacket::advertisement(p.clone()))]
+        );
+        router
+            .handle_input(now, advertisement(MacAddr(2, 0, 0, 0, 0, 6)))
+            .count();
+        assert_eq!(router.state().name(), "Backup");
+    }
+
+    #[test]
+    fn input_queue_orders_inputs_from_several_threads() {
+        let (mut router, _, now) = active_in(Mode::Owner);
+        let mut queue = InputQueue::new();
+        let sender = queue.sender();
+        let later = now + Interval::from_secs(1);
+        std::thread::spawn(move || sender.push_at(later, Command::Shutdown.into()))
+            .join()
+            .unwrap();
+        queue.sender().push_at(now, Input::Timer);
+
+        let drained = queue.drain(later).collect::<Vec<_>>();
+        assert_eq!(
+            drained,
+            vec![(now, Input::Timer), (later, Command::Shutdown.into())]
+        );
+        queue.push(Command::Startup.into());
+        let mut handler = MockHandler::default();
+        assert_eq!(queue.dispatch(later, &mut router, &mut handler), 1);
+        assert_eq!(handler.activated, 0);
+
+        // Nothing is handed out before what was already drained.
+        queue.sender().push_at(now, Input::Timer);
+        assert_eq!(queue.drain(later).next(), Some((later, Input::Timer)));
+    }
+
+    #[cfg(feature = "pcap-dump")]
+    #[test]
+    fn pcap_dump_mirrors_filtered_frames_and_rotates() {
+        use transport::{MemoryTransport, PcapDump, Transport};
+
+        let advertisement = |vrid: u8| {
+            let mut frame = vec![0; 14 + 20 + 8];
+            frame[12..14].copy_from_slice(&[0x08, 0x00]);
+            frame[14] = 0x45;
+            frame[23] = 112;
+            frame[35] = vrid;
+            frame
+        };
+        let path = std::env::temp_dir().join(format!("vrrp-dump-{}.pcap", std::process::id()));
+        let (left, mut right) = MemoryTransport::pair();
+        let mut dump = PcapDump::new(left, &path)
+            .with_vrids(vec![VRID::try_from(1).unwrap()])
+            .with_rotation(100, 2);
+
+        dump.send_frame(&advertisement(1)).unwrap();
+        dump.send_frame(&advertisement(2)).unwrap();
+        dump.send_frame(&[0; 60]).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 24 + 16 + 42);
+
+        dump.switch().set(false);
+        dump.send_frame(&advertisement(1)).unwrap();
+        dump.switch().set(true);
+        right.send_frame(&advertisement(1)).unwrap();
+        let mut buffer = [0; 64];
+        let deadline = Timestamp::now() + Interval::from_secs(1);
+        assert_eq!(dump.recv_frame(&mut buffer, deadline).unwrap(), Some(42));
+
+        let rotated = path.with_extension("pcap.1");
+        assert_eq!(std::fs::metadata(&rotated).unwrap().len(), 24 + 16 + 42);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 24 + 16 + 42);
+        assert!(dump.error().is_none());
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(rotated);
+    }
+
+    #[test]
+    fn wire_priority_round_trips_reserved_values() {
+        assert_eq!(WirePriority::from(0), WirePriority::Shutdown);
+        assert_eq!(WirePriority::from(255), WirePriority::Owner);
+        assert_eq!(
+            WirePriority::from(100),
+            WirePriority::Normal(Priority::default())
+        );
+        for value in [0, 1, 100, 254, 255] {
+            assert_eq!(u8::from(WirePriority::from(value)), value);
+        }
+        assert_eq!(
+            WirePriority::from(ReceivedPriority::Owner255),
+            WirePriority::Owner
+        );
+    }
+
+    #[cfg(feature = "graceful-takeover")]
+    #[test]
+    fn graceful_takeover_waits_for_the_final_advertisement() {
+        // Through the wire format, so the reserved bit is carried across.
+        let receive = |actions: Vec<Action>| -> Vec<Input> {
+            actions
+                .into_iter()
+                .filter_map(|action| match action {
+                    Action::Send { packet, .. } => {
+                        let message = wire::encode_packet(&packet)?.unwrap();
+                        let advertisement = wire::decode(&message).unwrap();
+                        let (SendPacket::Advertisement { parameters, .. }
+                        | SendPacket::ShutdownAdvertisement { parameters, .. }
+                        | SendPacket::TakeoverIntent { parameters, .. }) = packet
+                        else {
+                            return None;
+                        };
+                        let packet = ReceivedPacket::advertisement(
+                            parameters.mac_address(),
+                            parameters.primary_ip(),
+                            advertisement.priority,
+                            advertisement.max_advertise_interval,
+                        )
+                        .with_takeover_intent(advertisement.takeover_intent);
+                        Some(packet.into())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        let (_, p) = router_in(default_mode());
+        let p = p.with_graceful_takeover(true);
+        let mut incumbent = Router::new(p.clone());
+        let mut claimer = Router::new(
+            p.clone().with_mode(
+                BackupMode::with_primary_ip(TEST_SENDER_IP)
+                    .with_priority(Priority::try_from(200).unwrap())
+                    .into(),
+            ),
+        );
+        let now = Timestamp::now();
+        incumbent.handle_input(now, Command::Startup.into()).count();
+        let now = now + Interval::from_secs(10);
+        claimer.handle_input(now, Command::Startup.into()).count();
+        let advertisement = receive(incumbent.handle_input(now, Input::Timer).collect());
+        assert!(matches!(incumbent.state(), State::Active { .. }));
+
+        let intent = receive(
+            claimer
+                .handle_input(now, advertisement[0].clone())
+                .collect(),
+        );
+        assert!(matches!(
+            intent[..],
+            [Input::Packet(ReceivedPacket::TakeoverIntent { .. })]
+        ));
+        assert!(matches!(claimer.state(), State::Backup { .. }));
+        assert_eq!(
+            claimer.next_timer(now),
+            now + p.advertisement_interval,
+            "without an answer the claimer takes over after one interval"
+        );
+
+        let actions: Vec<_> = incumbent.handle_input(now, intent[0].clone()).collect();
+        assert!(matches!(
+            actions[..2],
+            [
+                Action::Deactivate { .. },
+                Action::Send {
+                    packet: SendPacket::ShutdownAdvertisement { .. },
+                    ..
+                }
+            ]
+        ));
+        assert!(matches!(incumbent.state(), State::Backup { .. }));
+
+        let final_advertisement = receive(actions);
+        claimer
+            .handle_input(now, final_advertisement[0].clone())
+            .count();
+        assert!(matches!(claimer.state(), State::Active { .. }));
+    }
+
+    #[test]
+    fn backup_prepares_before_taking_over() {
+        let (_, p) = router_in(default_mode());
+        let p = p.with_prepare_activate(true);
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        router.handle_input(now, Command::Startup.into()).count();
+        let interval = p.advertisement_interval;
+        let advertisement: Input =
+            ReceivedPacket::advertisement(TEST_VIRTUAL_MAC, TEST_SENDER_IP, 200, interval).into();
+        router.handle_input(now, advertisement.clone()).count();
+
+        let State::Backup {
+            active_down_timer, ..
+        } = *router.state()
+        else {
+            panic!("not Backup");
+        };
+        let prepare_at = now + Interval::from_secs(3);
+        assert_eq!(router.next_timer(now), prepare_at);
+        let actions: Vec<_> = router.handle_input(prepare_at, Input::Timer).collect();
+        assert!(matches!(actions[..], [Action::PrepareActivate { .. }]));
+        assert_eq!(router.next_timer(prepare_at), active_down_timer);
+
+        let actions: Vec<_> = router.handle_input(prepare_at, advertisement).collect();
+        assert!(actions
+            .iter()
+            .any(|action| matches!(action, Action::CancelPrepare { .. })));
+
+        let prepare_at = prepare_at + Interval::from_secs(3);
+        assert_eq!(router.next_timer(prepare_at), prepare_at);
+        router.handle_input(prepare_at, Input::Timer).count();
+        let actions: Vec<_> = router
+            .handle_input(router.next_timer(prepare_at), Input::Timer)
+            .collect();
+        assert!(actions
+            .iter()
+            .any(|action| matches!(action, Action::Activate { .. })));
+        assert!(!actions
+            .iter()
+            .any(|action| matches!(action, Action::CancelPrepare { .. })));
+    }
+
+    #[test]
+    fn state_changes_carry_their_reason() {
+        let (_, p) = router_in(default_mode());
+        let mut router = Router::new(p.with_notify_state_changes(true));
+        let mut now = Timestamp::now();
+        let reasons = |router: &mut Router, now, input: Input| {
+            router
+                .handle_input(now, input)
+                .filter_map(|action| match action {
+                    Action::Notify(Event::StateChanged { to, reason, .. }) => Some((to, reason)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let interval = Interval::from_secs(1);
+        let advertisement = |priority| {
+            Input::from(ReceivedPacket::advertisement(
+                TEST_VIRTUAL_MAC,
+                TEST_SENDER_IP,
+                priority,
+                interval,
+            ))
+        };
+
+        assert_eq!(
+            reasons(&mut router, now, Command::Startup.into()),
+            vec![("Backup", TransitionReason::Command)]
+        );
+        now = router.next_timer(now);
+        assert_eq!(
+            reasons(&mut router, now, Input::Timer),
+            vec![("Active", TransitionReason::ActiveDownTimeout)]
+        );
+        assert_eq!(
+            reasons(&mut router, now, advertisement(200)),
+            vec![("Backup", TransitionReason::Preempted)]
+        );
+        assert_eq!(reasons(&mut router, now, advertisement(0)), vec![]);
+        now = router.next_timer(now);
+        assert_eq!(
+            reasons(&mut router, now, Input::Timer),
+            vec![("Active", TransitionReason::ShutdownReceived)]
+        );
+        assert_eq!(
+            reasons(&mut router, now, Command::InterfaceDown.into()),
+            vec![("Initialized", TransitionReason::InterfaceDown)]
+        );
+    }
+
+    #[test]
+    fn effective_owner_yields_only_to_a_true_owner() {
+        let mode = BackupMode::effective_owner(TEST_PRIMARY_IP);
+        assert_eq!(Mode::from(mode.clone()).priority().get(), 254);
+        assert!(mode.preempt && mode.accept);
+
+        let lower_ip = Ipv4Addr::new(10, 0, 0, 1);
+        let advertisement = |priority| {
+            Input::from(ReceivedPacket::advertisement(
+                TEST_SENDER_MAC,
+                lower_ip,
+                priority,
+                Interval::from_secs(1),
+            ))
+        };
+
+        let (mut router, _, now) = active_in(mode.clone());
+        let _ = router.handle_input(now, advertisement(254));
+        assert!(
+            matches!(router.state(), State::Active { .. }),
+            "a tie at 254 should be broken by the primary address"
+        );
+        let _ = router.handle_input(now, advertisement(255));
+        assert!(
+            matches!(router.state(), State::Backup { .. }),
+            "the address owner should win regardless of its address"
+        );
+
+        let (mut router, _, now) = startup_in(mode);
+        let _ = router.handle_input(now, advertisement(255));
+        assert!(
+            matches!(router.state(), State::Backup { .. }),
+            "it should not preempt the address owner"
+        );
+    }
+
+    #[test]
+    fn commands_not_applicable_in_the_state_are_reported() {
+        let (_, p) = router_in(default_mode());
+        let mut router = Router::new(p.with_notify_not_applicable(true));
+        let now = Timestamp::now();
+        let handle = |router: &mut Router, command: Command| {
+            router.handle_input(now, command.into()).collect::<Vec<_>>()
+        };
+
+        assert!(!router.accepts(Command::Shutdown));
+        assert_eq!(
+            handle(&mut router, Command::Shutdown),
+            vec![Action::Notify(Event::NotApplicable {
+                command: Command::Shutdown,
+                state: "Initialized",
+            })]
+        );
+        assert!(router.accepts(Command::Startup));
+        let _ = handle(&mut router, Command::Startup);
+
+        assert!(!router.accepts(Command::Startup));
+        assert!(router.accepts(Command::Restart));
+        assert_eq!(
+            handle(&mut router, Command::Startup),
+            vec![Action::Notify(Event::NotApplicable {
+                command: Command::Startup,
+                state: "Backup",
+            })]
+        );
+        assert!(matches!(router.state(), State::Backup { .. }));
+    }
+
+    #[test]
+    fn router_set_keeps_admin_down_routers_down_across_reloads() {
+        let (_, p) = router_in(default_mode());
+        let now = Timestamp::now();
+        let mut set = RouterSet::new();
+        let _ = set.apply_config(now, vec![p.clone()]).unwrap().count();
+        let now = now + Interval::from_secs(10);
+        let mut handler = MockHandler::default();
+        set.handle_timers(now, &mut handler);
+        let state = |set: &RouterSet| set.get(TEST_INTERFACE, p.vrid).unwrap().state().name();
+        assert_eq!(state(&set), "Active");
+
+        assert!(set.set_admin_state(now, TEST_INTERFACE, p.vrid, false, &mut handler));
+        assert!(!set.is_admin_up(TEST_INTERFACE, p.vrid));
+        assert_eq!(state(&set), "Initialized");
+        assert_eq!(handler.deactivated, 1);
+        assert!(handler
+            .sent
+            .last()
+            .is_some_and(|sent| sent.contains("ShutdownAdvertisement")));
+
+        let updated = p.clone().with_garp_repeat(2);
+        assert_eq!(set.apply_config(now, vec![updated]).unwrap().count(), 0);
+        assert_eq!(state(&set), "Initialized");
+
+        assert!(set.set_admin_state(now, TEST_INTERFACE, p.vrid, true, &mut handler));
+        assert_eq!(state(&set), "Backup");
+        assert!(!set.set_admin_state(now, TEST_INTERFACE, VRID::MAX, false, &mut handler));
+    }
+
+    #[cfg(feature = "control")]
+    #[test]
+    fn control_protocol_answers_requests() {
+        use crate::control::{self, ForceFailover, ReloadConfig, Request, Response, ShowRouter};
+
+        let (_, p) = router_in(default_mode());
+        let now = Timestamp::now();
+        let mut set = RouterSet::new();
+        let _ = set.apply_config(now, vec![p.clone()]).unwrap().count();
+        let mut handler = MockHandler::default();
+        let vrid = u8::from(p.vrid);
+        let config = p.clone();
+        let mut handle = |set: &mut RouterSet, now, request| {
+            control::handle(set, now, request, &mut handler, |_| {
+                Ok(vec![config.clone()])
+            })
+        };
+
+        let mut buffer = Vec::new();
+        control::write_message(&mut buffer, &Request::Status).unwrap();
+        assert_eq!(&buffer[4..], br#"{"version":1,"request":"status"}"#);
+        let request = control::read_message(&mut buffer.as_slice()).unwrap();
+        let Response::Status { routers } = handle(&mut set, now, request) else {
+            panic!("expected a status");
+        };
+        assert_eq!((routers.len(), routers[0].state.as_str()), (1, "Backup"));
+
+        let interface = TEST_INTERFACE.0;
+        let failover = Request::ForceFailover(ForceFailover { interface, vrid });
+        assert!(matches!(
+            handle(&mut set, now, failover.clone()),
+            Response::Error { .. }
+        ));
+        let now = now + Interval::from_secs(10);
+        set.handle_timers(now, &mut MockHandler::default());
+        assert_eq!(handle(&mut set, now, failover), Response::Done);
+        let show = |vrid| Request::ShowRouter(ShowRouter { interface, vrid });
+        let Response::Router(details) = handle(&mut set, now, show(vrid)) else {
+            panic!("expected the router");
+        };
+        assert_eq!(details.status.state, "Backup");
+        assert!(details.stats.advertisements_sent > 0);
+        assert!(matches!(
+            handle(&mut set, now, show(vrid + 1)),
+            Response::Error { .. }
+        ));
+        let reload = Request::ReloadConfig(ReloadConfig::default());
+        assert_eq!(handle(&mut set, now, reload), Response::Done);
+
+        let mut other_version = Vec::new();
+        let json = br#"{"version":2,"request":"status"}"#;
+        other_version.extend_from_slice(&(json.len() as u32).to_be_bytes());
+        other_version.extend_from_slice(json);
+        assert!(control::read_message::<Request>(&mut other_version.as_slice()).is_err());
+        let mut oversized = (control::MAX_MESSAGE_LEN + 1).to_be_bytes().to_vec();
+        oversized.extend_from_slice(b"{}");
+        assert!(control::read_message::<Request>(&mut oversized.as_slice()).is_err());
+    }
+
+    #[test]
+    fn scheduled_failover_hands_over_and_holds_preemption() {
+        let (mut router, p, now) =
+            active_in(default_mode().with_priority(Priority::try_from(200).unwrap()));
+        let at = now + Interval::from_secs(30);
+        router.schedule_failover(at, Interval::from_secs(60));
+        assert_eq!(router.scheduled_failover(), Some(at));
+        assert!(router.next_timer(now) <= at);
+
+        let mut now = now;
+        while now < at {
+            now = router.next_timer(now);
+            router.handle_input(now, Input::Timer).count();
+        }
+        assert_eq!(now, at, "the failover should be due on its own timer");
+        assert!(matches!(router.state(), State::Backup { .. }));
+        assert_eq!(router.scheduled_failover(), None);
+        assert_eq!(router.history().last().unwrap().from, "Active");
+
+        let advertisement = Input::from(ReceivedPacket::advertisement(
+            TEST_SENDER_MAC,
+            TEST_SENDER_IP,
+            100,
+            p.advertisement_interval,
+        ));
+        let actions = router
+            .handle_input(at + Interval::from_secs(1), advertisement.clone())
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            actions[..],
+            [Action::Notify(Event::PreemptSuppressed { .. })]
+        ));
+        let suppressed = router
+            .handle_input(at + Interval::from_secs(61), advertisement)
+            .any(|action| matches!(action, Action::Notify(Event::PreemptSuppressed { .. })));
+        assert!(!suppressed, "it should preempt again after the hold");
+    }
+
+    #[test]
+    fn router_set_assigns_advertisement_phases() {
+        let (_, owner) = router_in(Mode::Owner);
+        let second = Parameters {
+            vrid: VRID::try_from(2).unwrap(),
+            ..owner.clone()
+        };
+        let now = Timestamp::now();
+        let mut set = RouterSet::new();
+        let _ = set
+            .apply_config(now, vec![owner.clone(), second])
+            .unwrap()
+            .count();
+        let adver_timer = |vrid| match set.get(TEST_INTERFACE, VRID::try_from(vrid).unwrap()) {
+            Some(router) => match router.state() {
+                State::Active { adver_timer, .. } => *adver_timer,
+                state => panic!("unexpected {state:?}"),
+            },
+            None => panic!("no router {vrid}"),
+        };
+        let interval = owner.advertisement_interval;
+        assert_eq!(adver_timer(1), now + interval);
+        assert_eq!(adver_timer(2), now + interval + interval / 4);
+
+        let (mut router, _) = router_in(Mode::Owner);
+        let phased = owner
+            .clone()
+            .with_advertisement_phase(Interval::from_centis(10));
+        router.update_parameters(now, phased).unwrap().count();
+        router.handle_input(now, Command::Startup.into()).count();
+        assert!(matches!(
+            router.state(),
+            State::Active { adver_timer, .. } if *adver_timer == now + interval + Interval::from_centis(10)
+        ));
+        assert_eq!(
+            owner.with_advertisement_phase(interval / 2).validate(),
+            Err(vec![ConfigError::AdvertisementPhase])
+        );
+    }
+
+    #[test]
+    fn ipv6_owner_announces_with_neighbor_advertisements() {
+        let virtual_ip_1 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let virtual_ip_2 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+        let p = Parameters::new(
+            VRID::try_from(7).unwrap(),
+            vec![virtual_ip_1, virtual_ip_2].try_into().unwrap(),
+            Mode::Owner,
+        )
+        .with_interface(TEST_INTERFACE);
+        let mut router = Router::new(p.clone());
+        let virtual_mac = MacAddr(0x00, 0x00, 0x5e, 0x00, 0x02, 7);
+        assert_eq!(p.mac_address(), virtual_mac);
+
+        let actions = router
+            .handle_input(Timestamp::now(), Command::Startup.into())
+            .collect::<Vec<_>>();
+
+        let send = |packet| Action::Send {
+            interface: TEST_INTERFACE,
+            packet,
+        };
+        let group = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x12);
+        assert_eq!(
+            actions,
+            vec![
+                Action::JoinMulticast {
+                    interface: TEST_INTERFACE,
+                    group,
+                },
+                Action::Activate {
+                    interface: TEST_INTERFACE,
+                    addresses: p.virtual_addresses.clone(),
+                },
+                send(SendPacket::advertisement(p.clone())),
+                send(SendPacket::NeighborAdvertisement {
+                    sender_mac: virtual_mac,
+                    sender_ip: virtual_ip_1,
+                    flags: NeighborFlags::default(),
+                }),
+                send(SendPacket::NeighborAdvertisement {
+                    sender_mac: virtual_mac,
+                    sender_ip: virtual_ip_2,
+                    flags: NeighborFlags::default(),
+                }),
+            ]
+        );
+        assert_eq!(
+            SendPacket::advertisement(p.clone()).destination_mac(),
+            Some(MacAddr(0x33, 0x33, 0x00, 0x00, 0x00, 0x12))
+        );
+        assert_eq!(
+            SendPacket::<Ipv6Addr>::NeighborAdvertisement {
+                sender_mac: virtual_mac,
+                sender_ip: virtual_ip_1,
+                flags: NeighborFlags::default(),
+            }
+            .destination_mac(),
+            Some(MacAddr(0x33, 0x33, 0x00, 0x00, 0x00, 0x01))
+        );
+
+        let actions = router
+            .handle_input(Timestamp::now(), Command::Shutdown.into())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions.last(),
+            Some(&Action::LeaveMulticast {
+                interface: TEST_INTERFACE,
+                group,
+            })
+        );
+    }
+
+    #[test]
+    fn ipv6_backup_runs_the_same_election() {
+        let primary_ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x42);
+        let sender_ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x24);
+        let virtual_ip =
+            VirtualAddress::try_from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 64))
+                .unwrap()
+                .with_arp_subnet_only(true);
+        let p = Parameters::new(
+            VRID::try_from(1).unwrap(),
+            vec![virtual_ip].try_into().unwrap(),
+            BackupMode::with_primary_ip(primary_ip),
+        )
+        .with_interface(TEST_INTERFACE);
+        let mut router = Router::new(p.clone());
+        let now = Timestamp::now();
+        let _ = router.handle_input(now, Command::Startup.into());
+
+        let now = now + Interval::from_secs(10);
+        let _ = router.handle_input(now, Input::Timer);
+        assert!(matches!(router.state(), State::Active { .. }));
+
+        // Neighbor Solicitations are answered from within the prefix only.
+        let solicit = |sender_ip| {
+            Input::Packet(ReceivedPacket::NeighborSolicitation {
+                sender_mac: TEST_SENDER_MAC,
+                sender_ip,
+                target_ip: virtual_ip.address,
+            })
+        };
+        let neighbor = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x99);
+        assert_eq!(
+            router
+                .handle_input(now, solicit(neighbor))
+                .collect::<Vec<_>>(),
+            vec![Action::Send {
+                interface: TEST_INTERFACE,
+                packet: SendPacket::SolicitedNeighborAdvertisement {
+                    sender_mac: p.mac_address(),
+                    sender_ip: virtual_ip.address,
+                    target_mac: TEST_SENDER_MAC,
+                    target_ip: neighbor,
+                    flags: NeighborFlags::default(),
+                },
+            }]
+        );
+        let outsider = Ipv6Addr::new(0x2001, 0xdb9, 0, 0, 0, 0, 0, 0x99);
+        assert_eq!(router.handle_input(now, solicit(outsider)).count(), 0);
+        // Duplicate Address Detection is answered to all nodes.
+        assert_eq!(
+            router
+                .handle_input(now, solicit(Ipv6Addr::UNSPECIFIED))
+                .collect::<Vec<_>>(),
+            vec![Action::Send {
+                interface: TEST_INTERFACE,
+                packet: SendPacket::NeighborAdvertisement {
+                    sender_mac: p.mac_address(),
+                    sender_ip: virtual_ip.address,
+                    flags: NeighborFlags::default(),
+                },
+            }]
+        );
+
+        let advertisement =
+            ReceivedPacket::advertisement(TEST_SENDER_MAC, sender_ip, 254, Interval::from_secs(1));
+        let actions = router
+            .handle_input(now, advertisement.into())
+            .collect::<Vec<_>>();
+        assert!(actions.contains(&Action::Deactivate {
+            interface: TEST_INTERFACE
+        }));
+        assert!(matches!(router.state(), State::Backup { .. }));
+        assert_eq!(
+            router.history().last().map(|transition| transition.cause),
+            Some(TransitionCause::Advertisement {
+                sender_ip,
+                priority: ReceivedPriority::try_from(254).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn ipv6_neighbor_advertisements_take_their_flags_and_repeat_count() {
+        let virtual_ip = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let flags = NeighborFlags {
+            router_flag: false,
+            override_flag: true,
+        };
+        let p = Parameters::new(
+            VRID::try_from(7).unwrap(),
+            vec![virtual_ip].try_into().unwrap(),
+            Mode::Owner,
+        )
+        .with_interface(TEST_INTERFACE)
+        .with_garp_repeat(3)
+        .with_na_repeat(1)
+        .with_garp_spacing(Interval::from_centis(50))
+        .with_neighbor_flags(flags);
+        let mut router = Router::new(p.clone());
+        let packet = SendPacket::NeighborAdvertisement {
+            sender_mac: p.mac_address(),
+            sender_ip: virtual_ip,
+            flags,
+        };
+        let announcement = Action::Send {
+            interface: TEST_INTERFACE,
+            packet: packet.clone(),
+        };
+
+        let start = Timestamp::now();
+        let actions = router
+            .handle_input(start, Command::Startup.into())
+            .collect::<Vec<_>>();
+        assert_eq!(actions.last(), Some(&announcement));
+
+        let now = start + p.garp_spacing;
+        let actions = router.handle_input(now, Input::Timer).collect::<Vec<_>>();
+        assert_eq!(actions, vec![announcement.clone()]);
+        assert!(
+            matches!(
+                router.state(),
+                State::Active {
+                    garp_timer: None,
+                    ..
+                }
+            ),
+            "na_repeat takes precedence over garp_repeat"
+        );
+        assert_eq!(
+            packet.to_string(),
+            format!("NA {virtual_ip} is-at {} override", p.mac_address())
+        );
+    }
+
+    #[test]
+    fn dispatch_passes_actions_to_handler() {
+        let (mut router, p) = router_in(Mode::Owner);
+
+        let mut handler = MockHandler::default();
+        router.dispatch(Timestamp::now(), Command::Startup.into(), &mut handler);
+
+        assert_eq!(handler.joined, vec![VRRP_MULTICAST_GROUP]);
+        assert_eq!(handler.activated, 1);
+        assert_eq!(
+            handler.sent,
+            vec![
+                format!("{:?}", SendPacket::advertisement(p.clone())),
+                format!(
+                    "{:?}",
+                    SendPacket::<Ipv4Addr>::GratuitousARP {
+                        sender_mac: p.mac_address(),
+                        sender_ip: TEST_VIRTUAL_IP_1
+                    }
+                ),
+                format!(
+                    "{:?}",
+                    SendPacket::<Ipv4Addr>::GratuitousARP {
+                        sender_mac: p.mac_address(),
+                        sender_ip: TEST_VIRTUAL_IP_2
+                    }
+                ),
+            ]
+        );
+
+        let mut handler = MockHandler::default();
+        router.dispatch(Timestamp::now(), Command::Shutdown.into(), &mut handler);
+
+        assert_eq!(handler.deactivated, 1);
+        assert_eq!(handler.left, vec![VRRP_MULTICAST_GROUP]);
+        assert_eq!(
+            handler.sent,
+            vec![format!(
+                "{:?}",
+                SendPacket::shutdown_advertisement(p.clone())
+            )]
+        );
+        assert_eq!(handler.routed, vec![]);
+    }
 }
@@ -0,0 +1,388 @@
+use crate::{Action, InterfaceId, VirtualAddress, VirtualAddresses, VRID};
+use pnet_base::MacAddr;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+// https://man7.org/linux/man-pages/man7/netlink.7.html
+const NLMSG_HEADER_LEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_ACK: u16 = 0x04;
+const NLM_F_REPLACE: u16 = 0x100;
+const NLM_F_EXCL: u16 = 0x200;
+const NLM_F_CREATE: u16 = 0x400;
+
+// https://man7.org/linux/man-pages/man7/rtnetlink.7.html
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const IFINFOMSG_LEN: usize = 16;
+const IFF_UP: u32 = 0x1;
+const IFLA_ADDRESS: u16 = 1;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_LINK: u16 = 5;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_INFO_KIND: u16 = 1;
+const IFLA_INFO_DATA: u16 = 2;
+const IFLA_MACVLAN_MODE: u16 = 1;
+const MACVLAN_MODE_PRIVATE: u32 = 1;
+const RTM_NEWADDR: u16 = 20;
+const RTM_DELADDR: u16 = 21;
+const IFADDRMSG_LEN: usize = 8;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const RT_SCOPE_UNIVERSE: u8 = 0;
+
+/// How a `NetlinkExecutor` assigns the virtual addresses.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Activation {
+    /// Add them to the interface itself.
+    #[default]
+    Addresses,
+    /// Bring up a macvlan interface with the virtual MAC address on top of the interface and
+    /// add them to that, like keepalived's `use_vmac`. It is deleted again on deactivation.
+    Macvlan { name: String, mac_address: MacAddr },
+}
+
+impl Activation {
+    /// A macvlan interface named `vrrp.<vrid>` with the virtual MAC address of the VRID.
+    pub fn vmac(vrid: VRID) -> Self {
+        Activation::Macvlan {
+            name: format!("vrrp.{}", u8::from(vrid)),
+            mac_address: vrid.into_mac_address(),
+        }
+    }
+}
+
+/// Carries out `Action::Activate` and `Action::Deactivate` by adding and
+/// removing the virtual addresses on a Linux interface over rtnetlink. The addresses
+/// of the last `Action::Activate` are the ones removed on deactivation, so each virtual
+/// router needs its own executor.
+///
+/// Only actions for the `InterfaceId` of the interface's index are carried out.
+pub struct NetlinkExecutor {
+    socket: OwnedFd,
+    interface: String,
+    interface_index: u32,
+    virtual_addresses: VirtualAddresses,
+    activation: Activation,
+    arp_sysctls: Option<ArpSysctls>,
+    sequence: u32,
+}
+
+impl NetlinkExecutor {
+    pub fn new(interface: &str, virtual_addresses: VirtualAddresses) -> io::Result<Self> {
+        let interface_index = interface_index(interface)?;
+
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let result = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            socket,
+            interface: interface.to_owned(),
+            interface_index,
+            virtual_addresses,
+            activation: Activation::default(),
+            arp_sysctls: None,
+            sequence: 0,
+        })
+    }
+
+    /// While Active, set `arp_ignore=1` and `arp_announce=2` on the interface so the kernel
+    /// does not answer or announce the virtual addresses with the interface's own MAC address.
+    /// The previous values are restored on `Action::Deactivate`.
+    pub fn with_arp_sysctls(self, enabled: bool) -> Self {
+        Self {
+            arp_sysctls: enabled.then(ArpSysctls::default),
+            ..self
+        }
+    }
+
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation, ..self }
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    pub fn interface_id(&self) -> InterfaceId {
+        InterfaceId(self.interface_index)
+    }
+
+    /// Executes the address related actions for this interface; every other action is
+    /// ignored.
+    pub fn execute(&mut self, action: &Action) -> io::Result<()> {
+        match action {
+            Action::Activate {
+                interface,
+                addresses,
+            } if *interface == self.interface_id() => {
+                self.virtual_addresses = addresses.clone();
+                self.activate()
+            }
+            Action::Deactivate { interface } if *interface == self.interface_id() => {
+                self.deactivate()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn activate(&mut self) -> io::Result<()> {
+        if let Some(sysctls) = &mut self.arp_sysctls {
+            sysctls.apply(&self.interface)?;
+        }
+        let interface_index = match self.activation.clone() {
+            Activation::Addresses => self.interface_index,
+            Activation::Macvlan { name, mac_address } => self.create_macvlan(&name, mac_address)?,
+        };
+        for index in 0..self.virtual_addresses.len() {
+            let payload = address_payload(interface_index, &self.virtual_addresses[index]);
+            self.request(RTM_NEWADDR, NLM_F_CREATE | NLM_F_REPLACE, &payload)?;
+        }
+        Ok(())
+    }
+
+    // Creates the macvlan interface, or reuses it if it is left over, and brings it up.
+    fn create_macvlan(&mut self, name: &str, mac_address: MacAddr) -> io::Result<u32> {
+        let mut info = attribute(IFLA_INFO_KIND, b"macvlan");
+        info.extend(attribute(
+            IFLA_INFO_DATA,
+            &attribute(IFLA_MACVLAN_MODE, &MACVLAN_MODE_PRIVATE.to_ne_bytes()),
+        ));
+        let mut payload = link_info(0);
+        payload.extend(attribute(IFLA_IFNAME, &[name.as_bytes(), &[0]].concat()));
+        payload.extend(attribute(IFLA_LINK, &self.interface_index.to_ne_bytes()));
+        payload.extend(attribute(IFLA_ADDRESS, &mac_address.octets()));
+        payload.extend(attribute(IFLA_LINKINFO, &info));
+        match self.request(RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL, &payload) {
+            Err(error) if error.raw_os_error() == Some(libc::EEXIST) => {}
+            result => result?,
+        }
+
+        let index = interface_index(name)?;
+        self.request(RTM_NEWLINK, 0, &link_info(index))?;
+        Ok(index)
+    }
+
+    fn deactivate(&mut self) -> io::Result<()> {
+        match self.activation.clone() {
+            // Deleting the interface removes its addresses.
+            Activation::Macvlan { name, .. } => match interface_index(&name) {
+                Ok(index) => self.request(RTM_DELLINK, 0, &link_info(index))?,
+                // Already deleted, e.g. by an operator.
+                Err(error) if error.raw_os_error() == Some(libc::ENODEV) => {}
+                Err(error) => return Err(error),
+            },
+            Activation::Addresses => {
+                for index in 0..self.virtual_addresses.len() {
+                    let payload =
+                        address_payload(self.interface_index, &self.virtual_addresses[index]);
+                    match self.request(RTM_DELADDR, 0, &payload) {
+                        // The address was already removed, e.g. by an operator.
+                        Err(error) if error.raw_os_error() == Some(libc::EADDRNOTAVAIL) => {}
+                        result => result?,
+                    }
+                }
+            }
+        }
+        if let Some(sysctls) = &mut self.arp_sysctls {
+            sysctls.restore(&self.interface)?;
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, message_type: u16, flags: u16, payload: &[u8]) -> io::Result<()> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let flags = NLM_F_REQUEST | NLM_F_ACK | flags;
+        let length = (NLMSG_HEADER_LEN + payload.len()) as u32;
+
+        // struct nlmsghdr
+        let mut message = Vec::with_capacity(length as usize);
+        message.extend_from_slice(&length.to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(&flags.to_ne_bytes());
+        message.extend_from_slice(&self.sequence.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(payload);
+
+        let sent = unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.receive_ack()
+    }
+
+    fn receive_ack(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let received = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if received < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut message = &buffer[..received as usize];
+            while message.len() >= NLMSG_HEADER_LEN {
+                let length = u32::from_ne_bytes(message[0..4].try_into().unwrap()) as usize;
+                let message_type = u16::from_ne_bytes(message[4..6].try_into().unwrap());
+                let sequence = u32::from_ne_bytes(message[8..12].try_into().unwrap());
+                if length < NLMSG_HEADER_LEN || length > message.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "truncated netlink message",
+                    ));
+                }
+
+                if message_type == NLMSG_ERROR && sequence == self.sequence {
+                    let payload = &message[NLMSG_HEADER_LEN..length];
+                    let code = payload.get(0..4).ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "truncated netlink error")
+                    })?;
+                    let code = i32::from_ne_bytes(code.try_into().unwrap());
+                    return match code {
+                        0 => Ok(()),
+                        code => Err(io::Error::from_raw_os_error(-code)),
+                    };
+                }
+
+                message = &message[align(length).min(message.len())..];
+            }
+        }
+    }
+}
+
+pub(crate) fn interface_index(interface: &str) -> io::Result<u32> {
+    let name = CString::new(interface)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+pub(crate) fn address_payload(interface_index: u32, virtual_address: &VirtualAddress) -> Vec<u8> {
+    let mut message = Vec::with_capacity(IFADDRMSG_LEN + 2 * 8);
+
+    // struct ifaddrmsg
+    message.push(libc::AF_INET as u8);
+    message.push(virtual_address.prefix_len);
+    message.push(0);
+    message.push(RT_SCOPE_UNIVERSE);
+    message.extend_from_slice(&interface_index.to_ne_bytes());
+
+    for kind in [IFA_LOCAL, IFA_ADDRESS] {
+        message.extend(attribute(kind, &virtual_address.address.octets()));
+    }
+    message
+}
+
+// struct ifinfomsg, bringing the interface up.
+pub(crate) fn link_info(interface_index: u32) -> Vec<u8> {
+    let mut message = Vec::with_capacity(IFINFOMSG_LEN);
+    message.push(libc::AF_UNSPEC as u8);
+    message.push(0);
+    message.extend_from_slice(&0u16.to_ne_bytes());
+    message.extend_from_slice(&interface_index.to_ne_bytes());
+    message.extend_from_slice(&IFF_UP.to_ne_bytes());
+    message.extend_from_slice(&IFF_UP.to_ne_bytes());
+    message
+}
+
+// struct rtattr followed by the payload, padded to 4 bytes.
+pub(crate) fn attribute(kind: u16, payload: &[u8]) -> Vec<u8> {
+    let length = 4 + payload.len();
+    let mut attribute = Vec::with_capacity(align(length));
+    attribute.extend_from_slice(&(length as u16).to_ne_bytes());
+    attribute.extend_from_slice(&kind.to_ne_bytes());
+    attribute.extend_from_slice(payload);
+    attribute.resize(align(length), 0);
+    attribute
+}
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+#[derive(Debug, Default)]
+struct ArpSysctls {
+    saved: Option<(String, String)>,
+}
+
+impl ArpSysctls {
+    const ARP_IGNORE: &'static str = "arp_ignore";
+    const ARP_ANNOUNCE: &'static str = "arp_announce";
+
+    fn apply(&mut self, interface: &str) -> io::Result<()> {
+        if self.saved.is_none() {
+            self.saved = Some((
+                read_sysctl(interface, Self::ARP_IGNORE)?,
+                read_sysctl(interface, Self::ARP_ANNOUNCE)?,
+            ));
+        }
+        write_sysctl(interface, Self::ARP_IGNORE, "1")?;
+        write_sysctl(interface, Self::ARP_ANNOUNCE, "2")
+    }
+
+    fn restore(&mut self, interface: &str) -> io::Result<()> {
+        match self.saved.take() {
+            Some((arp_ignore, arp_announce)) => {
+                write_sysctl(interface, Self::ARP_IGNORE, &arp_ignore)?;
+                write_sysctl(interface, Self::ARP_ANNOUNCE, &arp_announce)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn sysctl_path(interface: &str, name: &str) -> String {
+    format!("/proc/sys/net/ipv4/conf/{interface}/{name}")
+}
+
+fn read_sysctl(interface: &str, name: &str) -> io::Result<String> {
+    Ok(fs::read_to_string(sysctl_path(interface, name))?
+        .trim()
+        .to_owned())
+}
+
+fn write_sysctl(interface: &str, name: &str, value: &str) -> io::Result<()> {
+    fs::write(sysctl_path(interface, name), value)
+}
@@ -0,0 +1,64 @@
+use crate::Timestamp;
+use crate::{AddressFamily, ReceivedPriority};
+use pnet_base::MacAddr;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Info,
+    Warning,
+}
+
+/// Noteworthy protocol events for operational logging, see `Parameters::with_logging`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogEvent<A: AddressFamily = Ipv4Addr> {
+    BecameActive,
+    /// A router with a greater priority took over while we were Active.
+    LostElection {
+        sender_ip: A,
+    },
+    /// Advertisements are now received from a different Active router.
+    PeerChanged {
+        sender_ip: A,
+        priority: ReceivedPriority,
+    },
+    /// An advertisement was dropped because it was not sent from the virtual router MAC address.
+    RejectedAdvertisement {
+        sender_mac: MacAddr,
+    },
+}
+
+impl<A: AddressFamily> LogEvent<A> {
+    fn kind(&self) -> usize {
+        match self {
+            LogEvent::BecameActive => 0,
+            LogEvent::LostElection { .. } => 1,
+            LogEvent::PeerChanged { .. } => 2,
+            LogEvent::RejectedAdvertisement { .. } => 3,
+        }
+    }
+}
+
+const LOG_EVENT_KINDS: usize = 4;
+
+/// Lets through at most one event of each kind per `SPACING`.
+#[derive(Debug, Default)]
+pub(crate) struct LogLimiter {
+    last: [Option<Timestamp>; LOG_EVENT_KINDS],
+}
+
+impl LogLimiter {
+    const SPACING: Duration = Duration::from_secs(1);
+
+    pub(crate) fn allow<A: AddressFamily>(&mut self, now: Timestamp, event: &LogEvent<A>) -> bool {
+        let last = &mut self.last[event.kind()];
+        match last {
+            Some(at) if now < *at + Self::SPACING => false,
+            _ => {
+                *last = Some(now);
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{Router, State};
+use metrics::{counter, gauge};
+
+impl Router {
+    /// Publishes the state and `Stats` of this virtual router through the `metrics` facade,
+    /// labelled with its VRID and interface. Call it periodically, e.g. before every scrape.
+    ///
+    /// `vrrp_state` is 0 when Initialized, 1 in Backup or Monitoring, and 2 when Active or
+    /// shutting down.
+    pub fn export_metrics(&self) {
+        let parameters = self.parameters();
+        let labels = [
+            ("vrid", u8::from(parameters.vrid).to_string()),
+            ("interface", parameters.interface.0.to_string()),
+        ];
+        let state = match self.state() {
+            State::Initialized => 0.0,
+            State::Backup { .. } | State::Monitoring { .. } => 1.0,
+            State::Active { .. } | State::ShuttingDown { .. } => 2.0,
+        };
+        gauge!("vrrp_state", &labels).set(state);
+
+        let stats = self.stats();
+        for (name, value) in [
+            ("vrrp_transitions_total", stats.transitions),
+            ("vrrp_advertisements_sent_total", stats.advertisements_sent),
+            (
+                "vrrp_advertisements_received_total",
+                stats.advertisements_received,
+            ),
+            (
+                "vrrp_sender_mac_mismatches_total",
+                stats.sender_mac_mismatches,
+            ),
+            ("vrrp_address_conflicts_total", stats.address_conflicts),
+            (
+                "vrrp_reflected_advertisements_total",
+                stats.reflected_advertisements,
+            ),
+            (
+                "vrrp_rejected_shutdown_advertisements_total",
+                stats.rejected_shutdown_advertisements,
+            ),
+            (
+                "vrrp_own_advertisements_dropped_total",
+                stats.own_advertisements_dropped,
+            ),
+            (
+                "vrrp_compat_zero_intervals_total",
+                stats.compat_zero_intervals,
+            ),
+            (
+                "vrrp_arp_requests_rate_limited_total",
+                stats.arp_requests_rate_limited,
+            ),
+        ] {
+            counter!(name, &labels).absolute(value);
+        }
+        for (quantile, percentile) in [("0.5", 50), ("0.99", 99)] {
+            if let Some(jitter) = stats.advertisement_jitter.percentile(percentile) {
+                let labels = [
+                    labels[0].clone(),
+                    labels[1].clone(),
+                    ("quantile", quantile.to_owned()),
+                ];
+                gauge!("vrrp_advertisement_jitter_seconds", &labels).set(jitter.as_secs_f64());
+            }
+        }
+    }
+}
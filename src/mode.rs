@@ -1,37 +1,102 @@
-use crate::Priority;
+use crate::{AddressFamily, Priority};
 use std::net::Ipv4Addr;
 use std::num::NonZeroU8;
 
 #[derive(Clone, Debug, PartialEq)]
-pub enum Mode {
+pub enum Mode<A: AddressFamily = Ipv4Addr> {
     Owner,
-    Backup(BackupMode),
+    Backup(BackupMode<A>),
+    /// Listen only: track the elections on the network without ever becoming Active.
+    Monitor,
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct BackupMode {
-    pub primary_ip: Ipv4Addr,
+pub struct BackupMode<A: AddressFamily = Ipv4Addr> {
+    pub primary_ip: A,
     pub priority: Priority,
     pub preempt: bool,
     pub accept: bool,
+    /// Packets to the virtual addresses that are accepted even without `accept`.
+    pub accept_exceptions: Vec<AcceptException>,
 }
 
-impl From<BackupMode> for Mode {
-    fn from(value: BackupMode) -> Self {
+/// What an Active owner does on an advertisement from another owner, which means the
+/// virtual addresses are configured as local on two routers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OwnerConflict {
+    /// Treat it like an equal priority: step down only to a greater primary address.
+    #[default]
+    TieBreak,
+    /// Stay Active and advertise at once.
+    Defend,
+    /// Step down to Backup.
+    Yield,
+    /// Stay Active without answering, leaving it to the operator.
+    AlarmOnly,
+}
+
+/// Matches packets by IP protocol and, optionally, destination port.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AcceptException {
+    pub protocol: u8,
+    pub destination_port: Option<u16>,
+}
+
+impl AcceptException {
+    pub const ICMP: Self = Self::protocol(1);
+
+    pub const fn protocol(protocol: u8) -> Self {
+        Self {
+            protocol,
+            destination_port: None,
+        }
+    }
+
+    pub const fn tcp(destination_port: u16) -> Self {
+        Self {
+            protocol: 6,
+            destination_port: Some(destination_port),
+        }
+    }
+
+    pub const fn udp(destination_port: u16) -> Self {
+        Self {
+            protocol: 17,
+            destination_port: Some(destination_port),
+        }
+    }
+
+    fn matches(&self, protocol: u8, destination_port: Option<u16>) -> bool {
+        self.protocol == protocol
+            && (self.destination_port.is_none() || self.destination_port == destination_port)
+    }
+}
+
+impl<A: AddressFamily> From<BackupMode<A>> for Mode<A> {
+    fn from(value: BackupMode<A>) -> Self {
         Mode::Backup(value)
     }
 }
 
-impl BackupMode {
-    pub fn with_primary_ip(primary_ip: Ipv4Addr) -> Self {
+impl<A: AddressFamily> BackupMode<A> {
+    pub fn with_primary_ip(primary_ip: A) -> Self {
         Self {
             primary_ip,
             priority: Priority::default(),
             preempt: true,
             accept: false,
+            accept_exceptions: Vec::new(),
         }
     }
 
+    /// An "effective owner": priority 254, preempting and accepting packets like the address
+    /// owner, without owning the addresses. A true owner still wins at 255.
+    pub fn effective_owner(primary_ip: A) -> Self {
+        Self::with_primary_ip(primary_ip)
+            .with_priority(Priority::MAX)
+            .with_accept(true)
+    }
+
     pub fn with_priority(self, priority: Priority) -> Self {
         Self { priority, ..self }
     }
@@ -43,20 +108,37 @@ impl BackupMode {
     pub fn with_accept(self, accept: bool) -> Self {
         Self { accept, ..self }
     }
+
+    pub fn with_accept_exception(mut self, exception: AcceptException) -> Self {
+        self.accept_exceptions.push(exception);
+        self
+    }
 }
 
-impl Mode {
-    pub(crate) fn priority(&self) -> NonZeroU8 {
+impl<A: AddressFamily> Mode<A> {
+    /// The priority advertised, 255 for the address owner.
+    pub fn priority(&self) -> NonZeroU8 {
         match self {
             Mode::Owner => NonZeroU8::MAX,
             Mode::Backup(BackupMode { priority, .. }) => (*priority).into(),
+            Mode::Monitor => NonZeroU8::MIN,
         }
     }
 
-    pub(crate) fn should_accept(&self) -> bool {
+    pub(crate) fn should_accept(&self, protocol: u8, destination_port: Option<u16>) -> bool {
         match self {
             Mode::Owner => true,
-            Mode::Backup(BackupMode { accept, .. }) => *accept,
+            Mode::Backup(BackupMode {
+                accept,
+                accept_exceptions,
+                ..
+            }) => {
+                *accept
+                    || accept_exceptions
+                        .iter()
+                        .any(|exception| exception.matches(protocol, destination_port))
+            }
+            Mode::Monitor => false,
         }
     }
 
@@ -64,6 +146,7 @@ impl Mode {
         match self {
             Mode::Owner => true,
             Mode::Backup(BackupMode { preempt, .. }) => *preempt,
+            Mode::Monitor => false,
         }
     }
 }
@@ -0,0 +1,259 @@
+//! Exhaustive exploration of how advertisements between a few routers interleave on the
+//! simulated clock and network of `scenario`, checking that elections converge.
+//!
+//! Every run replays a sequence of choices from startup: each step delivers or loses the
+//! oldest advertisement in flight to one router, or lets the clock jump to the next timer.
+//! After `depth` steps the network becomes reliable. Once it settled exactly one router must
+//! be Active, the election winner if every router preempts, and stay Active.
+
+use crate::scenario::{advertisement, timer};
+use crate::Timestamp;
+use crate::{Command, Input, Interval, Parameters, ReceivedPacket, Router, State};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Choice {
+    /// Deliver the oldest advertisement in flight to this router.
+    Deliver(usize),
+    /// Lose the oldest advertisement in flight to this router.
+    Lose(usize),
+    Timer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    NoActive,
+    /// More than one router is Active on a reliable network.
+    DualActive(Vec<usize>),
+    /// A router other than the election winner is Active.
+    WrongActive {
+        active: usize,
+        winner: usize,
+    },
+}
+
+/// The choices leading to a violation, to be replayed with `Scenario` or by hand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Counterexample {
+    pub choices: Vec<Choice>,
+    pub violation: Violation,
+}
+
+pub struct Model {
+    parameters: Vec<Parameters>,
+    depth: usize,
+    max_losses: usize,
+}
+
+impl Model {
+    /// Routers on the same interface and VRID, started at the same time.
+    pub fn new(parameters: Vec<Parameters>) -> Self {
+        Self {
+            parameters,
+            depth: 8,
+            max_losses: 2,
+        }
+    }
+
+    /// How many steps are explored before the network becomes reliable.
+    pub fn with_depth(self, depth: usize) -> Self {
+        Self { depth, ..self }
+    }
+
+    /// How many advertisements a run loses at most.
+    pub fn with_max_losses(self, max_losses: usize) -> Self {
+        Self { max_losses, ..self }
+    }
+
+    /// Explores every run, returning how many there were.
+    pub fn check(&self) -> Result<usize, Counterexample> {
+        let mut runs = 0;
+        let mut prefixes = vec![Vec::new()];
+        while let Some(prefix) = prefixes.pop() {
+            let mut network = Network::start(&self.parameters);
+            for &choice in &prefix {
+                network.step(choice);
+            }
+            let losses = prefix
+                .iter()
+                .filter(|choice| matches!(choice, Choice::Lose(_)))
+                .count();
+            let choices = match prefix.len() < self.depth {
+                true => network.choices(losses < self.max_losses),
+                false => Vec::new(),
+            };
+            if choices.is_empty() {
+                runs += 1;
+                if let Err(violation) = network.settle(self.winner()) {
+                    return Err(Counterexample {
+                        choices: prefix,
+                        violation,
+                    });
+                }
+            }
+            for choice in choices {
+                let mut next = prefix.clone();
+                next.push(choice);
+                prefixes.push(next);
+            }
+        }
+        Ok(runs)
+    }
+
+    // The router that must end up Active, known only when every router preempts.
+    fn winner(&self) -> Option<usize> {
+        if !self.parameters.iter().all(|p| p.mode.should_preempt()) {
+            return None;
+        }
+        (0..self.parameters.len()).max_by_key(|&router| {
+            let parameters = &self.parameters[router];
+            (parameters.mode.priority(), parameters.primary_ip())
+        })
+    }
+}
+
+struct Network {
+    now: Timestamp,
+    routers: Vec<Router>,
+    // Advertisements sent but not yet delivered, oldest first, with their destination.
+    in_flight: Vec<(usize, ReceivedPacket)>,
+}
+
+impl Network {
+    fn start(parameters: &[Parameters]) -> Self {
+        let mut network = Self {
+            now: Timestamp::now(),
+            routers: parameters.iter().cloned().map(Router::new).collect(),
+            in_flight: Vec::new(),
+        };
+        for router in 0..network.routers.len() {
+            network.handle(router, Command::Startup.into());
+        }
+        network
+    }
+
+    fn handle(&mut self, router: usize, input: Input) {
+        let sent: Vec<_> = self.routers[router]
+            .handle_input(self.now, input)
+            .filter_map(advertisement)
+            .collect();
+        for packet in sent {
+            for peer in (0..self.routers.len()).filter(|&peer| peer != router) {
+                self.in_flight.push((peer, packet.clone()));
+            }
+        }
+    }
+
+    fn choices(&self, may_lose: bool) -> Vec<Choice> {
+        let mut choices = Vec::new();
+        for router in 0..self.routers.len() {
+            if self.in_flight.iter().any(|(to, _)| *to == router) {
+                choices.push(Choice::Deliver(router));
+                if may_lose {
+                    choices.push(Choice::Lose(router));
+                }
+            }
+        }
+        if self.next_timer().is_some() {
+            choices.push(Choice::Timer);
+        }
+        choices
+    }
+
+    fn step(&mut self, choice: Choice) {
+        match choice {
+            Choice::Deliver(router) | Choice::Lose(router) => {
+                let Some(index) = self.in_flight.iter().position(|(to, _)| *to == router) else {
+                    return;
+                };
+                let (_, packet) = self.in_flight.remove(index);
+                if choice == Choice::Deliver(router) {
+                    self.handle(router, packet.into());
+                }
+            }
+            Choice::Timer => {
+                let Some(next) = self.next_timer() else {
+                    return;
+                };
+                self.now = self.now.max(next);
+                for router in 0..self.routers.len() {
+                    if timer(self.routers[router].state()).is_some_and(|at| at <= self.now) {
+                        self.handle(router, Input::Timer);
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_timer(&self) -> Option<Timestamp> {
+        self.routers
+            .iter()
+            .filter_map(|router| timer(router.state()))
+            .min()
+    }
+
+    fn deliver_all(&mut self) {
+        while let Some((router, packet)) =
+            (!self.in_flight.is_empty()).then(|| self.in_flight.remove(0))
+        {
+            self.handle(router, packet.into());
+        }
+    }
+
+    fn active(&self) -> Vec<usize> {
+        (0..self.routers.len())
+            .filter(|&router| matches!(self.routers[router].state(), State::Active { .. }))
+            .collect()
+    }
+
+    // Runs on a reliable network until every router had the time to notice the Active
+    // router, then checks it stays the only one.
+    fn settle(&mut self, winner: Option<usize>) -> Result<(), Violation> {
+        let settled = self.now + self.settle_time();
+        self.deliver_all();
+        while self.now < settled {
+            self.step(Choice::Timer);
+            self.deliver_all();
+        }
+        let stable = self.now + self.settle_time();
+        loop {
+            let active = self.active();
+            match (active.as_slice(), winner) {
+                ([], _) => return Err(Violation::NoActive),
+                ([active], Some(winner)) if *active != winner => {
+                    return Err(Violation::WrongActive {
+                        active: *active,
+                        winner,
+                    })
+                }
+                ([_], _) => {}
+                _ => return Err(Violation::DualActive(active)),
+            }
+            if self.now >= stable {
+                return Ok(());
+            }
+            self.step(Choice::Timer);
+            self.deliver_all();
+        }
+    }
+
+    fn settle_time(&self) -> Duration {
+        let longest = self
+            .routers
+            .iter()
+            .map(|router| {
+                let parameters = router.parameters();
+                let interval = match parameters.learn_master_interval {
+                    true => Interval::MAX,
+                    false => parameters.advertised_interval(),
+                };
+                let grace = parameters
+                    .startup_grace
+                    .map_or(Duration::ZERO, Duration::from);
+                Duration::from(parameters.active_down_interval(interval) + interval) + grace
+            })
+            .max()
+            .unwrap_or_default();
+        2 * longest
+    }
+}
@@ -1,53 +1,491 @@
-use crate::{BackupMode, Interval, Mode, VirtualAddresses, VRID};
+use crate::{
+    timing, AddressFamily, ArpReplyLimit, BackupMode, Destination, FlapDamping, InterfaceId,
+    Interval, Mode, NeighborFlags, OwnerConflict, Profile, VirtualAddresses, VRID,
+};
 use pnet_base::MacAddr;
 use std::net::Ipv4Addr;
+use std::time::Duration;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct Parameters {
+pub struct Parameters<A: AddressFamily = Ipv4Addr> {
     pub vrid: VRID,
-    pub mode: Mode,
-    pub virtual_addresses: VirtualAddresses,
+    pub interface: InterfaceId,
+    pub mode: Mode<A>,
+    pub virtual_addresses: VirtualAddresses<A>,
     pub advertisement_interval: Interval,
+    /// The Max Advertise Interval carried by advertisements, when it differs from the
+    /// interval they are sent at. While migrating a cluster to a shorter interval, routers
+    /// can send faster while still advertising the old interval, so the down timers of peers
+    /// stay conservative. It may not be shorter than `advertisement_interval`.
+    pub advertised_interval: Option<Interval>,
+    /// Delays the first advertisement after becoming Active, after the one sent at once, so
+    /// routers becoming Active together do not keep advertising in step. Less than half the
+    /// advertisement interval, so Backups don't see it as irregular. `RouterSet` spreads the
+    /// routers without one across an interface.
+    pub advertisement_phase: Option<Interval>,
+    /// How many times the gratuitous ARPs are repeated after the initial announcement.
+    pub garp_repeat: u8,
+    /// How many times the unsolicited Neighbor Advertisements of IPv6 routers are repeated
+    /// after the initial announcement, `garp_repeat` times unless set.
+    pub na_repeat: Option<u8>,
+    pub garp_spacing: Interval,
+    /// How many times the shutdown advertisement is repeated, `garp_spacing` apart, before
+    /// the interface is deactivated, so a peer on a lossy link still takes over at once.
+    pub shutdown_repeat: u8,
+    /// Drop advertisements that were not sent from the virtual router MAC address.
+    pub verify_sender_mac: bool,
+    /// Drop our own advertisements looped back by the capture layer, recognized by our
+    /// primary address and, unless a shutdown advertisement, the virtual router MAC address.
+    /// Otherwise they are reported as `Event::ReflectedAdvertisement` while Active.
+    pub drop_own_advertisements: bool,
+    pub flap_damping: Option<FlapDamping>,
+    /// Do not preempt for this long after startup, so a restarted router with a higher
+    /// priority leaves the incumbent Active router alone during rolling restarts.
+    pub startup_grace: Option<Interval>,
+    /// After becoming Active, reject packets that would be accepted for this long, while the
+    /// gratuitous ARPs propagate and return traffic may still reach the previous Active router.
+    pub accept_settle: Option<Interval>,
+    /// Start in Backup assuming the longest possible advertisement interval, and arm the
+    /// down timer from the interval of the first advertisement received.
+    pub learn_master_interval: bool,
+    /// When the priority of an Active router is lowered, advertise it at once instead of at
+    /// the next advertisement, so a preempting peer takes over sooner. The router stays
+    /// Active until the peer's advertisement arrives.
+    pub advertise_priority_drop: bool,
+    /// Emit `Action::PrepareActivate` once a Backup's down timer is within one skew time of
+    /// expiring, so state such as connection tracking can be warmed up before taking over.
+    pub prepare_activate: bool,
+    /// Non-standard, not in RFC 9568: the MAC address advertisements are sent from. When an
+    /// advertisement has the local priority and primary address, as happens behind NAT, the
+    /// greater sender MAC address wins instead of treating it as reflected. Off by default.
+    pub mac_tiebreak: Option<MacAddr>,
+    /// Non-standard, not in RFC 9568: before preempting, a Backup announces its intent with
+    /// a reserved bit of its advertisement and waits for the Active router's final shutdown
+    /// advertisement, so the addresses are never active on both. Routers without the
+    /// extension see an ordinary advertisement and step down; the Backup then takes over
+    /// after one advertisement interval of the Active router. Off by default.
+    #[cfg(feature = "graceful-takeover")]
+    pub graceful_takeover: bool,
+    /// How an Active owner handles an advertisement from another owner.
+    pub owner_conflict: OwnerConflict,
+    /// Emit rate limited `Action::Log` for noteworthy protocol events.
+    pub logging: bool,
+    /// Emit `Event::StateChanged` with the reason of every change of state.
+    pub notify_state_changes: bool,
+    /// Emit `Event::NotApplicable` for commands that do nothing in the current state.
+    pub notify_not_applicable: bool,
+    /// While Active, answer ARP probes (RFC 5227) for the virtual addresses to defend them.
+    /// Turned off during a graceful handover to let the new owner claim them.
+    pub defend_addresses: bool,
+    /// Send ARP replies for the virtual addresses to the Ethernet broadcast address instead
+    /// of the requester, for switch fabrics that only relearn the virtual MAC address from
+    /// broadcasts.
+    pub broadcast_arp_replies: bool,
+    /// The flags of the Neighbor Advertisements of IPv6 routers.
+    pub neighbor_flags: NeighborFlags,
+    /// Limits the ARP replies sent to the same requester while Active.
+    pub arp_reply_limit: Option<ArpReplyLimit>,
+    /// The local address advertisements are sent from, instead of the primary address
+    /// derived from the mode.
+    pub source_ip: Option<A>,
+    /// Send advertisements to these peers instead of the multicast group.
+    pub unicast_peers: Vec<A>,
+    /// The real addresses of the interface, if known, to check the virtual and primary
+    /// addresses against.
+    pub interface_addresses: Vec<A>,
+    /// The IP MTU of the interface, if known, to check the advertisements fit in a frame.
+    pub mtu: Option<u16>,
+    /// Non-standard, for interop: treat a received Max Advertise Interval of 0 as 1
+    /// centisecond. The quirks of the IP header are tolerated by `wire::ParseContext`.
+    pub zero_interval_as_one_centi: bool,
 }
 
-impl Parameters {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigError<A: AddressFamily = Ipv4Addr> {
+    /// The advertisement interval must be between 1 and 4095 centiseconds.
+    AdvertisementInterval,
+    /// The advertised interval must be between the advertisement interval and 4095
+    /// centiseconds.
+    AdvertisedInterval,
+    /// The advertisement phase must be less than half the advertisement interval.
+    AdvertisementPhase,
+    /// Repeated gratuitous ARPs and shutdown advertisements need a non-zero spacing.
+    GarpSpacing,
+    /// A configuration lists the same VRID on the same interface twice.
+    DuplicateVirtualRouter { interface: InterfaceId, vrid: VRID },
+    /// The advertisement IP packet of `length` bytes does not fit in the interface MTU.
+    AdvertisementExceedsMtu { length: usize, mtu: u16 },
+    /// The address owner must have every virtual address as a real interface address.
+    OwnerAddressNotLocal(A),
+    /// The primary address of a Backup router must be a real interface address.
+    PrimaryAddressNotLocal(A),
+    /// `Mode::Owner` is forbidden by the `ConfigPolicy`.
+    OwnerForbidden,
+}
+
+/// Restrictions a deployment puts on the parameters on top of the RFC, see
+/// `Parameters::validate_with` and `RouterSet::with_config_policy`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConfigPolicy {
+    /// Reject `Mode::Owner`, so no router can claim priority 255.
+    pub forbid_owner: bool,
+}
+
+impl<A: AddressFamily> Parameters<A> {
     pub fn new(
         vrid: VRID,
-        virtual_addresses: VirtualAddresses,
-        mode: impl Into<Mode>,
+        virtual_addresses: VirtualAddresses<A>,
+        mode: impl Into<Mode<A>>,
     ) -> Self {
         Self {
             vrid,
+            interface: InterfaceId::default(),
             mode: mode.into(),
-            virtual_addresses: virtual_addresses.into(),
-            advertisement_interval: Interval::from_secs(100),
+            virtual_addresses,
+            advertisement_interval: Interval::from_centis(100),
+            advertised_interval: None,
+            garp_repeat: 0,
+            na_repeat: None,
+            garp_spacing: Interval::from_secs(1),
+            shutdown_repeat: 0,
+            verify_sender_mac: false,
+            drop_own_advertisements: false,
+            flap_damping: None,
+            startup_grace: None,
+            advertisement_phase: None,
+            accept_settle: None,
+            learn_master_interval: false,
+            advertise_priority_drop: false,
+            prepare_activate: false,
+            mac_tiebreak: None,
+            #[cfg(feature = "graceful-takeover")]
+            graceful_takeover: false,
+            owner_conflict: OwnerConflict::default(),
+            logging: false,
+            notify_state_changes: false,
+            notify_not_applicable: false,
+            defend_addresses: true,
+            broadcast_arp_replies: false,
+            neighbor_flags: NeighborFlags::default(),
+            arp_reply_limit: None,
+            source_ip: None,
+            unicast_peers: Vec::new(),
+            interface_addresses: Vec::new(),
+            mtu: None,
+            zero_interval_as_one_centi: false,
         }
     }
 
-    pub fn with_mode(self, mode: Mode) -> Self {
+    /// Takes the timings from the profile. Builders called afterwards override them.
+    pub fn with_profile(self, profile: Profile) -> Self {
+        profile.apply(self)
+    }
+
+    pub fn with_mode(self, mode: Mode<A>) -> Self {
         Self { mode, ..self }
     }
 
-    pub(crate) fn primary_ip(&self) -> Ipv4Addr {
+    pub fn with_interface(self, interface: InterfaceId) -> Self {
+        Self { interface, ..self }
+    }
+
+    pub fn with_advertised_interval(self, advertised_interval: Interval) -> Self {
+        Self {
+            advertised_interval: Some(advertised_interval),
+            ..self
+        }
+    }
+
+    pub fn with_advertisement_phase(self, advertisement_phase: Interval) -> Self {
+        Self {
+            advertisement_phase: Some(advertisement_phase),
+            ..self
+        }
+    }
+
+    pub fn with_garp_repeat(self, garp_repeat: u8) -> Self {
+        Self {
+            garp_repeat,
+            ..self
+        }
+    }
+
+    pub fn with_na_repeat(self, na_repeat: u8) -> Self {
+        Self {
+            na_repeat: Some(na_repeat),
+            ..self
+        }
+    }
+
+    pub fn with_garp_spacing(self, garp_spacing: Interval) -> Self {
+        Self {
+            garp_spacing,
+            ..self
+        }
+    }
+
+    pub fn with_shutdown_repeat(self, shutdown_repeat: u8) -> Self {
+        Self {
+            shutdown_repeat,
+            ..self
+        }
+    }
+
+    pub fn with_verify_sender_mac(self, verify_sender_mac: bool) -> Self {
+        Self {
+            verify_sender_mac,
+            ..self
+        }
+    }
+
+    pub fn with_drop_own_advertisements(self, drop_own_advertisements: bool) -> Self {
+        Self {
+            drop_own_advertisements,
+            ..self
+        }
+    }
+
+    pub fn with_flap_damping(self, flap_damping: FlapDamping) -> Self {
+        Self {
+            flap_damping: Some(flap_damping),
+            ..self
+        }
+    }
+
+    pub fn with_startup_grace(self, startup_grace: Interval) -> Self {
+        Self {
+            startup_grace: Some(startup_grace),
+            ..self
+        }
+    }
+
+    pub fn with_accept_settle(self, accept_settle: Interval) -> Self {
+        Self {
+            accept_settle: Some(accept_settle),
+            ..self
+        }
+    }
+
+    pub fn with_advertise_priority_drop(self, advertise_priority_drop: bool) -> Self {
+        Self {
+            advertise_priority_drop,
+            ..self
+        }
+    }
+
+    pub fn with_prepare_activate(self, prepare_activate: bool) -> Self {
+        Self {
+            prepare_activate,
+            ..self
+        }
+    }
+
+    /// See `Parameters::mac_tiebreak`.
+    pub fn with_mac_tiebreak(self, local_mac: MacAddr) -> Self {
+        Self {
+            mac_tiebreak: Some(local_mac),
+            ..self
+        }
+    }
+
+    /// See `Parameters::graceful_takeover`.
+    #[cfg(feature = "graceful-takeover")]
+    pub fn with_graceful_takeover(self, graceful_takeover: bool) -> Self {
+        Self {
+            graceful_takeover,
+            ..self
+        }
+    }
+
+    pub fn with_owner_conflict(self, owner_conflict: OwnerConflict) -> Self {
+        Self {
+            owner_conflict,
+            ..self
+        }
+    }
+
+    pub fn with_learn_master_interval(self, learn_master_interval: bool) -> Self {
+        Self {
+            learn_master_interval,
+            ..self
+        }
+    }
+
+    pub fn with_logging(self, logging: bool) -> Self {
+        Self { logging, ..self }
+    }
+
+    pub fn with_notify_state_changes(self, notify_state_changes: bool) -> Self {
+        Self {
+            notify_state_changes,
+            ..self
+        }
+    }
+
+    pub fn with_notify_not_applicable(self, notify_not_applicable: bool) -> Self {
+        Self {
+            notify_not_applicable,
+            ..self
+        }
+    }
+
+    pub fn with_arp_reply_limit(self, arp_reply_limit: ArpReplyLimit) -> Self {
+        Self {
+            arp_reply_limit: Some(arp_reply_limit),
+            ..self
+        }
+    }
+
+    pub fn with_broadcast_arp_replies(self, broadcast_arp_replies: bool) -> Self {
+        Self {
+            broadcast_arp_replies,
+            ..self
+        }
+    }
+
+    pub fn with_neighbor_flags(self, neighbor_flags: NeighborFlags) -> Self {
+        Self {
+            neighbor_flags,
+            ..self
+        }
+    }
+
+    pub fn with_defend_addresses(self, defend_addresses: bool) -> Self {
+        Self {
+            defend_addresses,
+            ..self
+        }
+    }
+
+    pub fn with_source_ip(self, source_ip: A) -> Self {
+        Self {
+            source_ip: Some(source_ip),
+            ..self
+        }
+    }
+
+    pub fn with_unicast_peers(self, unicast_peers: Vec<A>) -> Self {
+        Self {
+            unicast_peers,
+            ..self
+        }
+    }
+
+    pub fn with_interface_addresses(self, interface_addresses: Vec<A>) -> Self {
+        Self {
+            interface_addresses,
+            ..self
+        }
+    }
+
+    pub fn with_mtu(self, mtu: u16) -> Self {
+        Self {
+            mtu: Some(mtu),
+            ..self
+        }
+    }
+
+    pub fn with_zero_interval_as_one_centi(self, zero_interval_as_one_centi: bool) -> Self {
+        Self {
+            zero_interval_as_one_centi,
+            ..self
+        }
+    }
+
+    /// Checks the parameters, reporting every problem found rather than just the first.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError<A>>> {
+        self.validate_with(ConfigPolicy::default())
+    }
+
+    /// Like `validate`, also enforcing the policy.
+    pub fn validate_with(&self, policy: ConfigPolicy) -> Result<(), Vec<ConfigError<A>>> {
+        let mut errors = Vec::new();
+        if policy.forbid_owner && self.mode == Mode::Owner {
+            errors.push(ConfigError::OwnerForbidden);
+        }
+        if self.advertisement_interval.is_zero()
+            || self.advertisement_interval.as_centis() > Interval::MAX_WIRE_CENTIS as u32
+        {
+            errors.push(ConfigError::AdvertisementInterval);
+        }
+        if let Some(advertised_interval) = self.advertised_interval {
+            if advertised_interval.as_centis() > Interval::MAX_WIRE_CENTIS as u32
+                || Duration::from(advertised_interval) < Duration::from(self.advertisement_interval)
+            {
+                errors.push(ConfigError::AdvertisedInterval);
+            }
+        }
+        if let Some(phase) = self.advertisement_phase {
+            if Duration::from(phase) >= Duration::from(self.advertisement_interval / 2) {
+                errors.push(ConfigError::AdvertisementPhase);
+            }
+        }
+        let length = A::packet_len(self.virtual_addresses.len());
+        if let Some(mtu) = self.mtu.filter(|&mtu| length > mtu as usize) {
+            errors.push(ConfigError::AdvertisementExceedsMtu { length, mtu });
+        }
+        if (A::announcement_repeat(self) > 0 || self.shutdown_repeat > 0)
+            && self.garp_spacing.is_zero()
+        {
+            errors.push(ConfigError::GarpSpacing);
+        }
+        if !self.interface_addresses.is_empty() {
+            let is_local = |address| self.interface_addresses.contains(&address);
+            match &self.mode {
+                Mode::Owner => errors.extend(
+                    self.virtual_addresses
+                        .iter()
+                        .filter(|virtual_address| !is_local(virtual_address.address))
+                        .map(|virtual_address| {
+                            ConfigError::OwnerAddressNotLocal(virtual_address.address)
+                        }),
+                ),
+                Mode::Backup(BackupMode { primary_ip, .. }) if !is_local(*primary_ip) => {
+                    errors.push(ConfigError::PrimaryAddressNotLocal(*primary_ip))
+                }
+                Mode::Backup(_) | Mode::Monitor => {}
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// The Max Advertise Interval of advertisements, see `advertised_interval`.
+    pub fn advertised_interval(&self) -> Interval {
+        self.advertised_interval
+            .unwrap_or(self.advertisement_interval)
+    }
+
+    /// The address advertisements are sent from.
+    pub fn primary_ip(&self) -> A {
+        if let Some(source_ip) = self.source_ip {
+            return source_ip;
+        }
         match self.mode {
-            Mode::Owner => self.virtual_addresses.get(0).unwrap(),
+            Mode::Owner | Mode::Monitor => self.virtual_addresses.first(),
             Mode::Backup(BackupMode { primary_ip, .. }) => primary_ip,
         }
     }
 
-    pub(crate) fn priority(&self) -> u16 {
-        self.mode.priority().get() as u16
+    pub fn destination(&self) -> Destination<A> {
+        match self.unicast_peers.is_empty() {
+            true => Destination::Multicast,
+            false => Destination::Unicast(self.unicast_peers.clone()),
+        }
     }
 
     pub(crate) fn active_down_interval(&self, active_adver_interval: Interval) -> Interval {
-        3 * active_adver_interval + self.skew_time(active_adver_interval)
+        timing::active_down_interval(self.mode.priority(), active_adver_interval)
     }
 
     pub(crate) fn skew_time(&self, active_adver_interval: Interval) -> Interval {
-        ((256 - self.priority()) * active_adver_interval) / 256
+        timing::skew_time(self.mode.priority(), active_adver_interval)
     }
 
-    pub(crate) fn mac_address(&self) -> MacAddr {
-        self.vrid.into_mac_address()
+    /// The virtual router MAC address.
+    pub fn mac_address(&self) -> MacAddr {
+        A::mac_address(self.vrid)
     }
 }
@@ -0,0 +1,71 @@
+use crate::{AddressFamily, Interval, ReceivedPriority, Timestamp};
+use pnet_base::MacAddr;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+
+// Bounds the table when advertisements arrive from many, possibly spoofed, addresses.
+const MAX_PEERS: usize = 32;
+
+/// Another VRRP router observed advertising the same VRID, with the fields of its last
+/// advertisement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peer<A: AddressFamily = Ipv4Addr> {
+    pub primary_ip: A,
+    /// The source MAC address of its last advertisement with a priority; shutdown
+    /// advertisements do not carry one.
+    pub sender_mac: Option<MacAddr>,
+    /// The priority of its last advertisement, `None` for a shutdown advertisement.
+    pub priority: Option<ReceivedPriority>,
+    pub last_seen: Timestamp,
+    pub advertisements: u64,
+    pub advertisement_interval: Interval,
+}
+
+#[derive(Debug)]
+pub(crate) struct PeerTable<A: AddressFamily> {
+    peers: BTreeMap<A, Peer<A>>,
+}
+
+impl<A: AddressFamily> Default for PeerTable<A> {
+    fn default() -> Self {
+        Self {
+            peers: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: AddressFamily> PeerTable<A> {
+    pub(crate) fn record(
+        &mut self,
+        now: Timestamp,
+        primary_ip: A,
+        sender_mac: Option<MacAddr>,
+        priority: Option<ReceivedPriority>,
+        advertisement_interval: Interval,
+    ) {
+        if !self.peers.contains_key(&primary_ip) && self.peers.len() >= MAX_PEERS {
+            // Make room by forgetting the peer heard from least recently.
+            if let Some(stale) = self.peers.values().min_by_key(|peer| peer.last_seen) {
+                let stale = stale.primary_ip;
+                self.peers.remove(&stale);
+            }
+        }
+        let peer = self.peers.entry(primary_ip).or_insert(Peer {
+            primary_ip,
+            sender_mac,
+            priority,
+            last_seen: now,
+            advertisements: 0,
+            advertisement_interval,
+        });
+        peer.sender_mac = sender_mac.or(peer.sender_mac);
+        peer.priority = priority;
+        peer.last_seen = now;
+        peer.advertisements += 1;
+        peer.advertisement_interval = advertisement_interval;
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Peer<A>> {
+        self.peers.values()
+    }
+}
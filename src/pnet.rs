@@ -0,0 +1,106 @@
+use crate::{wire, ReceivedPacket};
+use pnet_base::MacAddr;
+use pnet_packet::arp::{ArpOperations, ArpPacket};
+use pnet_packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet_packet::ip::IpNextHeaderProtocols;
+use pnet_packet::ipv4::Ipv4Packet;
+use pnet_packet::Packet;
+
+/// Why a captured packet is not a `ReceivedPacket`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PacketError {
+    /// Neither IPv4 nor ARP.
+    EtherType(u16),
+    /// Too short for the header of its protocol.
+    Truncated,
+    /// An advertisement with a TTL other than 255, it may have been forwarded.
+    Ttl(u8),
+    Advertisement(wire::WireError),
+    /// Neither an ARP request nor reply.
+    ArpOperation(u16),
+}
+
+impl TryFrom<&EthernetPacket<'_>> for ReceivedPacket {
+    type Error = PacketError;
+
+    fn try_from(frame: &EthernetPacket<'_>) -> Result<Self, Self::Error> {
+        match frame.get_ethertype() {
+            EtherTypes::Ipv4 => {
+                let packet = Ipv4Packet::new(frame.payload()).ok_or(PacketError::Truncated)?;
+                ReceivedPacket::from_ipv4(frame.get_source(), frame.get_destination(), &packet)
+            }
+            EtherTypes::Arp => {
+                let packet = ArpPacket::new(frame.payload()).ok_or(PacketError::Truncated)?;
+                ReceivedPacket::try_from(&packet)
+            }
+            ether_type => Err(PacketError::EtherType(ether_type.0)),
+        }
+    }
+}
+
+impl TryFrom<&ArpPacket<'_>> for ReceivedPacket {
+    type Error = PacketError;
+
+    fn try_from(packet: &ArpPacket<'_>) -> Result<Self, Self::Error> {
+        let sender_mac = packet.get_sender_hw_addr();
+        let sender_ip = packet.get_sender_proto_addr();
+        let target_ip = packet.get_target_proto_addr();
+        match packet.get_operation() {
+            _ if sender_ip == target_ip => Ok(ReceivedPacket::GratuitousARP {
+                sender_mac,
+                sender_ip,
+            }),
+            ArpOperations::Request => Ok(ReceivedPacket::RequestARP {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            }),
+            ArpOperations::Reply => Ok(ReceivedPacket::ReplyARP {
+                sender_mac,
+                sender_ip,
+                target_mac: packet.get_target_hw_addr(),
+                target_ip,
+            }),
+            operation => Err(PacketError::ArpOperation(operation.0)),
+        }
+    }
+}
+
+impl ReceivedPacket {
+    /// Converts an IPv4 packet, given the MAC addresses of the Ethernet frame it came in.
+    pub fn from_ipv4(
+        source_mac: MacAddr,
+        destination_mac: MacAddr,
+        packet: &Ipv4Packet<'_>,
+    ) -> Result<Self, PacketError> {
+        let protocol = packet.get_next_level_protocol();
+        if protocol == IpNextHeaderProtocols::Vrrp {
+            if packet.get_ttl() != wire::TTL {
+                return Err(PacketError::Ttl(packet.get_ttl()));
+            }
+            let advertisement =
+                wire::decode(packet.payload()).map_err(PacketError::Advertisement)?;
+            return Ok(ReceivedPacket::advertisement(
+                source_mac,
+                packet.get_source(),
+                advertisement.priority,
+                advertisement.max_advertise_interval,
+            )
+            .with_takeover_intent(advertisement.takeover_intent));
+        }
+        // TCP and UDP both start with the source and destination ports.
+        let destination_port = match protocol {
+            IpNextHeaderProtocols::Tcp | IpNextHeaderProtocols::Udp => {
+                let ports = packet.payload().get(..4).ok_or(PacketError::Truncated)?;
+                Some(u16::from_be_bytes([ports[2], ports[3]]))
+            }
+            _ => None,
+        };
+        Ok(ReceivedPacket::IP {
+            target_mac: destination_mac,
+            target_ip: packet.get_destination(),
+            protocol: protocol.0,
+            destination_port,
+        })
+    }
+}
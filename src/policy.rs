@@ -0,0 +1,39 @@
+use crate::{AddressFamily, Priority, RoutePacket};
+use std::net::Ipv4Addr;
+
+/// Computes the priority a Backup router runs with from its configured priority, e.g. to
+/// lower it while a tracked interface is down or under load, or for an operator override.
+///
+/// The policy is queried on every input, so it can read external signals directly. Tracking
+/// weights are best applied with `Priority::saturating_sub` and `Priority::saturating_add`.
+pub trait PriorityPolicy {
+    fn priority(&self, configured: Priority) -> Priority;
+}
+
+impl<F: Fn(Priority) -> Priority> PriorityPolicy for F {
+    fn priority(&self, configured: Priority) -> Priority {
+        self(configured)
+    }
+}
+
+/// A received IP packet addressed to the virtual router MAC address, see `RoutePolicy`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IpPacketMeta<A: AddressFamily = Ipv4Addr> {
+    pub target_ip: A,
+    pub protocol: u8,
+    pub destination_port: Option<u16>,
+    /// What the router decided from the Accept_Mode, the virtual addresses and its state.
+    pub decision: RoutePacket,
+}
+
+/// Overrides how IP packets addressed to the virtual router are routed, e.g. for a firewall
+/// or load balancer. Consulted after the router's own checks, with their decision.
+pub trait RoutePolicy<A: AddressFamily = Ipv4Addr> {
+    fn route(&self, packet: &IpPacketMeta<A>) -> RoutePacket;
+}
+
+impl<A: AddressFamily, F: Fn(&IpPacketMeta<A>) -> RoutePacket> RoutePolicy<A> for F {
+    fn route(&self, packet: &IpPacketMeta<A>) -> RoutePacket {
+        self(packet)
+    }
+}
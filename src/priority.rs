@@ -1,10 +1,27 @@
+use crate::Error;
 use std::cmp::Ordering;
+use std::fmt;
 use std::num::NonZeroU8;
 
-#[derive(Clone, Copy, Debug, PartialOrd, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq)]
 pub struct Priority(NonZeroU8);
 
 impl Priority {
+    /// The greatest priority short of the address owner's 255.
+    pub const MAX: Self = Self(NonZeroU8::new(254).unwrap());
+
+    /// Lowers the priority by a tracking weight, to no less than 1: never the shutdown
+    /// priority 0.
+    pub fn saturating_sub(self, weight: u8) -> Self {
+        Self(NonZeroU8::new(self.0.get().saturating_sub(weight)).unwrap_or(NonZeroU8::MIN))
+    }
+
+    /// Raises the priority by a tracking weight, to no more than `Priority::MAX`: never the
+    /// owner priority 255.
+    pub fn saturating_add(self, weight: u8) -> Self {
+        Self(self.0.saturating_add(weight).min(Self::MAX.0))
+    }
+
     pub fn as_u16(&self) -> u16 {
         <Priority as Into<NonZeroU8>>::into(*self).get() as u16
     }
@@ -17,22 +34,20 @@ impl Default for Priority {
 }
 
 impl TryFrom<u8> for Priority {
-    type Error = ();
+    type Error = Error;
 
+    /// Fails for the shutdown priority 0 and the owner priority 255.
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        const SHUTDOWN: u8 = 0;
-        const OWNER: u8 = 255;
-
-        match value {
-            SHUTDOWN | OWNER => Err(()),
-            priority => Ok(Self(NonZeroU8::new(priority).unwrap())),
-        }
+        NonZeroU8::new(value)
+            .filter(|&priority| priority != NonZeroU8::MAX)
+            .map(Self)
+            .ok_or(Error::ReservedPriority(value))
     }
 }
 
-impl Into<NonZeroU8> for Priority {
-    fn into(self) -> NonZeroU8 {
-        self.0
+impl From<Priority> for NonZeroU8 {
+    fn from(priority: Priority) -> Self {
+        priority.0
     }
 }
 
@@ -47,3 +62,109 @@ impl PartialOrd<NonZeroU8> for Priority {
         self.0.partial_cmp(other)
     }
 }
+
+/// The priority carried by an advertisement, which unlike `Priority` can belong to the
+/// address owner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceivedPriority {
+    /// Priority 255, the sender owns the virtual addresses.
+    Owner255,
+    Normal(Priority),
+}
+
+impl TryFrom<u8> for ReceivedPriority {
+    type Error = Error;
+
+    /// Fails for priority 0, which is a shutdown advertisement.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            255 => Ok(Self::Owner255),
+            priority => Priority::try_from(priority).map(Self::Normal),
+        }
+    }
+}
+
+impl From<Priority> for ReceivedPriority {
+    fn from(priority: Priority) -> Self {
+        Self::Normal(priority)
+    }
+}
+
+impl From<ReceivedPriority> for NonZeroU8 {
+    fn from(priority: ReceivedPriority) -> Self {
+        match priority {
+            ReceivedPriority::Owner255 => NonZeroU8::MAX,
+            ReceivedPriority::Normal(priority) => priority.into(),
+        }
+    }
+}
+
+impl From<ReceivedPriority> for u8 {
+    fn from(priority: ReceivedPriority) -> Self {
+        NonZeroU8::from(priority).get()
+    }
+}
+
+impl Ord for ReceivedPriority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        NonZeroU8::from(*self).cmp(&NonZeroU8::from(*other))
+    }
+}
+
+impl PartialOrd for ReceivedPriority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The priority field of an advertisement on the wire, modelling the values `Priority`
+/// excludes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WirePriority {
+    /// Priority 0, the Active router is shutting down.
+    Shutdown,
+    Normal(Priority),
+    /// Priority 255, the sender owns the virtual addresses.
+    Owner,
+}
+
+impl From<u8> for WirePriority {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Shutdown,
+            255 => Self::Owner,
+            priority => Self::Normal(Priority(NonZeroU8::new(priority).unwrap())),
+        }
+    }
+}
+
+impl From<WirePriority> for u8 {
+    fn from(priority: WirePriority) -> Self {
+        match priority {
+            WirePriority::Shutdown => 0,
+            WirePriority::Normal(priority) => priority.0.get(),
+            WirePriority::Owner => u8::MAX,
+        }
+    }
+}
+
+impl From<Priority> for WirePriority {
+    fn from(priority: Priority) -> Self {
+        Self::Normal(priority)
+    }
+}
+
+impl From<ReceivedPriority> for WirePriority {
+    fn from(priority: ReceivedPriority) -> Self {
+        match priority {
+            ReceivedPriority::Owner255 => Self::Owner,
+            ReceivedPriority::Normal(priority) => Self::Normal(priority),
+        }
+    }
+}
+
+impl fmt::Display for WirePriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", u8::from(*self))
+    }
+}
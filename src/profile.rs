@@ -0,0 +1,65 @@
+use crate::{AddressFamily, Error, Interval, Parameters};
+use std::fmt;
+use std::str::FromStr;
+
+/// Named timings, so a fleet of virtual routers can share them instead of repeating the
+/// numbers per instance. See `Parameters::with_profile`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// The defaults of RFC 9568: advertisements every second, announced once.
+    #[default]
+    RfcDefault,
+    /// Advertisements every 100ms, with announcements repeated for sub-second failover.
+    FastFailover,
+    /// Advertisements every 2 seconds, with announcements repeated for lossy or busy links.
+    Conservative,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 3] = [
+        Profile::RfcDefault,
+        Profile::FastFailover,
+        Profile::Conservative,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::RfcDefault => "rfc-default",
+            Profile::FastFailover => "fast-failover",
+            Profile::Conservative => "conservative",
+        }
+    }
+
+    /// Sets the timings of the profile, keeping every other parameter.
+    pub fn apply<A: AddressFamily>(self, parameters: Parameters<A>) -> Parameters<A> {
+        let (advertisement_interval, garp_repeat, garp_spacing, shutdown_repeat) = match self {
+            Profile::RfcDefault => (Interval::from_centis(100), 0, Interval::from_secs(1), 0),
+            Profile::FastFailover => (Interval::from_centis(10), 2, Interval::from_centis(10), 2),
+            Profile::Conservative => (Interval::from_secs(2), 3, Interval::from_secs(2), 2),
+        };
+        Parameters {
+            advertisement_interval,
+            garp_repeat,
+            garp_spacing,
+            shutdown_repeat,
+            ..parameters
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Profile::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+            .ok_or(Error::UnknownProfile)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{ActionHandler, Clock, Input, Router, SystemClock, Timestamp};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Collects inputs from several threads, such as packet capture, timers and the control
+/// plane, and hands them to a `Router` in the order they happened.
+#[derive(Debug)]
+pub struct InputQueue<C = SystemClock> {
+    sender: InputSender<C>,
+    receiver: Receiver<(Timestamp, Input)>,
+    // Inputs are never handed out before this, so the router's time does not go backwards.
+    last: Option<Timestamp>,
+}
+
+/// Pushes inputs into an `InputQueue` from any thread without blocking.
+#[derive(Clone, Debug)]
+pub struct InputSender<C = SystemClock> {
+    sender: Sender<(Timestamp, Input)>,
+    clock: C,
+}
+
+impl<C: Clock> InputSender<C> {
+    /// Queues the input, timestamped now by the queue's clock.
+    pub fn push(&self, input: Input) {
+        self.push_at(self.clock.now(), input);
+    }
+
+    /// Queues the input with the time it happened, e.g. the capture timestamp of a packet.
+    pub fn push_at(&self, at: Timestamp, input: Input) {
+        // The queue owns a sender, so the receiver cannot have been dropped.
+        let _ = self.sender.send((at, input));
+    }
+}
+
+impl Default for InputQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputQueue {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock + Clone> InputQueue<C> {
+    /// A queue timestamping the inputs pushed without a time with `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender: InputSender { sender, clock },
+            receiver,
+            last: None,
+        }
+    }
+
+    pub fn sender(&self) -> InputSender<C> {
+        self.sender.clone()
+    }
+    pub fn push(&self, input: Input) {
+        self.sender.push(input);
+    }
+
+    /// Takes the queued inputs, ordered by when they happened, with the time to handle each
+    /// at: never later than `now`, nor earlier than an input handed out before.
+    pub fn drain(&mut self, now: Timestamp) -> impl Iterator<Item = (Timestamp, Input)> {
+        let mut inputs = self.receiver.try_iter().collect::<Vec<_>>();
+        // Stable, so inputs pushed at the same time keep their order.
+        inputs.sort_by_key(|(at, _)| *at);
+        let floor = self.last.map(|last| last.min(now));
+        let at = move |time: Timestamp| floor.map_or(time, |floor| time.max(floor)).min(now);
+        if let Some((last, _)) = inputs.last() {
+            self.last = Some(at(*last));
+        }
+        inputs
+            .into_iter()
+            .map(move |(time, input)| (at(time), input))
+    }
+
+    /// Handles the queued inputs in order, returning how many there were.
+    pub fn dispatch(
+        &mut self,
+        now: Timestamp,
+        router: &mut Router,
+        handler: &mut impl ActionHandler,
+    ) -> usize {
+        let mut count = 0;
+        for (at, input) in self.drain(now) {
+            router.dispatch(at, input, handler);
+            count += 1;
+        }
+        count
+    }
+}
@@ -1,25 +1,249 @@
-use crate::Interval;
+use crate::{AddressFamily, Interval, ReceivedPriority, WirePriority};
 use pnet_base::MacAddr;
+use std::fmt;
 use std::net::Ipv4Addr;
-use std::num::NonZeroU8;
 
-#[derive(Debug, PartialEq)]
-pub enum ReceivedPacket {
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReceivedPacket<A: AddressFamily = Ipv4Addr> {
     ShutdownAdvertisement {
+        sender_ip: A,
         max_advertise_interval: Interval,
     },
     Advertisement {
-        sender_ip: Ipv4Addr,
-        priority: NonZeroU8,
+        sender_mac: MacAddr,
+        sender_ip: A,
+        priority: ReceivedPriority,
+        max_advertise_interval: Interval,
+    },
+    /// An advertisement from a Backup about to preempt, see `Parameters::graceful_takeover`.
+    /// Handled like `Advertisement` by routers without the extension.
+    TakeoverIntent {
+        sender_mac: MacAddr,
+        sender_ip: A,
+        priority: ReceivedPriority,
         max_advertise_interval: Interval,
     },
+    /// IPv4 only, see `AddressFamily::ArpAddr`.
     RequestARP {
         sender_mac: MacAddr,
-        sender_ip: Ipv4Addr,
-        target_ip: Ipv4Addr,
+        sender_ip: A::ArpAddr,
+        target_ip: A::ArpAddr,
+    },
+    /// IPv4 only, see `AddressFamily::ArpAddr`.
+    ReplyARP {
+        sender_mac: MacAddr,
+        sender_ip: A::ArpAddr,
+        target_mac: MacAddr,
+        target_ip: A::ArpAddr,
+    },
+    /// IPv4 only, see `AddressFamily::ArpAddr`.
+    GratuitousARP {
+        sender_mac: MacAddr,
+        sender_ip: A::ArpAddr,
+    },
+    /// A Neighbor Solicitation for `target_ip`, the IPv6 counterpart of `RequestARP`. A
+    /// `sender_ip` of `::` is a Duplicate Address Detection probe.
+    NeighborSolicitation {
+        sender_mac: MacAddr,
+        sender_ip: A::NdAddr,
+        target_ip: A::NdAddr,
+    },
+    /// A solicited or unsolicited Neighbor Advertisement that `sender_ip` is at
+    /// `sender_mac`, the IPv6 counterpart of `ReplyARP` and `GratuitousARP`.
+    NeighborAdvertisement {
+        sender_mac: MacAddr,
+        sender_ip: A::NdAddr,
     },
     IP {
         target_mac: MacAddr,
-        target_ip: Ipv4Addr,
+        target_ip: A,
+        /// The IP protocol number, e.g. 1 for ICMP.
+        protocol: u8,
+        /// The destination port for TCP and UDP.
+        destination_port: Option<u16>,
     },
 }
+
+impl<A: AddressFamily> fmt::Display for ReceivedPacket<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceivedPacket::ShutdownAdvertisement {
+                sender_ip,
+                max_advertise_interval,
+            } => write!(
+                f,
+                "ADVERTISEMENT from {sender_ip} prio=0 interval={max_advertise_interval}"
+            ),
+            ReceivedPacket::Advertisement {
+                sender_mac,
+                sender_ip,
+                priority,
+                max_advertise_interval,
+            } => write!(
+                f,
+                "ADVERTISEMENT from {sender_ip} ({sender_mac}) prio={} interval={max_advertise_interval}",
+                u8::from(*priority)
+            ),
+            ReceivedPacket::TakeoverIntent {
+                sender_mac,
+                sender_ip,
+                priority,
+                max_advertise_interval,
+            } => write!(
+                f,
+                "TAKEOVER-INTENT from {sender_ip} ({sender_mac}) prio={} interval={max_advertise_interval}",
+                u8::from(*priority)
+            ),
+            ReceivedPacket::RequestARP {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            } => write!(f, "ARP who-has {target_ip} tell {sender_ip} ({sender_mac})"),
+            ReceivedPacket::ReplyARP {
+                sender_mac,
+                sender_ip,
+                target_mac,
+                target_ip,
+            } => write!(
+                f,
+                "ARP REPLY {sender_ip} is-at {sender_mac} to {target_ip} ({target_mac})"
+            ),
+            ReceivedPacket::GratuitousARP {
+                sender_mac,
+                sender_ip,
+            } => write!(f, "GARP {sender_ip} is-at {sender_mac}"),
+            ReceivedPacket::NeighborSolicitation {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            } => write!(f, "NS who-has {target_ip} tell {sender_ip} ({sender_mac})"),
+            ReceivedPacket::NeighborAdvertisement {
+                sender_mac,
+                sender_ip,
+            } => write!(f, "NA {sender_ip} is-at {sender_mac}"),
+            ReceivedPacket::IP {
+                target_mac,
+                target_ip,
+                protocol,
+                destination_port,
+            } => {
+                write!(f, "IP to {target_ip} ({target_mac}) proto={protocol}")?;
+                match destination_port {
+                    Some(port) => write!(f, " port={port}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl<A: AddressFamily> ReceivedPacket<A> {
+    /// Builds the packet for a parsed advertisement, mapping `WirePriority::Shutdown` to a
+    /// `ShutdownAdvertisement` as the Active router does when it abdicates.
+    pub fn advertisement(
+        sender_mac: MacAddr,
+        sender_ip: A,
+        priority: impl Into<WirePriority>,
+        max_advertise_interval: Interval,
+    ) -> Self {
+        let priority = match priority.into() {
+            WirePriority::Shutdown => {
+                return Self::ShutdownAdvertisement {
+                    sender_ip,
+                    max_advertise_interval,
+                }
+            }
+            WirePriority::Normal(priority) => ReceivedPriority::Normal(priority),
+            WirePriority::Owner => ReceivedPriority::Owner255,
+        };
+        Self::Advertisement {
+            sender_mac,
+            sender_ip,
+            priority,
+            max_advertise_interval,
+        }
+    }
+
+    /// Turns an `Advertisement` into a `TakeoverIntent`, for an advertisement that had
+    /// `wire::TAKEOVER_INTENT` set.
+    pub fn with_takeover_intent(self, takeover_intent: bool) -> Self {
+        match self {
+            ReceivedPacket::Advertisement {
+                sender_mac,
+                sender_ip,
+                priority,
+                max_advertise_interval,
+            } if takeover_intent => ReceivedPacket::TakeoverIntent {
+                sender_mac,
+                sender_ip,
+                priority,
+                max_advertise_interval,
+            },
+            packet => packet,
+        }
+    }
+
+    /// The ARP and Neighbor Discovery packets, alike for both families.
+    pub(crate) fn neighbor(&self) -> Option<Neighbor<A>> {
+        match *self {
+            ReceivedPacket::RequestARP {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            } => Some(Neighbor::Request {
+                sender_mac,
+                sender_ip: sender_ip.into(),
+                target_ip: target_ip.into(),
+            }),
+            ReceivedPacket::NeighborSolicitation {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            } => Some(Neighbor::Request {
+                sender_mac,
+                sender_ip: sender_ip.into(),
+                target_ip: target_ip.into(),
+            }),
+            ReceivedPacket::ReplyARP {
+                sender_mac,
+                sender_ip,
+                ..
+            }
+            | ReceivedPacket::GratuitousARP {
+                sender_mac,
+                sender_ip,
+            } => Some(Neighbor::Claim {
+                sender_mac,
+                sender_ip: sender_ip.into(),
+            }),
+            ReceivedPacket::NeighborAdvertisement {
+                sender_mac,
+                sender_ip,
+            } => Some(Neighbor::Claim {
+                sender_mac,
+                sender_ip: sender_ip.into(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) enum Neighbor<A> {
+    /// An ARP request or Neighbor Solicitation for `target_ip`.
+    Request {
+        sender_mac: MacAddr,
+        sender_ip: A,
+        target_ip: A,
+    },
+    /// An ARP reply, gratuitous ARP or Neighbor Advertisement that `sender_ip` is at
+    /// `sender_mac`.
+    Claim { sender_mac: MacAddr, sender_ip: A },
+}
+
+impl<A: Copy> Neighbor<A> {
+    pub(crate) fn sender_ip(&self) -> A {
+        match *self {
+            Neighbor::Request { sender_ip, .. } | Neighbor::Claim { sender_ip, .. } => sender_ip,
+        }
+    }
+}
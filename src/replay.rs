@@ -0,0 +1,295 @@
+//! Records the inputs fed to a router together with the actions it emitted, so an incident
+//! can be reproduced deterministically from the log.
+//!
+//! A log is plain text with one input per line, e.g. `3609375 timer`, prefixed with its
+//! offset in microseconds from the first input. The `Debug` representation of every action
+//! it produced follows on its own line, starting with a tab.
+//!
+//! The parameters are not part of the log, replay on a router built from the same ones.
+
+use crate::Timestamp;
+use crate::{Action, ActionHandler, Command, Input, Interval, ReceivedPacket, Router};
+use pnet_base::MacAddr;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayLog {
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// The offset from the first recorded input.
+    pub at: Duration,
+    pub input: Input,
+    /// The `Debug` representation of every emitted action.
+    pub actions: Vec<String>,
+}
+
+/// A line of a log that could not be parsed, counting from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+}
+
+/// The router emitted different actions than recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// The index of the entry that diverged.
+    pub entry: usize,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+/// Wraps `Router::dispatch`, recording every input and the resulting actions.
+#[derive(Default)]
+pub struct Recorder {
+    start: Option<Timestamp>,
+    log: ReplayLog,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dispatch(
+        &mut self,
+        router: &mut Router,
+        now: Timestamp,
+        input: Input,
+        handler: &mut impl ActionHandler,
+    ) {
+        // A late packet is recorded as a packet at the time it was received, which is how
+        // the router evaluates it.
+        let (now, input) = input.resolve(now);
+        let start = *self.start.get_or_insert(now);
+        let mut actions = Vec::new();
+        for action in router.handle_input(now, input.clone()) {
+            actions.push(format!("{action:?}"));
+            handler.handle(action);
+        }
+        self.log.entries.push(Entry {
+            at: now.saturating_duration_since(start),
+            input,
+            actions,
+        });
+    }
+
+    pub fn log(&self) -> &ReplayLog {
+        &self.log
+    }
+
+    pub fn into_log(self) -> ReplayLog {
+        self.log
+    }
+}
+
+impl ReplayLog {
+    /// Feeds the recorded inputs to the router, with `start` standing in for the time of
+    /// the first input, and stops at the first entry whose actions differ from the log.
+    pub fn replay(&self, router: &mut Router, start: Timestamp) -> Result<(), Divergence> {
+        for (index, entry) in self.entries.iter().enumerate() {
+            let actual: Vec<String> = router
+                .handle_input(start + entry.at, entry.input.clone())
+                .map(|action: Action| format!("{action:?}"))
+                .collect();
+            if actual != entry.actions {
+                return Err(Divergence {
+                    entry: index,
+                    expected: entry.actions.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ReplayLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            write!(f, "{} ", entry.at.as_micros())?;
+            write_input(f, &entry.input)?;
+            writeln!(f)?;
+            for action in &entry.actions {
+                writeln!(f, "\t{action}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ReplayLog {
+    type Err = ParseError;
+
+    fn from_str(log: &str) -> Result<Self, Self::Err> {
+        let mut entries: Vec<Entry> = Vec::new();
+        for (index, line) in log.lines().enumerate() {
+            let error = ParseError { line: index + 1 };
+            if let Some(action) = line.strip_prefix('\t') {
+                entries
+                    .last_mut()
+                    .ok_or(error)?
+                    .actions
+                    .push(action.to_owned());
+            } else if !line.is_empty() {
+                let mut fields = line.split(' ');
+                let at = parse_field(&mut fields).map(Duration::from_micros);
+                let input = parse_input(&mut fields);
+                match (at, input, fields.next()) {
+                    (Some(at), Some(input), None) => entries.push(Entry {
+                        at,
+                        input,
+                        actions: Vec::new(),
+                    }),
+                    _ => return Err(error),
+                }
+            }
+        }
+        Ok(Self { entries })
+    }
+}
+
+fn micros(interval: Interval) -> u128 {
+    Duration::from(interval).as_micros()
+}
+
+fn write_input(f: &mut fmt::Formatter<'_>, input: &Input) -> fmt::Result {
+    match input {
+        Input::Command(Command::Startup) => write!(f, "startup"),
+        Input::Command(Command::Shutdown) => write!(f, "shutdown"),
+        Input::Command(Command::Restart) => write!(f, "restart"),
+        Input::Command(Command::InterfaceDown) => write!(f, "interface-down"),
+        Input::Timer => write!(f, "timer"),
+        // Only written when the entry was not recorded, the receive time is lost.
+        Input::LatePacket { packet, .. } => write_input(f, &Input::Packet(packet.clone())),
+        Input::Packet(ReceivedPacket::ShutdownAdvertisement {
+            sender_ip,
+            max_advertise_interval,
+        }) => write!(
+            f,
+            "shutdown-advertisement {sender_ip} {}",
+            micros(*max_advertise_interval)
+        ),
+        Input::Packet(ReceivedPacket::Advertisement {
+            sender_mac,
+            sender_ip,
+            priority,
+            max_advertise_interval,
+        }) => write!(
+            f,
+            "advertisement {sender_mac} {sender_ip} {} {}",
+            u8::from(*priority),
+            micros(*max_advertise_interval)
+        ),
+        Input::Packet(ReceivedPacket::TakeoverIntent {
+            sender_mac,
+            sender_ip,
+            priority,
+            max_advertise_interval,
+        }) => write!(
+            f,
+            "takeover-intent {sender_mac} {sender_ip} {} {}",
+            u8::from(*priority),
+            micros(*max_advertise_interval)
+        ),
+        Input::Packet(ReceivedPacket::RequestARP {
+            sender_mac,
+            sender_ip,
+            target_ip,
+        }) => write!(f, "request-arp {sender_mac} {sender_ip} {target_ip}"),
+        Input::Packet(ReceivedPacket::ReplyARP {
+            sender_mac,
+            sender_ip,
+            target_mac,
+            target_ip,
+        }) => write!(
+            f,
+            "reply-arp {sender_mac} {sender_ip} {target_mac} {target_ip}"
+        ),
+        Input::Packet(ReceivedPacket::GratuitousARP {
+            sender_mac,
+            sender_ip,
+        }) => write!(f, "gratuitous-arp {sender_mac} {sender_ip}"),
+        Input::Packet(
+            ReceivedPacket::NeighborSolicitation { sender_ip, .. }
+            | ReceivedPacket::NeighborAdvertisement { sender_ip, .. },
+        ) => match *sender_ip {},
+        Input::Packet(ReceivedPacket::IP {
+            target_mac,
+            target_ip,
+            protocol,
+            destination_port,
+        }) => {
+            write!(f, "ip {target_mac} {target_ip} {protocol} ")?;
+            match destination_port {
+                Some(port) => write!(f, "{port}"),
+                None => write!(f, "-"),
+            }
+        }
+    }
+}
+
+fn parse_field<'a, T: FromStr>(fields: &mut impl Iterator<Item = &'a str>) -> Option<T> {
+    fields.next()?.parse().ok()
+}
+
+fn parse_interval<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Interval> {
+    parse_field(fields).map(|micros| Interval::from_duration(Duration::from_micros(micros)))
+}
+
+fn parse_input<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Input> {
+    let packet = match fields.next()? {
+        "startup" => return Some(Command::Startup.into()),
+        "shutdown" => return Some(Command::Shutdown.into()),
+        "restart" => return Some(Command::Restart.into()),
+        "interface-down" => return Some(Command::InterfaceDown.into()),
+        "timer" => return Some(Input::Timer),
+        "shutdown-advertisement" => ReceivedPacket::ShutdownAdvertisement {
+            sender_ip: parse_field::<Ipv4Addr>(fields)?,
+            max_advertise_interval: parse_interval(fields)?,
+        },
+        "advertisement" => ReceivedPacket::Advertisement {
+            sender_mac: parse_field::<MacAddr>(fields)?,
+            sender_ip: parse_field(fields)?,
+            priority: parse_field::<u8>(fields)?.try_into().ok()?,
+            max_advertise_interval: parse_interval(fields)?,
+        },
+        "takeover-intent" => ReceivedPacket::TakeoverIntent {
+            sender_mac: parse_field::<MacAddr>(fields)?,
+            sender_ip: parse_field(fields)?,
+            priority: parse_field::<u8>(fields)?.try_into().ok()?,
+            max_advertise_interval: parse_interval(fields)?,
+        },
+        "request-arp" => ReceivedPacket::RequestARP {
+            sender_mac: parse_field(fields)?,
+            sender_ip: parse_field(fields)?,
+            target_ip: parse_field(fields)?,
+        },
+        "reply-arp" => ReceivedPacket::ReplyARP {
+            sender_mac: parse_field(fields)?,
+            sender_ip: parse_field(fields)?,
+            target_mac: parse_field(fields)?,
+            target_ip: parse_field(fields)?,
+        },
+        "gratuitous-arp" => ReceivedPacket::GratuitousARP {
+            sender_mac: parse_field(fields)?,
+            sender_ip: parse_field(fields)?,
+        },
+        "ip" => ReceivedPacket::IP {
+            target_mac: parse_field(fields)?,
+            target_ip: parse_field(fields)?,
+            protocol: parse_field(fields)?,
+            destination_port: match fields.next()? {
+                "-" => None,
+                port => Some(port.parse().ok()?),
+            },
+        },
+        _ => return None,
+    };
+    Some(packet.into())
+}
@@ -1,61 +1,359 @@
-use crate::actions::Actions;
+use crate::actions::{Actions, ShutdownActive, TransitionToActive};
+use crate::arp_limit::ArpReplyLimiter;
+use crate::flap::FlapDetector;
+use crate::history::History;
+use crate::log::LogLimiter;
+use crate::peers::PeerTable;
+use crate::received::Neighbor;
 use crate::{
-    Action, BackupMode, Command, Input, Interval, Mode, Parameters, ReceivedPacket, RoutePacket,
-    SendPacket,
+    timing, Action, ActionHandler, AddressFamily, BackupMode, Command, ConfigError, Event, Input,
+    Interval, Level, LogEvent, Mode, OwnerConflict, Parameters, PreemptSuppression, Priority,
+    ReceivedPacket, ReceivedPriority, RoutePacket, SendPacket, Stats,
+};
+use crate::{
+    IpPacketMeta, Peer, PriorityPolicy, RoutePolicy, Timestamp, Transition, TransitionCause,
+    TransitionReason, VirtualAddresses, VRID,
 };
 use pnet_base::MacAddr;
 use std::cmp::Ordering;
+use std::fmt;
 use std::net::Ipv4Addr;
 use std::num::NonZeroU8;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::Duration;
+
+// How often `Event::PreemptSuppressed` is repeated while preemption stays suppressed.
+const PREEMPT_SUPPRESSED_REPEAT: Duration = Duration::from_secs(60);
 
-pub struct Router {
+pub struct Router<A: AddressFamily = Ipv4Addr> {
     mac_address: MacAddr,
-    parameters: Parameters,
-    state: State,
+    parameters: Arc<Parameters<A>>,
+    state: State<A>,
+    stats: Stats,
+    flaps: FlapDetector,
+    arp_replies: ArpReplyLimiter,
+    // Waiting for the first advertisement to learn the Active router's interval.
+    learning_interval: bool,
+    // The sender and arrival of the last advertisement received in Backup.
+    peer: Option<(A, Timestamp)>,
+    // When `Event::PreemptSuppressed` was last emitted, and why.
+    preempt_suppressed: Option<(Timestamp, PreemptSuppression)>,
+    // The sender and interval of the last `Event::ConfigConflict`, only repeated once they change.
+    config_conflict: Option<(A, Interval)>,
+    // The end of the startup grace period, see `Parameters::startup_grace`.
+    grace_until: Option<Timestamp>,
+    // When to hand over and for how long not to preempt after, see `schedule_failover`.
+    scheduled_failover: Option<(Timestamp, Interval)>,
+    // The end of the preemption hold of the last scheduled failover.
+    failover_hold_until: Option<Timestamp>,
+    // Assigned by `RouterSet` when the parameters have no `advertisement_phase`.
+    advertisement_phase: Option<Interval>,
+    // The Active router a Backup announced it is taking over from, see
+    // `Parameters::graceful_takeover`.
+    claiming: Option<A>,
+    // `Action::PrepareActivate` was emitted for the current down timer.
+    preparing: bool,
+    // The down timer was shortened by a shutdown advertisement.
+    abdicated: bool,
+    logs: LogLimiter,
+    peers: PeerTable<A>,
+    history: History<A>,
+    priority_policy: Option<Box<dyn PriorityPolicy + Send>>,
+    // The priority from the parameters, before the policy was applied to them.
+    configured_priority: Option<Priority>,
+    route_policy: Option<Box<dyn RoutePolicy<A> + Send>>,
 }
 
-impl Router {
-    pub fn new(parameters: Parameters) -> Self {
+impl<A: AddressFamily> Router<A> {
+    pub fn new(parameters: Parameters<A>) -> Self {
         Self {
             mac_address: parameters.mac_address(),
-            parameters,
+            parameters: Arc::new(parameters),
             state: State::Initialized,
+            stats: Stats::default(),
+            flaps: FlapDetector::default(),
+            arp_replies: ArpReplyLimiter::default(),
+            learning_interval: false,
+            peer: None,
+            preempt_suppressed: None,
+            config_conflict: None,
+            grace_until: None,
+            scheduled_failover: None,
+            failover_hold_until: None,
+            advertisement_phase: None,
+            claiming: None,
+            preparing: false,
+            abdicated: false,
+            logs: LogLimiter::default(),
+            peers: PeerTable::default(),
+            history: History::default(),
+            priority_policy: None,
+            configured_priority: None,
+            route_policy: None,
+        }
+    }
+
+    /// Derives the priority of a Backup router from its configured one with `policy`.
+    pub fn with_priority_policy(self, policy: impl PriorityPolicy + Send + 'static) -> Self {
+        let mut router = Self {
+            priority_policy: Some(Box::new(policy)),
+            ..self
+        };
+        router.configured_priority = router.backup_priority();
+        router.apply_priority_policy();
+        router
+    }
+
+    /// Lets `policy` override the routing of the IP packets addressed to the virtual router.
+    pub fn with_route_policy(self, policy: impl RoutePolicy<A> + Send + 'static) -> Self {
+        Self {
+            route_policy: Some(Box::new(policy)),
+            ..self
         }
     }
 
-    pub fn state(&self) -> &State {
+    pub fn state(&self) -> &State<A> {
         &self.state
     }
 
-    pub fn next_timer(&self, now: Instant) -> Instant {
-        match &self.state {
+    /// Whether the command does anything in the current state; when it does not, handling it
+    /// only emits `Event::NotApplicable`.
+    pub fn accepts(&self, command: Command) -> bool {
+        match (&self.state, command) {
+            (State::Initialized, Command::Startup | Command::Restart) => true,
+            (State::Initialized, Command::Shutdown | Command::InterfaceDown) => false,
+            (State::ShuttingDown { .. }, Command::Startup | Command::Restart) => true,
+            (State::ShuttingDown { .. }, Command::Shutdown | Command::InterfaceDown) => false,
+            (
+                State::Backup { .. } | State::Active { .. } | State::Monitoring { .. },
+                Command::Startup,
+            ) => false,
+            (
+                State::Backup { .. } | State::Active { .. } | State::Monitoring { .. },
+                Command::Shutdown | Command::Restart | Command::InterfaceDown,
+            ) => true,
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// The other routers heard advertising this VRID, ordered by primary address.
+    pub fn peers(&self) -> impl Iterator<Item = &Peer<A>> {
+        self.peers.iter()
+    }
+
+    /// The most recent state transitions, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &Transition<A>> {
+        self.history.iter()
+    }
+
+    /// The virtual MAC address the router answers for.
+    pub fn mac_address(&self) -> MacAddr {
+        self.mac_address
+    }
+
+    pub fn vrid(&self) -> VRID {
+        self.parameters.vrid
+    }
+
+    pub fn virtual_addresses(&self) -> &VirtualAddresses<A> {
+        &self.parameters.virtual_addresses
+    }
+
+    /// The parameters in effect, with the priority given by the priority policy, if any.
+    pub fn parameters(&self) -> &Parameters<A> {
+        &self.parameters
+    }
+
+    /// Replaces the parameters of the virtual router in place, rejecting invalid ones. The new
+    /// parameters take effect from the next input.
+    ///
+    /// When the virtual MAC changes while Active, the announcement sequence is sent again so
+    /// switches relearn which port the virtual MAC is behind. See also
+    /// `Parameters::advertise_priority_drop`.
+    ///
+    /// A change of `mode` takes effect at once: a router that becomes the owner transitions
+    /// to Active without waiting out the down timer, and an Active router switched to
+    /// `Mode::Monitor` releases its addresses and only watches from then on.
+    pub fn update_parameters(
+        &mut self,
+        now: Timestamp,
+        parameters: Parameters<A>,
+    ) -> Result<impl Iterator<Item = Action<A>> + 'static, Vec<ConfigError<A>>> {
+        parameters.validate()?;
+        Ok(self.replace_parameters(now, parameters))
+    }
+
+    // `update_parameters` for parameters the caller already validated, e.g. with the
+    // `ConfigPolicy` of a `RouterSet`.
+    pub(crate) fn replace_parameters(
+        &mut self,
+        now: Timestamp,
+        parameters: Parameters<A>,
+    ) -> Actions<A> {
+        let mac_changed = parameters.mac_address() != self.mac_address;
+        let priority = self.parameters.mode.priority();
+        self.mac_address = parameters.mac_address();
+        self.parameters = Arc::new(parameters);
+        self.configured_priority = self.backup_priority();
+        self.apply_priority_policy();
+        match (&self.state, &self.parameters.mode) {
+            (State::Backup { .. } | State::Monitoring { .. }, Mode::Owner) => {
+                return self.transition_to_active(now);
+            }
+            (State::Active { .. }, Mode::Monitor) => return self.step_down_to_monitoring(),
+            (State::Backup { .. }, Mode::Monitor) => {
+                self.state = State::Monitoring { active: None };
+                self.peer = None;
+                return self.stop_preparing();
+            }
+            (State::Monitoring { .. }, Mode::Backup(_)) => return self.start(now),
+            (State::Active { .. }, _) => {}
+            _ => return Actions::NONE,
+        }
+        if !mac_changed {
+            if self.parameters.advertise_priority_drop && self.parameters.mode.priority() < priority
+            {
+                return self.send_advertisment(now);
+            }
+            return Actions::NONE;
+        }
+        self.enter_active(now);
+        Actions::transition_to_active(self.parameters.clone(), TransitionToActive::Advertisment)
+    }
+
+    pub fn next_timer(&self, now: Timestamp) -> Timestamp {
+        let timer = match &self.state {
             State::Initialized => now + self.parameters.advertisement_interval,
             State::Backup {
                 active_down_timer, ..
-            } => *active_down_timer,
-            State::Active { adver_timer } => *adver_timer,
+            } => match self.prepare_at() {
+                Some(prepare_at) if !self.preparing => prepare_at.min(*active_down_timer),
+                _ => *active_down_timer,
+            },
+            State::Active {
+                adver_timer,
+                garp_timer,
+                ..
+            } => match garp_timer {
+                Some(garp_timer) => (*adver_timer).min(garp_timer.at),
+                None => *adver_timer,
+            },
+            State::Monitoring {
+                active: Some(active),
+            } => active.active_down_timer,
+            State::Monitoring { active: None } => now + self.parameters.advertisement_interval,
+            State::ShuttingDown { repeat } => repeat.at,
+        };
+        match self.scheduled_failover {
+            Some((at, _)) => timer.min(at),
+            None => timer,
+        }
+    }
+
+    /// Hands over gracefully at `at`, as for a Restart command: an Active router sends a
+    /// shutdown advertisement and becomes a Backup, e.g. before planned maintenance. From
+    /// then on it does not preempt for `hold`, so the new Active router keeps the role while
+    /// the maintenance goes on. Replaces an earlier schedule; the address owner never hands over.
+    pub fn schedule_failover(&mut self, at: Timestamp, hold: Interval) {
+        self.scheduled_failover = Some((at, hold));
+    }
+
+    pub fn cancel_scheduled_failover(&mut self) {
+        self.scheduled_failover = None;
+    }
+
+    pub fn scheduled_failover(&self) -> Option<Timestamp> {
+        self.scheduled_failover.map(|(at, _)| at)
+    }
+
+    /// Handles the input and passes every resulting action to the handler.
+    pub fn dispatch(
+        &mut self,
+        now: Timestamp,
+        input: Input<A>,
+        handler: &mut impl ActionHandler<A>,
+    ) {
+        for action in self.handle_input(now, input) {
+            handler.handle(action);
         }
     }
 
     pub fn handle_input(
         &mut self,
-        now: Instant,
-        input: Input,
-    ) -> impl Iterator<Item = Action> + '_ {
-        match &self.state {
+        now: Timestamp,
+        input: Input<A>,
+    ) -> impl Iterator<Item = Action<A>> + 'static {
+        let (now, input) = input.resolve(now);
+        let (input, takeover_intent) = self.takeover_intent(input);
+        let input = self.compat_input(input);
+        let input = self.scheduled_failover_input(now, input);
+        self.apply_priority_policy();
+        if self.parameters.drop_own_advertisements && self.is_own_advertisement(&input) {
+            self.stats.own_advertisements_dropped += 1;
+            return Actions::NONE;
+        }
+        if let Input::Packet(
+            ReceivedPacket::Advertisement { .. } | ReceivedPacket::ShutdownAdvertisement { .. },
+        ) = input
+        {
+            if self.state != State::Initialized {
+                self.stats.advertisements_received += 1;
+                self.record_peer(now, &input);
+            }
+        }
+        if let Input::Packet(ReceivedPacket::Advertisement { sender_mac, .. }) = input {
+            if self.state != State::Initialized && self.rejects_sender_mac(sender_mac) {
+                return self.log(
+                    now,
+                    Level::Warning,
+                    LogEvent::RejectedAdvertisement { sender_mac },
+                );
+            }
+        }
+
+        if let Input::Command(command) = input {
+            if !self.accepts(command) {
+                let state = self.state.name();
+                return match self.parameters.notify_not_applicable {
+                    true => Action::Notify(Event::NotApplicable { command, state }).into(),
+                    false => Actions::NONE,
+                };
+            }
+        }
+
+        let from = self.state.name();
+        let cause = TransitionCause::from(&input);
+        let actions = match &self.state {
             State::Initialized => match input {
-                Input::Command(Command::Startup) => self.startup(now),
-                Input::Command(Command::Shutdown) => Actions::None,
-                Input::Timer => Actions::None,
-                Input::Packet(ReceivedPacket::ShutdownAdvertisement { .. }) => Actions::None,
-                Input::Packet(ReceivedPacket::Advertisement { .. }) => Actions::None,
-                Input::Packet(ReceivedPacket::RequestARP { .. }) => Actions::None,
+                Input::Command(Command::Startup | Command::Restart) => self.startup(now),
+                Input::Command(Command::Shutdown | Command::InterfaceDown) => {
+                    unreachable!("not applicable, see accepts")
+                }
+                Input::Timer => Actions::NONE,
+                Input::Packet(ReceivedPacket::ShutdownAdvertisement { .. }) => Actions::NONE,
+                Input::LatePacket { .. } => unreachable!("resolved above"),
+                Input::Packet(ReceivedPacket::TakeoverIntent { .. }) => {
+                    unreachable!("resolved above")
+                }
+                Input::Packet(ReceivedPacket::Advertisement { .. }) => Actions::NONE,
+                Input::Packet(
+                    ReceivedPacket::RequestARP { .. }
+                    | ReceivedPacket::ReplyARP { .. }
+                    | ReceivedPacket::GratuitousARP { .. }
+                    | ReceivedPacket::NeighborSolicitation { .. }
+                    | ReceivedPacket::NeighborAdvertisement { .. },
+                ) => Actions::NONE,
                 Input::Packet(ReceivedPacket::IP { .. }) => RoutePacket::Reject.into(),
             },
-            State::Active { adver_timer } => match input {
-                Input::Command(Command::Shutdown) => self.shutdown_active(),
-                Input::Command(Command::Startup) => Actions::None,
+            State::Active { .. } => match input {
+                Input::Command(Command::Shutdown | Command::InterfaceDown) => {
+                    self.shutdown_active(now)
+                }
+                Input::Command(Command::Startup) => unreachable!("not applicable, see accepts"),
+                Input::Command(Command::Restart) => self.restart(now),
                 Input::Packet(ReceivedPacket::ShutdownAdvertisement { .. }) => {
                     self.send_advertisment(now)
                 }
@@ -63,211 +361,1178 @@ impl Router {
                     sender_ip,
                     priority,
                     max_advertise_interval: active_adver_interval,
+                    ..
+                }) if takeover_intent
+                    && NonZeroU8::from(priority) > self.parameters.mode.priority() =>
+                {
+                    self.yield_to_takeover(now, sender_ip, active_adver_interval)
+                }
+                Input::Packet(ReceivedPacket::Advertisement {
+                    sender_mac,
+                    sender_ip,
+                    priority,
+                    max_advertise_interval: active_adver_interval,
                 }) => self.handle_active_advertisement(
                     now,
+                    sender_mac,
                     sender_ip,
                     priority,
                     active_adver_interval,
                 ),
-                Input::Timer if now >= *adver_timer => self.send_advertisment(now),
-                Input::Timer => Actions::None,
-                Input::Packet(ReceivedPacket::RequestARP {
-                    sender_ip,
-                    sender_mac,
-                    target_ip,
-                }) if self.is_associated_address(target_ip) => SendPacket::ReplyARP {
-                    sender_mac: self.mac_address,
-                    sender_ip: target_ip,
-                    target_mac: sender_mac,
-                    target_ip: sender_ip,
+                Input::Timer => self.handle_active_timer(now),
+                Input::LatePacket { .. } => unreachable!("resolved above"),
+                Input::Packet(ReceivedPacket::TakeoverIntent { .. }) => {
+                    unreachable!("resolved above")
                 }
-                .into(),
-                Input::Packet(ReceivedPacket::RequestARP { .. }) => Actions::None,
+                Input::Packet(
+                    ref packet @ (ReceivedPacket::RequestARP { .. }
+                    | ReceivedPacket::ReplyARP { .. }
+                    | ReceivedPacket::GratuitousARP { .. }
+                    | ReceivedPacket::NeighborSolicitation { .. }
+                    | ReceivedPacket::NeighborAdvertisement { .. }),
+                ) => self.handle_neighbor(now, packet),
                 Input::Packet(ReceivedPacket::IP {
                     target_mac,
                     target_ip,
-                }) => self.route_ip_packet(target_mac, target_ip),
+                    protocol,
+                    destination_port,
+                }) => self.route_ip_packet(now, target_mac, target_ip, protocol, destination_port),
             },
             State::Backup {
                 active_down_timer, ..
             } => match input {
                 Input::Timer if now >= *active_down_timer => self.transition_to_active(now),
-                Input::Timer => Actions::None,
-                Input::Command(Command::Startup) => self.transition_to_active(now),
-                Input::Command(Command::Shutdown) => self.shutdown_backup(),
+                Input::Timer => self.prepare_activate(now),
+                Input::Command(Command::Startup) => unreachable!("not applicable, see accepts"),
+                Input::LatePacket { .. } => unreachable!("resolved above"),
+                Input::Packet(ReceivedPacket::TakeoverIntent { .. }) => {
+                    unreachable!("resolved above")
+                }
+                Input::Command(Command::Shutdown | Command::InterfaceDown) => {
+                    self.shutdown_backup()
+                }
+                Input::Command(Command::Restart) => self.restart(now),
+                // The final advertisement of the Active router we are taking over from.
+                Input::Packet(ReceivedPacket::ShutdownAdvertisement { sender_ip, .. })
+                    if self.claiming == Some(sender_ip) =>
+                {
+                    self.transition_to_active(now)
+                }
+                Input::Packet(ReceivedPacket::ShutdownAdvertisement { sender_ip, .. })
+                    if self.rejects_shutdown_sender(sender_ip) =>
+                {
+                    self.stats.rejected_shutdown_advertisements += 1;
+                    Actions::NONE
+                }
                 Input::Packet(ReceivedPacket::ShutdownAdvertisement {
                     max_advertise_interval: active_adver_interval,
+                    ..
                 }) => self.update_active_down_timer_for_shutdown(now, active_adver_interval),
                 Input::Packet(ReceivedPacket::Advertisement {
-                    sender_ip: _,
+                    sender_ip,
                     priority,
                     max_advertise_interval: active_adver_interval,
-                }) => self.update_active_down_timer(now, priority, active_adver_interval),
+                    ..
+                }) => {
+                    self.update_active_down_timer(now, sender_ip, priority, active_adver_interval)
+                }
+                Input::Packet(ReceivedPacket::IP { .. }) => RoutePacket::Reject.into(),
+                Input::Packet(
+                    ReceivedPacket::RequestARP { .. }
+                    | ReceivedPacket::ReplyARP { .. }
+                    | ReceivedPacket::GratuitousARP { .. }
+                    | ReceivedPacket::NeighborSolicitation { .. }
+                    | ReceivedPacket::NeighborAdvertisement { .. },
+                ) => Actions::NONE,
+            },
+            State::ShuttingDown { repeat } => match input {
+                Input::Timer if now >= repeat.at => self.repeat_shutdown_advertisement(now),
+                Input::Timer => Actions::NONE,
+                Input::Command(Command::Shutdown | Command::InterfaceDown) => {
+                    unreachable!("not applicable, see accepts")
+                }
+                Input::Command(Command::Startup | Command::Restart) => self.restart(now),
+                Input::LatePacket { .. } => unreachable!("resolved above"),
+                // The virtual addresses stay assigned until the last shutdown advertisement,
+                //  so they are answered for and accepted like in Active until then.
+                Input::Packet(
+                    ref packet @ (ReceivedPacket::RequestARP { .. }
+                    | ReceivedPacket::ReplyARP { .. }
+                    | ReceivedPacket::GratuitousARP { .. }
+                    | ReceivedPacket::NeighborSolicitation { .. }
+                    | ReceivedPacket::NeighborAdvertisement { .. }),
+                ) => self.handle_neighbor(now, packet),
+                Input::Packet(ReceivedPacket::IP {
+                    target_mac,
+                    target_ip,
+                    protocol,
+                    destination_port,
+                }) => self.route_ip_packet(now, target_mac, target_ip, protocol, destination_port),
+                Input::Packet(_) => Actions::NONE,
+            },
+            State::Monitoring { active } => match input {
+                Input::Timer => match active {
+                    Some(active) if now >= active.active_down_timer => self.lose_active_peer(),
+                    _ => Actions::NONE,
+                },
+                Input::Command(Command::Startup) => unreachable!("not applicable, see accepts"),
+                Input::Command(Command::Shutdown | Command::InterfaceDown) => {
+                    self.shutdown_backup()
+                }
+                Input::Command(Command::Restart) => self.restart(now),
+                Input::LatePacket { .. } => unreachable!("resolved above"),
+                Input::Packet(ReceivedPacket::TakeoverIntent { .. }) => {
+                    unreachable!("resolved above")
+                }
+                Input::Packet(ReceivedPacket::ShutdownAdvertisement { sender_ip, .. }) => {
+                    match active {
+                        Some(active) if active.sender_ip == sender_ip => self.lose_active_peer(),
+                        _ => Actions::NONE,
+                    }
+                }
+                Input::Packet(ReceivedPacket::Advertisement {
+                    sender_ip,
+                    priority,
+                    max_advertise_interval,
+                    ..
+                }) => self.observe_advertisement(now, sender_ip, priority, max_advertise_interval),
                 Input::Packet(ReceivedPacket::IP { .. }) => RoutePacket::Reject.into(),
-                Input::Packet(ReceivedPacket::RequestARP { .. }) => Actions::None,
+                Input::Packet(
+                    ReceivedPacket::RequestARP { .. }
+                    | ReceivedPacket::ReplyARP { .. }
+                    | ReceivedPacket::GratuitousARP { .. }
+                    | ReceivedPacket::NeighborSolicitation { .. }
+                    | ReceivedPacket::NeighborAdvertisement { .. },
+                ) => Actions::NONE,
             },
+        };
+        let to = self.state.name();
+        if to == from {
+            return actions;
+        }
+        self.history.record(Transition {
+            at: now,
+            from,
+            to,
+            cause,
+        });
+        let reason = self.transition_reason(cause);
+        self.abdicated = false;
+        match self.parameters.notify_state_changes {
+            true => actions.then(Action::Notify(Event::StateChanged { from, to, reason })),
+            false => actions,
+        }
+    }
+
+    fn transition_reason(&self, cause: TransitionCause<A>) -> TransitionReason {
+        match cause {
+            TransitionCause::Command(Command::InterfaceDown) => TransitionReason::InterfaceDown,
+            TransitionCause::Command(_) => TransitionReason::Command,
+            TransitionCause::ShutdownAdvertisement { .. } => TransitionReason::ShutdownReceived,
+            TransitionCause::Timer if self.abdicated => TransitionReason::ShutdownReceived,
+            // The last repeated shutdown advertisement, see `Parameters::shutdown_repeat`.
+            TransitionCause::Timer if self.state == State::Initialized => TransitionReason::Command,
+            TransitionCause::Timer => TransitionReason::ActiveDownTimeout,
+            TransitionCause::Advertisement { .. } if self.priority_lowered() => {
+                TransitionReason::TrackingFailure
+            }
+            TransitionCause::Advertisement { .. }
+            | TransitionCause::Arp { .. }
+            | TransitionCause::Packet => TransitionReason::Preempted,
         }
     }
 
-    fn startup(&mut self, now: Instant) -> Actions {
+    // Whether the priority policy has lowered our priority below the configured one.
+    fn priority_lowered(&self) -> bool {
+        match (self.backup_priority(), self.configured_priority) {
+            (Some(priority), Some(configured)) => priority < configured,
+            _ => false,
+        }
+    }
+
+    /// Handles a `TakeoverIntent` as the advertisement it is, returning whether its sender is
+    /// about to preempt and the extension is on.
+    fn takeover_intent(&self, input: Input<A>) -> (Input<A>, bool) {
+        match input {
+            Input::Packet(ReceivedPacket::TakeoverIntent {
+                sender_mac,
+                sender_ip,
+                priority,
+                max_advertise_interval,
+            }) => {
+                let advertisement = ReceivedPacket::Advertisement {
+                    sender_mac,
+                    sender_ip,
+                    priority,
+                    max_advertise_interval,
+                };
+                (Input::Packet(advertisement), self.graceful_takeover())
+            }
+            input => (input, false),
+        }
+    }
+
+    fn compat_input(&mut self, mut input: Input<A>) -> Input<A> {
+        if let Input::Packet(ReceivedPacket::Advertisement {
+            max_advertise_interval,
+            ..
+        }) = &mut input
+        {
+            if self.parameters.zero_interval_as_one_centi && max_advertise_interval.is_zero() {
+                *max_advertise_interval = Interval::from_centis(1);
+                self.stats.compat_zero_intervals += 1;
+            }
+        }
+        input
+    }
+
+    #[cfg(feature = "graceful-takeover")]
+    fn graceful_takeover(&self) -> bool {
+        self.parameters.graceful_takeover
+    }
+
+    #[cfg(not(feature = "graceful-takeover"))]
+    fn graceful_takeover(&self) -> bool {
+        false
+    }
+
+    fn is_own_advertisement(&self, input: &Input<A>) -> bool {
+        match *input {
+            Input::Packet(ReceivedPacket::Advertisement {
+                sender_mac,
+                sender_ip,
+                ..
+            }) => sender_mac == self.mac_address && sender_ip == self.parameters.primary_ip(),
+            Input::Packet(ReceivedPacket::ShutdownAdvertisement { sender_ip, .. }) => {
+                sender_ip == self.parameters.primary_ip()
+            }
+            _ => false,
+        }
+    }
+
+    /// Advertisements are sent from the virtual router MAC address by the Active router,
+    /// so any other source MAC address is counted and, if configured, dropped.
+    fn rejects_sender_mac(&mut self, sender_mac: MacAddr) -> bool {
+        if sender_mac == self.mac_address {
+            return false;
+        }
+        self.stats.sender_mac_mismatches += 1;
+        self.parameters.verify_sender_mac
+    }
+
+    fn startup(&mut self, now: Timestamp) -> Actions<A> {
+        let log = self.start(now);
+        self.startup_actions().then(log)
+    }
+
+    /// Shuts down and starts up again in one stream of actions, e.g. to apply a change that
+    /// needs the multicast group rejoined.
+    fn restart(&mut self, now: Timestamp) -> Actions<A> {
+        let shutdown = match self.state {
+            State::Active { .. } => {
+                self.stats.advertisements_sent += 1;
+                ShutdownActive::Advertisment
+            }
+            State::ShuttingDown { .. } => ShutdownActive::Deactivate,
+            _ => ShutdownActive::LeaveMulticast,
+        };
+        let cancel = self.stop_preparing();
+        let log = self.start(now);
+        let shutdown = cancel.then(Actions::shutdown_active(self.parameters.clone(), shutdown));
+        shutdown.then(self.startup_actions()).then(log)
+    }
+
+    // Enters the state after startup, returning what to log.
+    fn start(&mut self, now: Timestamp) -> Actions<A> {
+        self.claiming = None;
+        self.preparing = false;
+        self.peer = None;
         if self.is_owner() {
-            self.transition_to_active(now)
+            self.enter_active(now);
+            return self.log(now, Level::Info, LogEvent::BecameActive);
+        }
+        if self.parameters.mode == Mode::Monitor {
+            self.state = State::Monitoring { active: None };
         } else {
-            let active_adver_interval = self.parameters.advertisement_interval;
+            // Until an advertisement tells otherwise, assume the Active router uses the
+            // longest interval the protocol can express rather than our own.
+            self.learning_interval = self.parameters.learn_master_interval;
+            self.grace_until = self.parameters.startup_grace.map(|grace| now + grace);
+            let active_adver_interval = if self.learning_interval {
+                Interval::MAX
+            } else {
+                self.parameters.advertised_interval()
+            };
             let active_down_timer = self.active_down_timer(now, active_adver_interval);
             self.state = State::Backup {
                 active_adver_interval,
                 active_down_timer,
             };
-            Actions::None
+        }
+        Actions::NONE
+    }
+
+    fn startup_actions(&self) -> Actions<A> {
+        if self.is_owner() {
+            Actions::transition_to_active(
+                self.parameters.clone(),
+                TransitionToActive::JoinMulticast,
+            )
+        } else {
+            Action::JoinMulticast {
+                interface: self.parameters.interface,
+                group: A::MULTICAST_GROUP,
+            }
+            .into()
         }
     }
 
-    fn transition_to_active(&mut self, now: Instant) -> Actions {
+    fn transition_to_active(&mut self, now: Timestamp) -> Actions<A> {
+        self.claiming = None;
+        self.preparing = false;
+        self.peer = None;
+        self.enter_active(now);
+        let flap = self.record_transition(now);
+        let log = self.log(now, Level::Info, LogEvent::BecameActive);
+        let actions =
+            Actions::transition_to_active(self.parameters.clone(), Default::default()).then(log);
+        match flap {
+            Some(event) => actions.then(Action::Notify(event)),
+            None => actions,
+        }
+    }
+
+    fn enter_active(&mut self, now: Timestamp) {
+        self.stats.advertisements_sent += 1;
+        let phase = self
+            .parameters
+            .advertisement_phase
+            .or(self.advertisement_phase);
+        let adver_timer = self.adver_timer(now);
         self.state = State::Active {
-            adver_timer: self.adver_timer(now),
+            adver_timer: phase.map_or(adver_timer, |phase| adver_timer + phase),
+            garp_timer: self.garp_timer(now, A::announcement_repeat(&self.parameters)),
+            accept_after: self.parameters.accept_settle.map(|settle| now + settle),
         };
-        Actions::TransitionToActive(&self.parameters, Default::default())
     }
 
-    fn send_advertisment(&mut self, now: Instant) -> Actions {
+    fn send_advertisment(&mut self, now: Timestamp) -> Actions<A> {
+        let adver_timer = self.adver_timer(now);
+        match &mut self.state {
+            State::Active {
+                adver_timer: timer, ..
+            } => *timer = adver_timer,
+            state => {
+                *state = State::Active {
+                    adver_timer,
+                    garp_timer: None,
+                    accept_after: None,
+                }
+            }
+        }
+        self.stats.advertisements_sent += 1;
+        self.send(SendPacket::advertisement(self.parameters.clone()))
+    }
+
+    fn handle_active_timer(&mut self, now: Timestamp) -> Actions<A> {
+        let State::Active {
+            adver_timer,
+            garp_timer,
+            accept_after,
+        } = self.state
+        else {
+            return Actions::NONE;
+        };
+
+        let advertise = now >= adver_timer;
+        let announce = garp_timer.is_some_and(|garp_timer| now >= garp_timer.at);
+
+        let adver_timer = if advertise {
+            self.adver_timer(now)
+        } else {
+            adver_timer
+        };
+        let garp_timer = match garp_timer {
+            Some(garp_timer) if announce => self.garp_timer(now, garp_timer.remaining - 1),
+            garp_timer => garp_timer,
+        };
         self.state = State::Active {
-            adver_timer: self.adver_timer(now),
+            adver_timer,
+            garp_timer,
+            accept_after,
         };
-        SendPacket::Advertisement(&self.parameters).into()
+        if advertise {
+            self.stats.advertisements_sent += 1;
+        }
+
+        match (advertise, announce) {
+            (true, true) => Actions::transition_to_active(
+                self.parameters.clone(),
+                TransitionToActive::Advertisment,
+            ),
+            (true, false) => self.send(SendPacket::advertisement(self.parameters.clone())),
+            (false, true) => Actions::transition_to_active(
+                self.parameters.clone(),
+                TransitionToActive::NextARP(0),
+            ),
+            (false, false) => Actions::NONE,
+        }
     }
 
     fn handle_active_advertisement(
         &mut self,
-        now: Instant,
-        sender_ip: Ipv4Addr,
-        sender_priority: NonZeroU8,
+        now: Timestamp,
+        sender_mac: MacAddr,
+        sender_ip: A,
+        sender_priority: ReceivedPriority,
         active_adver_interval: Interval,
-    ) -> Actions {
-        match (
-            sender_priority.partial_cmp(&self.parameters.mode.priority()),
-            sender_ip.cmp(&self.parameters.primary_ip()),
-        ) {
+    ) -> Actions<A> {
+        let priority = match sender_priority {
+            // Only one router can own the addresses, so two owners are treated like equal
+            //  priorities and we only step down to a greater primary address.
+            ReceivedPriority::Owner255 if self.is_owner() => Ordering::Equal,
+            ReceivedPriority::Owner255 => Ordering::Greater,
+            ReceivedPriority::Normal(priority) => {
+                NonZeroU8::from(priority).cmp(&self.parameters.mode.priority())
+            }
+        };
+        let conflict = self.config_conflict(sender_ip, active_adver_interval);
+        if priority == Ordering::Equal
+            && self.is_owner()
+            && sender_ip != self.parameters.primary_ip()
+        {
+            return self
+                .handle_owner_conflict(now, sender_ip, active_adver_interval)
+                .then(conflict);
+        }
+        let address = match sender_ip.cmp(&self.parameters.primary_ip()) {
+            // Not in the RFC, see `Parameters::mac_tiebreak`.
+            Ordering::Equal => match self.parameters.mac_tiebreak {
+                Some(local_mac) => sender_mac.cmp(&local_mac),
+                None => Ordering::Equal,
+            },
+            ordering => ordering,
+        };
+        let actions = match (priority, address) {
             // If the Priority in the ADVERTISEMENT is greater than the local Priority
             //  or the Priority in the ADVERTISEMENT is equal to the local Priority
             //  and the primary IPvX address of the sender is greater than the local primary IPvX address
             //  (based on an unsigned integer comparison of the IPvX addresses in network byte order),
             //  then
-            (Some(Ordering::Greater), _) | (Some(Ordering::Equal), Ordering::Greater) => {
+            (Ordering::Greater, _) | (Ordering::Equal, Ordering::Greater) => {
+                self.peer = Some((sender_ip, now));
+                let log = self.log(now, Level::Info, LogEvent::LostElection { sender_ip });
                 self.deactivate_and_transition_to_backup(now, active_adver_interval)
+                    .then(log)
+            }
+            // Our own primary address and priority: the advertisement was reflected back to us
+            //  by a switching loop, or another router is misconfigured with our address.
+            (Ordering::Equal, Ordering::Equal) => {
+                self.stats.reflected_advertisements += 1;
+                Action::Notify(Event::ReflectedAdvertisement { sender_ip }).into()
             }
             _ => {
                 // Send an ADVERTISEMENT immediately to assert the Active state to the sending VRRP Router
                 //  and to update any learning bridges with the correct Active VRRP Router path.
                 self.send_advertisment(now)
             }
+        };
+        actions.then(conflict)
+    }
+
+    fn config_conflict(&mut self, sender_ip: A, active_adver_interval: Interval) -> Actions<A> {
+        if active_adver_interval == self.parameters.advertised_interval() {
+            self.config_conflict = None;
+            return Actions::NONE;
         }
+        let conflict = Some((sender_ip, active_adver_interval));
+        if self.config_conflict == conflict {
+            return Actions::NONE;
+        }
+        self.config_conflict = conflict;
+        Action::Notify(Event::ConfigConflict {
+            sender_ip,
+            advertisement_interval: active_adver_interval,
+        })
+        .into()
+    }
+
+    // Steps down for a Backup about to preempt, deactivating before the shutdown
+    // advertisement it waits for so the addresses are never active on both.
+    fn yield_to_takeover(
+        &mut self,
+        now: Timestamp,
+        sender_ip: A,
+        active_adver_interval: Interval,
+    ) -> Actions<A> {
+        self.peer = Some((sender_ip, now));
+        self.stats.advertisements_sent += 1;
+        let log = self.log(now, Level::Info, LogEvent::LostElection { sender_ip });
+        self.deactivate_and_transition_to_backup(now, active_adver_interval)
+            .then(self.send(SendPacket::shutdown_advertisement(self.parameters.clone())))
+            .then(log)
+    }
+
+    fn handle_owner_conflict(
+        &mut self,
+        now: Timestamp,
+        sender_ip: A,
+        active_adver_interval: Interval,
+    ) -> Actions<A> {
+        let event = Action::Notify(Event::OwnerConflict { sender_ip });
+        let step_down = match self.parameters.owner_conflict {
+            OwnerConflict::TieBreak => sender_ip > self.parameters.primary_ip(),
+            OwnerConflict::Yield => true,
+            OwnerConflict::Defend | OwnerConflict::AlarmOnly => false,
+        };
+        let actions = if step_down {
+            self.peer = Some((sender_ip, now));
+            let log = self.log(now, Level::Info, LogEvent::LostElection { sender_ip });
+            self.deactivate_and_transition_to_backup(now, active_adver_interval)
+                .then(log)
+        } else if self.parameters.owner_conflict == OwnerConflict::AlarmOnly {
+            Actions::NONE
+        } else {
+            self.send_advertisment(now)
+        };
+        actions.then(event)
     }
 
-    fn is_greater_priority_than(&self, sender_priority: NonZeroU8) -> bool {
+    fn backup_priority(&self) -> Option<Priority> {
         match &self.parameters.mode {
-            Mode::Owner => true,
-            Mode::Backup(BackupMode { priority, .. }) => *priority > sender_priority,
+            Mode::Backup(BackupMode { priority, .. }) => Some(*priority),
+            Mode::Owner | Mode::Monitor => None,
+        }
+    }
+
+    fn apply_priority_policy(&mut self) {
+        let (Some(policy), Some(configured)) = (&self.priority_policy, self.configured_priority)
+        else {
+            return;
+        };
+        if let Mode::Backup(backup) = &mut Arc::make_mut(&mut self.parameters).mode {
+            backup.priority = policy.priority(configured);
+        }
+    }
+
+    fn record_peer(&mut self, now: Timestamp, input: &Input<A>) {
+        let (sender_ip, sender_mac, priority, interval) = match input {
+            Input::Packet(ReceivedPacket::Advertisement {
+                sender_mac,
+                sender_ip,
+                priority,
+                max_advertise_interval,
+            }) => (
+                *sender_ip,
+                Some(*sender_mac),
+                Some(*priority),
+                *max_advertise_interval,
+            ),
+            Input::Packet(ReceivedPacket::ShutdownAdvertisement {
+                sender_ip,
+                max_advertise_interval,
+            }) => (*sender_ip, None, None, *max_advertise_interval),
+            _ => return,
+        };
+        // Our own advertisements looped back are not from a peer.
+        if sender_ip != self.parameters.primary_ip() {
+            self.peers
+                .record(now, sender_ip, sender_mac, priority, interval);
+        }
+    }
+
+    fn log(&mut self, now: Timestamp, level: Level, event: LogEvent<A>) -> Actions<A> {
+        if self.parameters.logging && self.logs.allow(now, &event) {
+            Action::Log(level, event).into()
+        } else {
+            Actions::NONE
+        }
+    }
+
+    fn preempt_suppression(&self, now: Timestamp) -> Option<PreemptSuppression> {
+        if !self.parameters.mode.should_preempt() {
+            Some(PreemptSuppression::Disabled)
+        } else if self.flaps.is_holding_down(now) {
+            Some(PreemptSuppression::FlapDamping)
+        } else if self.grace_until.is_some_and(|until| now < until) {
+            Some(PreemptSuppression::StartupGrace)
+        } else if self.failover_hold_until.is_some_and(|until| now < until) {
+            Some(PreemptSuppression::FailoverHold)
+        } else {
+            None
+        }
+    }
+
+    // Turns the timer of a due scheduled failover into a Restart of an Active router.
+    fn scheduled_failover_input(&mut self, now: Timestamp, input: Input<A>) -> Input<A> {
+        match self.scheduled_failover {
+            Some((at, hold)) if matches!(input, Input::Timer) && now >= at => {
+                self.scheduled_failover = None;
+                self.failover_hold_until = Some(at + hold);
+                match self.state {
+                    State::Active { .. } if !self.is_owner() => Command::Restart.into(),
+                    _ => input,
+                }
+            }
+            _ => input,
+        }
+    }
+
+    fn record_transition(&mut self, now: Timestamp) -> Option<Event<A>> {
+        self.stats.transitions += 1;
+        let flap_damping = self.parameters.flap_damping?;
+        self.flaps.record(now, &flap_damping)
+    }
+
+    fn is_greater_priority_than(&self, sender_priority: ReceivedPriority) -> bool {
+        match (&self.parameters.mode, sender_priority) {
+            (Mode::Owner, _) => true,
+            (Mode::Backup(_), ReceivedPriority::Owner255) => false,
+            (Mode::Backup(BackupMode { priority, .. }), ReceivedPriority::Normal(sender)) => {
+                *priority > sender
+            }
+            (Mode::Monitor, _) => false,
         }
     }
 
     fn deactivate_and_transition_to_backup(
         &mut self,
-        now: Instant,
+        now: Timestamp,
         active_adver_interval: Interval,
-    ) -> Actions {
+    ) -> Actions<A> {
         self.state = State::Backup {
             active_down_timer: self.active_down_timer(now, active_adver_interval),
             active_adver_interval,
         };
-        Action::Deactivate.into()
+        let actions = Actions::from(Action::Deactivate {
+            interface: self.parameters.interface,
+        });
+        match self.record_transition(now) {
+            Some(event) => actions.then(Action::Notify(event)),
+            None => actions,
+        }
     }
 
     fn update_active_down_timer(
         &mut self,
-        now: Instant,
-        active_priority: NonZeroU8,
+        now: Timestamp,
+        sender_ip: A,
+        active_priority: ReceivedPriority,
         active_adver_interval: Interval,
-    ) -> Actions {
-        if !self.parameters.mode.should_preempt() || !self.is_greater_priority_than(active_priority)
-        {
+    ) -> Actions<A> {
+        let learned = std::mem::take(&mut self.learning_interval);
+        let greater = self.is_greater_priority_than(active_priority);
+        let suppression = self.preempt_suppression(now);
+        let preempt = suppression.is_none();
+        let claim = if learned || !preempt || !greater {
+            self.claiming = None;
+            self.abdicated = false;
             self.state = State::Backup {
                 active_down_timer: self.active_down_timer(now, active_adver_interval),
                 active_adver_interval,
             };
+            Actions::NONE
+        } else if self.graceful_takeover() && self.claiming.is_none() {
+            self.claim(now, sender_ip, active_adver_interval)
+        } else {
+            Actions::NONE
+        };
+        let suppressed = suppression.filter(|_| greater && !learned);
+        let suppressed = match (suppressed, self.preempt_suppressed) {
+            (None, _) => {
+                self.preempt_suppressed = None;
+                Actions::NONE
+            }
+            (Some(reason), Some((last, notified)))
+                if reason == notified && now < last + PREEMPT_SUPPRESSED_REPEAT =>
+            {
+                Actions::NONE
+            }
+            (Some(reason), _) => {
+                self.preempt_suppressed = Some((now, reason));
+                Action::Notify(Event::PreemptSuppressed {
+                    sender_ip,
+                    priority: active_priority,
+                    own_priority: self.parameters.mode.priority(),
+                    reason,
+                })
+                .into()
+            }
+        };
+        let actions = match self.peer.replace((sender_ip, now)) {
+            Some((peer, _)) if peer != sender_ip => self.log(
+                now,
+                Level::Info,
+                LogEvent::PeerChanged {
+                    sender_ip,
+                    priority: active_priority,
+                },
+            ),
+            Some((_, last)) => {
+                let elapsed = now.saturating_duration_since(last);
+                match (self.stats.advertisement_jitter)
+                    .record(elapsed, active_adver_interval.into())
+                {
+                    Some(jitter) => {
+                        Action::Notify(Event::IrregularAdvertisements { sender_ip, jitter }).into()
+                    }
+                    None => Actions::NONE,
+                }
+            }
+            None => Actions::NONE,
+        };
+        actions
+            .then(suppressed)
+            .then(claim)
+            .then(self.cancel_prepare(now))
+    }
+
+    // Announces the takeover, falling back to taking over after one advertisement interval
+    // of the Active router if it does not have the extension.
+    fn claim(
+        &mut self,
+        now: Timestamp,
+        sender_ip: A,
+        active_adver_interval: Interval,
+    ) -> Actions<A> {
+        self.claiming = Some(sender_ip);
+        if let State::Backup {
+            active_down_timer, ..
+        } = &mut self.state
+        {
+            *active_down_timer = (*active_down_timer).min(now + active_adver_interval);
         }
-        Actions::None
+        self.stats.advertisements_sent += 1;
+        self.send(SendPacket::takeover_intent(self.parameters.clone()))
+    }
+
+    /// Only the Active router the Backup last heard from may abdicate; before any
+    /// advertisement was heard every sender is trusted.
+    fn rejects_shutdown_sender(&self, sender_ip: A) -> bool {
+        self.peer.is_some_and(|(peer, _)| peer != sender_ip)
     }
 
     fn update_active_down_timer_for_shutdown(
         &mut self,
-        now: Instant,
+        now: Timestamp,
         active_adver_interval: Interval,
-    ) -> Actions {
+    ) -> Actions<A> {
+        self.abdicated = true;
         self.state = State::Backup {
             active_down_timer: self.active_down_timer_for_shutdown(now, active_adver_interval),
             active_adver_interval,
         };
-        Actions::None
+        Actions::NONE
+    }
+
+    fn observe_advertisement(
+        &mut self,
+        now: Timestamp,
+        sender_ip: A,
+        priority: ReceivedPriority,
+        max_advertise_interval: Interval,
+    ) -> Actions<A> {
+        let State::Monitoring { active } = &mut self.state else {
+            return Actions::NONE;
+        };
+
+        let is_new_active = match active {
+            None => true,
+            Some(active) if active.sender_ip == sender_ip => false,
+            // Two routers advertising at once, follow the one that wins the election.
+            Some(active) => match priority.cmp(&active.priority) {
+                Ordering::Greater => true,
+                Ordering::Equal => sender_ip > active.sender_ip,
+                Ordering::Less => return Actions::NONE,
+            },
+        };
+        *active = Some(ObservedActive {
+            sender_ip,
+            priority,
+            max_advertise_interval,
+            // Monitors have no priority of their own, so wait as long as any Backup would.
+            active_down_timer: now
+                + timing::active_down_interval(NonZeroU8::MIN, max_advertise_interval),
+        });
+
+        if is_new_active {
+            Action::Notify(Event::NewActivePeer {
+                sender_ip,
+                priority,
+            })
+            .into()
+        } else {
+            Actions::NONE
+        }
+    }
+
+    // Releases the addresses for `Mode::Monitor`, staying in the multicast group to watch.
+    fn step_down_to_monitoring(&mut self) -> Actions<A> {
+        self.stats.advertisements_sent += 1;
+        self.state = State::Monitoring { active: None };
+        self.send(SendPacket::shutdown_advertisement(self.parameters.clone()))
+            .then(Action::Deactivate {
+                interface: self.parameters.interface,
+            })
     }
 
-    fn route_ip_packet(&mut self, target_mac: MacAddr, target_ip: Ipv4Addr) -> Actions {
+    fn lose_active_peer(&mut self) -> Actions<A> {
+        match std::mem::replace(&mut self.state, State::Monitoring { active: None }) {
+            State::Monitoring {
+                active: Some(active),
+            } => Action::Notify(Event::LostPeer {
+                sender_ip: active.sender_ip,
+            })
+            .into(),
+            _ => Actions::NONE,
+        }
+    }
+
+    fn handle_neighbor(&mut self, now: Timestamp, packet: &ReceivedPacket<A>) -> Actions<A> {
+        match packet.neighbor() {
+            // An ARP probe (RFC 5227) or Duplicate Address Detection (RFC 4862) from a host
+            //  checking whether a virtual address is in use.
+            Some(Neighbor::Request {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            }) if sender_ip == A::UNSPECIFIED && self.is_associated_address(target_ip) => {
+                self.defend_address(sender_mac, target_ip)
+            }
+            Some(Neighbor::Request {
+                sender_mac,
+                sender_ip,
+                target_ip,
+            }) if self.answers_arp(target_ip, sender_ip) => {
+                self.reply_arp(now, sender_mac, sender_ip, target_ip)
+            }
+            Some(Neighbor::Claim {
+                sender_mac,
+                sender_ip,
+            }) => self.check_address_conflict(sender_mac, sender_ip),
+            Some(Neighbor::Request { .. }) | None => Actions::NONE,
+        }
+    }
+
+    fn defend_address(&self, prober_mac: MacAddr, address: A) -> Actions<A> {
+        if !self.parameters.defend_addresses {
+            return Actions::NONE;
+        }
+        self.send(A::reply(
+            &self.parameters,
+            self.mac_address,
+            address,
+            prober_mac,
+            A::UNSPECIFIED,
+        ))
+    }
+
+    fn check_address_conflict(&mut self, sender_mac: MacAddr, sender_ip: A) -> Actions<A> {
+        if sender_mac == self.mac_address || !self.is_associated_address(sender_ip) {
+            return Actions::NONE;
+        }
+        self.stats.address_conflicts += 1;
+        Action::Notify(Event::AddressConflict {
+            address: sender_ip,
+            sender_mac,
+        })
+        .into()
+    }
+
+    fn route_ip_packet(
+        &mut self,
+        now: Timestamp,
+        target_mac: MacAddr,
+        target_ip: A,
+        protocol: u8,
+        destination_port: Option<u16>,
+    ) -> Actions<A> {
         if target_mac != self.mac_address {
-            Actions::None
-        } else if self.should_accept_packets_for(target_ip) {
-            RoutePacket::Accept.into()
+            return Actions::NONE;
+        }
+        let decision = if self
+            .parameters
+            .mode
+            .should_accept(protocol, destination_port)
+            && self.is_associated_address(target_ip)
+        {
+            match self.state {
+                State::Active {
+                    accept_after: Some(accept_after),
+                    ..
+                } if now < accept_after => RoutePacket::Reject,
+                _ => RoutePacket::Accept,
+            }
         } else {
-            RoutePacket::Forward.into()
+            RoutePacket::Forward
+        };
+        let decision = match &self.route_policy {
+            Some(policy) => policy.route(&IpPacketMeta {
+                target_ip,
+                protocol,
+                destination_port,
+                decision,
+            }),
+            None => decision,
+        };
+        decision.into()
+    }
+
+    fn shutdown_active(&mut self, now: Timestamp) -> Actions<A> {
+        self.stats.advertisements_sent += 1;
+        let repeat = self.garp_timer(now, self.parameters.shutdown_repeat);
+        let Some(repeat) = repeat else {
+            self.state = State::Initialized;
+            return Actions::shutdown_active(self.parameters.clone(), Default::default());
+        };
+        self.state = State::ShuttingDown { repeat };
+        self.send(SendPacket::shutdown_advertisement(self.parameters.clone()))
+    }
+
+    // Sends the next shutdown advertisement, deactivating after the last one.
+    fn repeat_shutdown_advertisement(&mut self, now: Timestamp) -> Actions<A> {
+        let State::ShuttingDown { repeat } = self.state else {
+            return Actions::NONE;
+        };
+        self.stats.advertisements_sent += 1;
+        match self.garp_timer(now, repeat.remaining - 1) {
+            Some(repeat) => {
+                self.state = State::ShuttingDown { repeat };
+                self.send(SendPacket::shutdown_advertisement(self.parameters.clone()))
+            }
+            None => {
+                self.state = State::Initialized;
+                Actions::shutdown_active(self.parameters.clone(), Default::default())
+            }
         }
     }
 
-    fn shutdown_active(&mut self) -> Actions {
+    fn shutdown_backup(&mut self) -> Actions<A> {
         self.state = State::Initialized;
-        Actions::ShutdownActive(&self.parameters, Default::default())
+        self.peer = None;
+        self.stop_preparing().then(self.leave_multicast())
     }
 
-    fn shutdown_backup(&mut self) -> Actions {
-        self.state = State::Initialized;
-        Actions::None
+    fn stop_preparing(&mut self) -> Actions<A> {
+        match std::mem::take(&mut self.preparing) {
+            true => Action::CancelPrepare {
+                interface: self.parameters.interface,
+            }
+            .into(),
+            false => Actions::NONE,
+        }
+    }
+
+    // When the down timer is within one skew time of expiring, see
+    // `Parameters::prepare_activate`.
+    fn prepare_at(&self) -> Option<Timestamp> {
+        let State::Backup {
+            active_down_timer,
+            active_adver_interval,
+        } = self.state
+        else {
+            return None;
+        };
+        if !self.parameters.prepare_activate {
+            return None;
+        }
+        let skew_time = self.parameters.skew_time(active_adver_interval);
+        Some(
+            active_down_timer
+                .checked_sub(skew_time.into())
+                .unwrap_or(active_down_timer),
+        )
     }
 
-    fn should_accept_packets_for(&self, target_ip: Ipv4Addr) -> bool {
-        self.parameters.mode.should_accept() && self.is_associated_address(target_ip)
+    fn prepare_activate(&mut self, now: Timestamp) -> Actions<A> {
+        if self.preparing || self.prepare_at().is_none_or(|at| now < at) {
+            return Actions::NONE;
+        }
+        self.preparing = true;
+        Action::PrepareActivate {
+            interface: self.parameters.interface,
+            addresses: self.parameters.virtual_addresses.clone(),
+        }
+        .into()
+    }
+
+    // After the down timer was restarted by an advertisement.
+    fn cancel_prepare(&mut self, now: Timestamp) -> Actions<A> {
+        if !self.preparing || self.prepare_at().is_some_and(|at| now >= at) {
+            return Actions::NONE;
+        }
+        self.preparing = false;
+        Action::CancelPrepare {
+            interface: self.parameters.interface,
+        }
+        .into()
+    }
+
+    fn leave_multicast(&self) -> Actions<A> {
+        Action::LeaveMulticast {
+            interface: self.parameters.interface,
+            group: A::MULTICAST_GROUP,
+        }
+        .into()
+    }
+
+    fn send(&self, packet: SendPacket<A>) -> Actions<A> {
+        Action::send(&self.parameters, packet).into()
     }
 
     fn is_owner(&self) -> bool {
         matches!(self.parameters.mode, Mode::Owner)
     }
 
-    fn is_associated_address(&self, ip_address: Ipv4Addr) -> bool {
+    fn is_associated_address(&self, ip_address: A) -> bool {
         self.parameters.virtual_addresses.contains(ip_address)
     }
 
-    fn adver_timer(&mut self, now: Instant) -> Instant {
+    fn reply_arp(
+        &mut self,
+        now: Timestamp,
+        sender_mac: MacAddr,
+        sender_ip: A,
+        target_ip: A,
+    ) -> Actions<A> {
+        if let Some(limit) = &self.parameters.arp_reply_limit {
+            if !self.arp_replies.allow(now, sender_mac, limit) {
+                self.stats.arp_requests_rate_limited += 1;
+                return Actions::NONE;
+            }
+        }
+        self.send(A::reply(
+            &self.parameters,
+            self.mac_address,
+            target_ip,
+            sender_mac,
+            sender_ip,
+        ))
+    }
+
+    // See `VirtualAddress::arp_subnet_only`.
+    fn answers_arp(&self, target_ip: A, sender_ip: A) -> bool {
+        self.parameters
+            .virtual_addresses
+            .find(target_ip)
+            .is_some_and(|address| !address.arp_subnet_only || address.in_subnet(sender_ip))
+    }
+
+    /// Sends the next advertisement at `at` if that is sooner, for `RouterSet` to spread the
+    /// advertisements of its routers. Delaying them could let a Backup take over.
+    pub(crate) fn advance_advertisement(&mut self, at: Timestamp) {
+        if let State::Active { adver_timer, .. } = &mut self.state {
+            *adver_timer = (*adver_timer).min(at);
+        }
+    }
+
+    /// Delays the first advertisement after becoming Active by `phase`, unless the parameters
+    /// set `advertisement_phase`. `RouterSet` assigns it so routers becoming Active together
+    /// don't advertise in step; with `RouterSet::with_advertisement_pacing` the later ones are
+    /// then pulled forward to their slot by `advance_advertisement`.
+    pub(crate) fn set_advertisement_phase(&mut self, phase: Interval) {
+        self.advertisement_phase = Some(phase);
+    }
+
+    fn adver_timer(&self, now: Timestamp) -> Timestamp {
         now + self.parameters.advertisement_interval
     }
 
-    fn active_down_timer(&self, now: Instant, active_adver_interval: Interval) -> Instant {
+    fn garp_timer(&self, now: Timestamp, remaining: u8) -> Option<GarpTimer> {
+        (remaining > 0).then(|| GarpTimer {
+            at: now + self.parameters.garp_spacing,
+            remaining,
+        })
+    }
+
+    fn active_down_timer(&self, now: Timestamp, active_adver_interval: Interval) -> Timestamp {
         now + self.parameters.active_down_interval(active_adver_interval)
     }
 
     fn active_down_timer_for_shutdown(
         &self,
-        now: Instant,
+        now: Timestamp,
         active_adver_interval: Interval,
-    ) -> Instant {
+    ) -> Timestamp {
         now + self.parameters.skew_time(active_adver_interval)
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum State {
+pub enum State<A: AddressFamily = Ipv4Addr> {
     Initialized,
     Backup {
-        active_down_timer: Instant,
+        active_down_timer: Timestamp,
         active_adver_interval: Interval,
     },
     Active {
-        adver_timer: Instant,
+        adver_timer: Timestamp,
+        garp_timer: Option<GarpTimer>,
+        /// Packets are not accepted before this, see `Parameters::accept_settle`.
+        accept_after: Option<Timestamp>,
+    },
+    /// Repeating the shutdown advertisement before deactivating, see
+    /// `Parameters::shutdown_repeat`.
+    ShuttingDown {
+        repeat: GarpTimer,
+    },
+    /// The state of a `Mode::Monitor` router after startup.
+    Monitoring {
+        active: Option<ObservedActive<A>>,
     },
 }
+
+impl<A: AddressFamily> fmt::Display for State<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Backup {
+                active_adver_interval,
+                ..
+            } => write!(f, "Backup active_interval={active_adver_interval}"),
+            State::Monitoring {
+                active: Some(active),
+            } => write!(
+                f,
+                "Monitoring active={} prio={} interval={}",
+                active.sender_ip,
+                u8::from(active.priority),
+                active.max_advertise_interval
+            ),
+            State::ShuttingDown { repeat } => {
+                write!(f, "ShuttingDown remaining={}", repeat.remaining)
+            }
+            State::Initialized | State::Active { .. } | State::Monitoring { active: None } => {
+                f.write_str(self.name())
+            }
+        }
+    }
+}
+
+impl<A: AddressFamily> State<A> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Initialized => "Initialized",
+            State::Backup { .. } => "Backup",
+            State::Active { .. } => "Active",
+            State::ShuttingDown { .. } => "ShuttingDown",
+            State::Monitoring { .. } => "Monitoring",
+        }
+    }
+}
+
+/// The Active router as observed by a monitoring router.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObservedActive<A: AddressFamily = Ipv4Addr> {
+    pub sender_ip: A,
+    pub priority: ReceivedPriority,
+    pub max_advertise_interval: Interval,
+    pub active_down_timer: Timestamp,
+}
+
+/// The next repetition of the gratuitous ARPs sent after becoming Active, or of the shutdown
+/// advertisement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GarpTimer {
+    pub at: Timestamp,
+    pub remaining: u8,
+}
@@ -0,0 +1,476 @@
+use crate::filter::CaptureFilter;
+use crate::Timestamp;
+use crate::{
+    Action, ActionHandler, Command, ConfigError, ConfigPolicy, Input, InterfaceId, InterfaceStats,
+    Parameters, Router, SendPacket, State, VRID,
+};
+use pnet_base::MacAddr;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+/// The virtual routers of a daemon, keyed by interface and VRID.
+#[derive(Default)]
+pub struct RouterSet {
+    routers: BTreeMap<(InterfaceId, VRID), Router>,
+    garp_pacing: Option<Duration>,
+    paced_garps: BTreeMap<InterfaceId, PacedGarps>,
+    // The origin of the advertisement slots of `RouterSet::with_advertisement_pacing`.
+    advertisement_epoch: Option<Timestamp>,
+    advertisement_pacing: bool,
+    // The routers shut down by `RouterSet::set_admin_state`, kept down across reloads.
+    admin_down: BTreeSet<(InterfaceId, VRID)>,
+    config_policy: ConfigPolicy,
+}
+
+// The gratuitous ARPs held back on one interface by `RouterSet::with_garp_pacing`, with the
+// VRID of the router that sent them so they are dropped once it is no longer Active.
+#[derive(Default)]
+struct PacedGarps {
+    queue: VecDeque<(VRID, MacAddr, Ipv4Addr)>,
+    next_at: Option<Timestamp>,
+}
+
+impl RouterSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends at most one gratuitous ARP per `pacing` on each interface, so that many virtual
+    /// routers becoming Active at once, e.g. when a link comes back, don't flood the switch
+    /// CPU. Those of `apply_config` are not paced.
+    pub fn with_garp_pacing(self, pacing: Duration) -> Self {
+        Self {
+            garp_pacing: Some(pacing),
+            ..self
+        }
+    }
+
+    /// Spreads the advertisements of the Active routers on each interface evenly across their
+    /// advertisement interval, instead of sending them in bursts when many became Active
+    /// together. Each router gets a slot by VRID; advertisements are only ever sent earlier
+    /// to reach it.
+    pub fn with_advertisement_pacing(self) -> Self {
+        Self {
+            advertisement_pacing: true,
+            ..self
+        }
+    }
+
+    /// Enforces the policy on every configuration applied, e.g. to forbid address owners
+    /// across a fleet.
+    pub fn with_config_policy(self, config_policy: ConfigPolicy) -> Self {
+        Self {
+            config_policy,
+            ..self
+        }
+    }
+
+    pub fn get(&self, interface: InterfaceId, vrid: VRID) -> Option<&Router> {
+        self.routers.get(&(interface, vrid))
+    }
+
+    pub fn get_mut(&mut self, interface: InterfaceId, vrid: VRID) -> Option<&mut Router> {
+        self.routers.get_mut(&(interface, vrid))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Router> {
+        self.routers.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.routers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routers.is_empty()
+    }
+
+    /// The advertisements of the routers on `interface`.
+    pub fn interface_stats(&self, interface: InterfaceId) -> InterfaceStats {
+        let mut stats = InterfaceStats::default();
+        for (_, router) in self.on_interface(interface) {
+            stats.routers += 1;
+            stats.advertisements_sent += router.stats().advertisements_sent;
+            if matches!(router.state(), State::Active { .. }) {
+                stats.active += 1;
+                let interval = Duration::from(router.parameters().advertisement_interval);
+                stats.advertisements_per_second += 1.0 / interval.as_secs_f64();
+            }
+        }
+        stats
+    }
+
+    fn on_interface(
+        &self,
+        interface: InterfaceId,
+    ) -> impl Iterator<Item = (&(InterfaceId, VRID), &Router)> {
+        self.routers
+            .range((interface, VRID::MIN)..=(interface, VRID::MAX))
+    }
+
+    /// A filter for capturing the traffic of the routers on `interface`.
+    pub fn capture_filter(&self, interface: InterfaceId) -> CaptureFilter {
+        CaptureFilter::new(
+            self.on_interface(interface)
+                .map(|(_, router)| router.parameters()),
+        )
+    }
+
+    /// Checks a whole configuration against the config policy without applying it, e.g.
+    /// before a reload.
+    pub fn validate_config(&self, config: &[Parameters]) -> Result<(), Vec<ConfigError>> {
+        validate_config(config.to_vec(), self.config_policy).map(|_| ())
+    }
+
+    /// The earliest timer of all virtual routers and paced gratuitous ARPs.
+    pub fn next_timer(&self, now: Timestamp) -> Option<Timestamp> {
+        self.timers(now).min()
+    }
+
+    /// When to wake up next so that every timer due within `tolerance` of the earliest one is
+    /// handled by the same wakeup; no timer fires more than `tolerance` late.
+    pub fn next_coalesced_timer(&self, now: Timestamp, tolerance: Duration) -> Option<Timestamp> {
+        let latest = self.next_timer(now)? + tolerance;
+        self.timers(now).filter(|timer| *timer <= latest).max()
+    }
+
+    fn timers(&self, now: Timestamp) -> impl Iterator<Item = Timestamp> + '_ {
+        let garps = self
+            .paced_garps
+            .values()
+            .filter(|paced| !paced.queue.is_empty())
+            .map(move |paced| paced.next_at.unwrap_or(now));
+        self.routers
+            .values()
+            .map(move |router| router.next_timer(now))
+            .chain(garps)
+    }
+
+    /// Passes the input to the virtual router, returning false if there is none.
+    pub fn dispatch(
+        &mut self,
+        now: Timestamp,
+        interface: InterfaceId,
+        vrid: VRID,
+        input: Input,
+        handler: &mut impl ActionHandler,
+    ) -> bool {
+        match self.routers.get_mut(&(interface, vrid)) {
+            Some(router) => {
+                for action in router.handle_input(now, input) {
+                    pace(
+                        self.garp_pacing,
+                        &mut self.paced_garps,
+                        vrid,
+                        action,
+                        handler,
+                    );
+                }
+                self.pace_advertisements(now, Some(interface));
+                self.send_paced_garps(now, handler);
+                #[cfg(feature = "debug-invariants")]
+                self.assert_invariants();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Administratively shuts down or starts up the virtual router, like "shutdown" under a
+    /// VRRP group on a router CLI: an Active router hands over gracefully. The router stays
+    /// down across `apply_config` until set up again. Returns false if there is no router.
+    pub fn set_admin_state(
+        &mut self,
+        now: Timestamp,
+        interface: InterfaceId,
+        vrid: VRID,
+        up: bool,
+        handler: &mut impl ActionHandler,
+    ) -> bool {
+        let key = (interface, vrid);
+        if !self.routers.contains_key(&key) {
+            return false;
+        }
+        let command = match up {
+            true if self.admin_down.remove(&key) => Command::Startup,
+            false if self.admin_down.insert(key) => Command::Shutdown,
+            _ => return true,
+        };
+        self.dispatch(now, interface, vrid, command.into(), handler)
+    }
+
+    /// Whether the virtual router is not shut down by `set_admin_state`.
+    pub fn is_admin_up(&self, interface: InterfaceId, vrid: VRID) -> bool {
+        !self.admin_down.contains(&(interface, vrid))
+    }
+
+    /// Fires the timer of every virtual router that is due.
+    pub fn handle_timers(&mut self, now: Timestamp, handler: &mut impl ActionHandler) {
+        for (&(_, vrid), router) in &mut self.routers {
+            if router.next_timer(now) <= now {
+                for action in router.handle_input(now, Input::Timer) {
+                    pace(
+                        self.garp_pacing,
+                        &mut self.paced_garps,
+                        vrid,
+                        action,
+                        handler,
+                    );
+                }
+            }
+        }
+        self.pace_advertisements(now, None);
+        self.send_paced_garps(now, handler);
+        #[cfg(feature = "debug-invariants")]
+        {
+            self.assert_invariants();
+            for (key, router) in &self.routers {
+                assert!(
+                    router.next_timer(now) > now,
+                    "the timer of {key:?} is still due after firing"
+                );
+            }
+        }
+    }
+
+    // Spreads the first advertisements after becoming Active of the routers on each
+    // interface across half their interval, by VRID, unless their parameters set a phase.
+    fn assign_advertisement_phases(&mut self) {
+        let mut counts = BTreeMap::new();
+        for (interface, _) in self.routers.keys() {
+            *counts.entry(*interface).or_insert(0u16) += 1;
+        }
+        let mut slots = BTreeMap::new();
+        for (&(interface, _), router) in &mut self.routers {
+            let slot = slots.entry(interface).or_insert(0u16);
+            let step = router.parameters().advertisement_interval / (2 * counts[&interface]);
+            router.set_advertisement_phase(*slot * step);
+            *slot += 1;
+        }
+    }
+
+    // Moves the advertisement timers of the Active routers on `interface`, or on every
+    // interface, to their next slot when that is sooner.
+    fn pace_advertisements(&mut self, now: Timestamp, interface: Option<InterfaceId>) {
+        if !self.advertisement_pacing {
+            return;
+        }
+        let epoch = *self.advertisement_epoch.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(epoch).as_nanos();
+        let mut counts = BTreeMap::new();
+        for (key_interface, _) in self.routers.keys() {
+            *counts.entry(*key_interface).or_insert(0u128) += 1;
+        }
+        let mut slots = BTreeMap::new();
+        for (&(key_interface, _), router) in &mut self.routers {
+            let slot = slots.entry(key_interface).or_insert(0u128);
+            *slot += 1;
+            if interface.is_some_and(|interface| interface != key_interface) {
+                continue;
+            }
+            let interval = Duration::from(router.parameters().advertisement_interval).as_nanos();
+            if interval == 0 {
+                continue;
+            }
+            let phase = interval * (*slot - 1) / counts[&key_interface];
+            // The first slot after now, at most one interval away.
+            let until_slot = interval - (elapsed + interval - phase) % interval;
+            router.advance_advertisement(now + Duration::from_nanos(until_slot as u64));
+        }
+    }
+
+    fn send_paced_garps(&mut self, now: Timestamp, handler: &mut impl ActionHandler) {
+        let Some(pacing) = self.garp_pacing else {
+            return;
+        };
+        for (&interface, paced) in &mut self.paced_garps {
+            if paced.next_at.is_some_and(|at| at > now) {
+                continue;
+            }
+            if let Some((_, sender_mac, sender_ip)) = paced.queue.pop_front() {
+                handler.handle(Action::Send {
+                    interface,
+                    packet: SendPacket::GratuitousARP {
+                        sender_mac,
+                        sender_ip,
+                    },
+                });
+                paced.next_at = Some(now + pacing);
+            }
+        }
+    }
+
+    /// Checks that every virtual router is still configured for the interface and VRID it is
+    /// kept under, e.g. after `get_mut` was used to update its parameters, and that no two
+    /// Active ones claim the same virtual address on an interface.
+    pub fn check_invariants(&self) -> Result<(), Vec<InvariantViolation>> {
+        let mut violations = Vec::new();
+        let mut claimed = BTreeSet::new();
+        for (&(interface, vrid), router) in &self.routers {
+            let parameters = router.parameters();
+            if (parameters.interface, parameters.vrid) != (interface, vrid) {
+                violations.push(InvariantViolation::KeyMismatch { interface, vrid });
+            }
+            if matches!(router.state(), State::Active { .. }) {
+                for virtual_address in &parameters.virtual_addresses {
+                    let address = virtual_address.address;
+                    if !claimed.insert((parameters.interface, address)) {
+                        violations.push(InvariantViolation::DuplicateActiveAddress {
+                            interface: parameters.interface,
+                            address,
+                        });
+                    }
+                }
+            }
+        }
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    fn assert_invariants(&self) {
+        if let Err(violations) = self.check_invariants() {
+            panic!("RouterSet invariants violated: {violations:?}");
+        }
+    }
+
+    /// Replaces the configuration: virtual routers missing from `config` are shut down and
+    /// removed, new ones are started and existing ones are updated in place.
+    ///
+    /// The whole configuration is validated against the config policy first, an invalid one
+    /// leaves the set untouched.
+    pub fn apply_config(
+        &mut self,
+        now: Timestamp,
+        config: Vec<Parameters>,
+    ) -> Result<impl Iterator<Item = Action> + 'static, Vec<ConfigError>> {
+        let config_by_key = validate_config(config, self.config_policy)?;
+
+        let removed_keys: Vec<_> = self
+            .routers
+            .keys()
+            .filter(|key| !config_by_key.contains_key(key))
+            .copied()
+            .collect();
+        let mut removed = Vec::new();
+        for key in removed_keys {
+            removed.extend(self.routers.remove(&key));
+            self.admin_down.remove(&key);
+            drop_paced_garps(&mut self.paced_garps, key.0, key.1);
+        }
+
+        let mut added = Vec::new();
+        let mut updated = BTreeMap::new();
+        for (key, parameters) in config_by_key {
+            match self.routers.get(&key) {
+                Some(router) if *router.parameters() == parameters => {}
+                Some(_) => {
+                    updated.insert(key, parameters);
+                }
+                None => {
+                    self.routers.insert(key, Router::new(parameters));
+                    added.push(key);
+                }
+            }
+        }
+
+        self.assign_advertisement_phases();
+        let mut actions = Vec::new();
+        for router in &mut removed {
+            actions.extend(router.handle_input(now, Input::Command(Command::Shutdown)));
+        }
+        for (key, router) in &mut self.routers {
+            if added.contains(key) {
+                actions.extend(router.handle_input(now, Input::Command(Command::Startup)));
+            } else if let Some(parameters) = updated.remove(key) {
+                for action in router.replace_parameters(now, parameters) {
+                    if let Action::Deactivate { interface } = action {
+                        drop_paced_garps(&mut self.paced_garps, interface, key.1);
+                    }
+                    actions.push(action);
+                }
+            }
+        }
+        self.pace_advertisements(now, None);
+        Ok(actions.into_iter())
+    }
+}
+
+/// Passes the action of the router with `vrid` to the handler, unless it is a gratuitous ARP
+/// to be paced. Deactivating drops the gratuitous ARPs the router still has queued, so they
+/// don't pull traffic away from the router that took over.
+fn pace(
+    pacing: Option<Duration>,
+    paced_garps: &mut BTreeMap<InterfaceId, PacedGarps>,
+    vrid: VRID,
+    action: Action,
+    handler: &mut impl ActionHandler,
+) {
+    match action {
+        Action::Send {
+            interface,
+            packet:
+                SendPacket::GratuitousARP {
+                    sender_mac,
+                    sender_ip,
+                },
+        } if pacing.is_some() => {
+            let paced = paced_garps.entry(interface).or_default();
+            paced.queue.push_back((vrid, sender_mac, sender_ip));
+        }
+        Action::Deactivate { interface } => {
+            drop_paced_garps(paced_garps, interface, vrid);
+            handler.handle(action);
+        }
+        action => handler.handle(action),
+    }
+}
+
+fn drop_paced_garps(
+    paced_garps: &mut BTreeMap<InterfaceId, PacedGarps>,
+    interface: InterfaceId,
+    vrid: VRID,
+) {
+    if let Some(paced) = paced_garps.get_mut(&interface) {
+        paced.queue.retain(|(queued, ..)| *queued != vrid);
+    }
+}
+
+/// A broken invariant of a `RouterSet`, see `RouterSet::check_invariants`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvariantViolation {
+    /// The router kept under this interface and VRID is configured for another one.
+    KeyMismatch { interface: InterfaceId, vrid: VRID },
+    /// More than one Active router claims this virtual address on the interface.
+    DuplicateActiveAddress {
+        interface: InterfaceId,
+        address: Ipv4Addr,
+    },
+}
+
+pub(crate) fn validate_config(
+    config: Vec<Parameters>,
+    policy: ConfigPolicy,
+) -> Result<BTreeMap<(InterfaceId, VRID), Parameters>, Vec<ConfigError>> {
+    let mut errors = Vec::new();
+    let mut config_by_key = BTreeMap::new();
+    for parameters in config {
+        if let Err(invalid) = parameters.validate_with(policy) {
+            errors.extend(invalid);
+        }
+        let (interface, vrid) = (parameters.interface, parameters.vrid);
+        if config_by_key
+            .insert((interface, vrid), parameters)
+            .is_some()
+        {
+            errors.push(ConfigError::DuplicateVirtualRouter { interface, vrid });
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(config_by_key),
+        false => Err(errors),
+    }
+}
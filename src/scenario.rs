@@ -0,0 +1,266 @@
+//! Scripted multi-router timelines on a simulated clock and network, for testing how
+//! virtual routers interact, e.g. "at t=3s the Active router loses priority".
+//!
+//! Advertisements are delivered instantly to every other connected router with the same
+//! interface and VRID. Nothing runs in real time: `run_until` jumps from one timer or
+//! scripted event to the next.
+
+use crate::Timestamp;
+use crate::{Action, Command, Input, Parameters, ReceivedPacket, Router, SendPacket, State};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub enum ScriptedEvent {
+    Input(Input),
+    /// Replaces the parameters, e.g. to lower the priority as tracking would.
+    UpdateParameters(Box<Parameters>),
+    /// Stops delivering advertisements to and from the router.
+    Disconnect,
+    Reconnect,
+}
+
+/// A change of state, in the order they happened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub at: Duration,
+    pub router: usize,
+    pub state: &'static str,
+}
+
+pub struct Scenario {
+    start: Timestamp,
+    now: Duration,
+    routers: Vec<Simulated>,
+    script: Vec<(Duration, usize, ScriptedEvent)>,
+    transitions: Vec<Transition>,
+}
+
+struct Simulated {
+    router: Router,
+    connected: bool,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self {
+            start: Timestamp::now(),
+            now: Duration::ZERO,
+            routers: Vec::new(),
+            script: Vec::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Adds a router that starts up at the current time, returning its index.
+    pub fn add_router(&mut self, parameters: Parameters) -> usize {
+        let index = self.routers.len();
+        self.routers.push(Simulated {
+            router: Router::new(parameters),
+            connected: true,
+        });
+        self.at(self.now, index, Command::Startup);
+        index
+    }
+
+    /// Schedules the event, events at the same time happen in the order they were added.
+    pub fn at(
+        &mut self,
+        at: Duration,
+        router: usize,
+        event: impl Into<ScriptedEvent>,
+    ) -> &mut Self {
+        let index = self.script.partition_point(|(other, ..)| *other <= at);
+        self.script.insert(index, (at, router, event.into()));
+        self
+    }
+
+    /// Advances the clock, handling scripted events and timers up to and including `until`.
+    pub fn run_until(&mut self, until: Duration) {
+        while let Some(next) = self.next_event().filter(|next| *next <= until) {
+            self.now = next;
+            while self.script.first().is_some_and(|(at, ..)| *at <= next) {
+                let (_, router, event) = self.script.remove(0);
+                self.handle_scripted(router, event);
+            }
+            let now = self.start + next;
+            for router in 0..self.routers.len() {
+                if timer(self.routers[router].router.state()).is_some_and(|at| at <= now) {
+                    self.deliver(router, Input::Timer);
+                }
+            }
+        }
+        self.now = self.now.max(until);
+    }
+
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub fn router(&self, index: usize) -> &Router {
+        &self.routers[index].router
+    }
+
+    pub fn transitions(&self) -> &[Transition] {
+        &self.transitions
+    }
+
+    fn next_event(&self) -> Option<Duration> {
+        let timers = self
+            .routers
+            .iter()
+            .filter_map(|simulated| timer(simulated.router.state()))
+            .map(|at| at.saturating_duration_since(self.start));
+        let scripted = self.script.first().map(|(at, ..)| *at);
+        timers.chain(scripted).min()
+    }
+
+    fn handle_scripted(&mut self, router: usize, event: ScriptedEvent) {
+        match event {
+            ScriptedEvent::Input(input) => self.deliver(router, input),
+            ScriptedEvent::UpdateParameters(parameters) => {
+                // Invalid parameters are kept out of scripts by the caller.
+                let now = self.start + self.now;
+                let sent = self.step(router, |router| {
+                    let actions = router.update_parameters(now, *parameters);
+                    actions
+                        .into_iter()
+                        .flatten()
+                        .filter_map(advertisement)
+                        .collect()
+                });
+                let mut queue = VecDeque::new();
+                self.broadcast(router, sent, &mut queue);
+                self.deliver_all(queue);
+            }
+            ScriptedEvent::Disconnect => self.routers[router].connected = false,
+            ScriptedEvent::Reconnect => self.routers[router].connected = true,
+        }
+    }
+
+    /// Passes the input to the router and every resulting advertisement to its peers.
+    fn deliver(&mut self, router: usize, input: Input) {
+        self.deliver_all(VecDeque::from([(router, input)]));
+    }
+
+    fn deliver_all(&mut self, mut queue: VecDeque<(usize, Input)>) {
+        let now = self.start + self.now;
+        while let Some((router, input)) = queue.pop_front() {
+            let sent = self.step(router, |router| {
+                router
+                    .handle_input(now, input)
+                    .filter_map(advertisement)
+                    .collect()
+            });
+            self.broadcast(router, sent, &mut queue);
+        }
+    }
+
+    // Runs `f` on the router, recording the transition if its state changed, and returns the
+    // advertisements it sent.
+    fn step(
+        &mut self,
+        router: usize,
+        f: impl FnOnce(&mut Router) -> Vec<ReceivedPacket>,
+    ) -> Vec<ReceivedPacket> {
+        let simulated = &mut self.routers[router];
+        let before = simulated.router.state().name();
+        let sent = f(&mut simulated.router);
+        let after = simulated.router.state().name();
+        if before != after {
+            self.transitions.push(Transition {
+                at: self.now,
+                router,
+                state: after,
+            });
+        }
+        sent
+    }
+
+    // Queues the advertisements sent by the router for its connected peers.
+    fn broadcast(
+        &self,
+        router: usize,
+        sent: Vec<ReceivedPacket>,
+        queue: &mut VecDeque<(usize, Input)>,
+    ) {
+        let simulated = &self.routers[router];
+        if !simulated.connected {
+            return;
+        }
+        let parameters = simulated.router.parameters();
+        let (interface, vrid) = (parameters.interface, parameters.vrid);
+        for (peer, other) in self.routers.iter().enumerate() {
+            let parameters = other.router.parameters();
+            if peer != router
+                && other.connected
+                && parameters.interface == interface
+                && parameters.vrid == vrid
+            {
+                for packet in &sent {
+                    queue.push_back((peer, Input::Packet(packet.clone())));
+                }
+            }
+        }
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Input> for ScriptedEvent {
+    fn from(input: Input) -> Self {
+        Self::Input(input)
+    }
+}
+
+impl From<Command> for ScriptedEvent {
+    fn from(command: Command) -> Self {
+        Self::Input(command.into())
+    }
+}
+
+pub(crate) fn timer(state: &State) -> Option<Timestamp> {
+    match state {
+        State::Initialized | State::Monitoring { active: None } => None,
+        State::Backup {
+            active_down_timer, ..
+        } => Some(*active_down_timer),
+        State::Active {
+            adver_timer,
+            garp_timer,
+            ..
+        } => Some(garp_timer.map_or(*adver_timer, |garp| garp.at.min(*adver_timer))),
+        State::ShuttingDown { repeat } => Some(repeat.at),
+        State::Monitoring {
+            active: Some(active),
+        } => Some(active.active_down_timer),
+    }
+}
+
+pub(crate) fn advertisement(action: Action) -> Option<ReceivedPacket> {
+    let Action::Send { packet, .. } = action else {
+        return None;
+    };
+    let (
+        SendPacket::Advertisement { parameters, .. }
+        | SendPacket::ShutdownAdvertisement { parameters, .. }
+        | SendPacket::TakeoverIntent { parameters, .. },
+        Some(priority),
+    ) = (&packet, packet.priority())
+    else {
+        return None;
+    };
+    let takeover_intent = matches!(packet, SendPacket::TakeoverIntent { .. });
+    Some(
+        ReceivedPacket::advertisement(
+            parameters.mac_address(),
+            parameters.primary_ip(),
+            priority,
+            parameters.advertised_interval(),
+        )
+        .with_takeover_intent(takeover_intent),
+    )
+}
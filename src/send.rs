@@ -1,23 +1,258 @@
-use crate::Parameters;
+use crate::{AddressFamily, Interval, Parameters, WirePriority};
 use pnet_base::MacAddr;
+use std::fmt;
 use std::net::Ipv4Addr;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum SendPacket<'a> {
-    Advertisement(&'a Parameters),
-    ShutdownAdvertisement(&'a Parameters),
+/// The IPv4 multicast address advertisements are sent to.
+pub const VRRP_MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 18);
+/// The Ethernet address `VRRP_MULTICAST_GROUP` maps to.
+pub const VRRP_MULTICAST_MAC: MacAddr = MacAddr(0x01, 0x00, 0x5e, 0x00, 0x00, 0x12);
+/// The Ethernet address of the IPv6 all-nodes group ff02::1, unsolicited Neighbor
+/// Advertisements are sent to.
+const ALL_NODES_MAC: MacAddr = MacAddr(0x33, 0x33, 0x00, 0x00, 0x00, 0x01);
+
+/// The flags of Neighbor Advertisements besides Solicited, which their variant tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NeighborFlags {
+    /// The sender is a router.
+    pub router_flag: bool,
+    /// The advertisement replaces the link-layer address the receivers have cached.
+    pub override_flag: bool,
+}
+
+impl Default for NeighborFlags {
+    /// Both set, as RFC 9568 section 6.4.2 requires of the advertisements of a virtual
+    /// router.
+    fn default() -> Self {
+        Self {
+            router_flag: true,
+            override_flag: true,
+        }
+    }
+}
+
+impl fmt::Display for NeighborFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.router_flag {
+            write!(f, " router")?;
+        }
+        if self.override_flag {
+            write!(f, " override")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where advertisements are sent to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destination<A: AddressFamily = Ipv4Addr> {
+    /// `AddressFamily::MULTICAST_GROUP`
+    Multicast,
+    /// Each of the unicast peers, see `Parameters::with_unicast_peers`.
+    Unicast(Vec<A>),
+}
+
+/// A packet to send. Advertisements share the parameters they were built from, so packets
+/// outlive the router that produced them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SendPacket<A: AddressFamily = Ipv4Addr> {
+    Advertisement {
+        parameters: Arc<Parameters<A>>,
+        source_ip: A,
+        destination: Destination<A>,
+    },
+    ShutdownAdvertisement {
+        parameters: Arc<Parameters<A>>,
+        source_ip: A,
+        destination: Destination<A>,
+    },
+    /// An advertisement announcing that the Backup sending it is about to preempt, see
+    /// `Parameters::graceful_takeover`.
+    TakeoverIntent {
+        parameters: Arc<Parameters<A>>,
+        source_ip: A,
+        destination: Destination<A>,
+    },
+    /// IPv4 only, see `AddressFamily::ArpAddr`.
     GratuitousARP {
         sender_mac: MacAddr,
-        sender_ip: Ipv4Addr,
+        sender_ip: A::ArpAddr,
     },
+    /// IPv4 only, see `AddressFamily::ArpAddr`.
     ReplyARP {
         sender_mac: MacAddr,
-        sender_ip: Ipv4Addr,
+        sender_ip: A::ArpAddr,
+        target_mac: MacAddr,
+        target_ip: A::ArpAddr,
+        /// Sent to the Ethernet broadcast address rather than `target_mac`, see
+        /// `Parameters::broadcast_arp_replies`.
+        broadcast: bool,
+    },
+    /// An unsolicited Neighbor Advertisement to all nodes, the IPv6 counterpart of
+    /// `GratuitousARP`.
+    NeighborAdvertisement {
+        sender_mac: MacAddr,
+        sender_ip: A::NdAddr,
+        /// See `Parameters::neighbor_flags`.
+        flags: NeighborFlags,
+    },
+    /// A Neighbor Advertisement answering a Neighbor Solicitation, the IPv6 counterpart of
+    /// `ReplyARP`.
+    SolicitedNeighborAdvertisement {
+        sender_mac: MacAddr,
+        sender_ip: A::NdAddr,
         target_mac: MacAddr,
-        target_ip: Ipv4Addr,
+        target_ip: A::NdAddr,
+        flags: NeighborFlags,
     },
 }
 
+impl<A: AddressFamily> SendPacket<A> {
+    /// An advertisement of the parameters, addressed as they configure.
+    pub fn advertisement(parameters: impl Into<Arc<Parameters<A>>>) -> Self {
+        let parameters = parameters.into();
+        Self::Advertisement {
+            source_ip: parameters.primary_ip(),
+            destination: parameters.destination(),
+            parameters,
+        }
+    }
+
+    pub fn shutdown_advertisement(parameters: impl Into<Arc<Parameters<A>>>) -> Self {
+        let parameters = parameters.into();
+        Self::ShutdownAdvertisement {
+            source_ip: parameters.primary_ip(),
+            destination: parameters.destination(),
+            parameters,
+        }
+    }
+
+    pub fn takeover_intent(parameters: impl Into<Arc<Parameters<A>>>) -> Self {
+        let parameters = parameters.into();
+        Self::TakeoverIntent {
+            source_ip: parameters.primary_ip(),
+            destination: parameters.destination(),
+            parameters,
+        }
+    }
+
+    /// The priority carried by an advertisement: the one of the mode, or
+    /// `WirePriority::Shutdown` when the Active router shuts down.
+    pub fn priority(&self) -> Option<WirePriority> {
+        match self {
+            SendPacket::Advertisement { parameters, .. }
+            | SendPacket::TakeoverIntent { parameters, .. } => {
+                Some(parameters.mode.priority().get().into())
+            }
+            SendPacket::ShutdownAdvertisement { .. } => Some(WirePriority::Shutdown),
+            SendPacket::GratuitousARP { .. }
+            | SendPacket::ReplyARP { .. }
+            | SendPacket::NeighborAdvertisement { .. }
+            | SendPacket::SolicitedNeighborAdvertisement { .. } => None,
+        }
+    }
+
+    /// The Ethernet destination of the packet: `AddressFamily::MULTICAST_MAC` for multicast
+    /// advertisements, the broadcast address for gratuitous ARPs, the all-nodes address for
+    /// Neighbor Advertisements, and the requester or the broadcast address for ARP replies.
+    /// `None` for unicast advertisements, whose peers' addresses the IO layer resolves.
+    pub fn destination_mac(&self) -> Option<MacAddr> {
+        match self {
+            SendPacket::Advertisement { destination, .. }
+            | SendPacket::ShutdownAdvertisement { destination, .. }
+            | SendPacket::TakeoverIntent { destination, .. } => match destination {
+                Destination::Multicast => Some(A::MULTICAST_MAC),
+                Destination::Unicast(_) => None,
+            },
+            SendPacket::GratuitousARP { .. } => Some(MacAddr::broadcast()),
+            SendPacket::NeighborAdvertisement { .. } => Some(ALL_NODES_MAC),
+            SendPacket::SolicitedNeighborAdvertisement { target_mac, .. } => Some(*target_mac),
+            SendPacket::ReplyARP {
+                target_mac,
+                broadcast,
+                ..
+            } => match broadcast {
+                true => Some(MacAddr::broadcast()),
+                false => Some(*target_mac),
+            },
+        }
+    }
+
+    /// The Max Advertise Interval carried by an advertisement, including the priority 0
+    /// advertisement sent on shutdown.
+    pub fn max_advertise_interval(&self) -> Option<Interval> {
+        match self {
+            SendPacket::Advertisement { parameters, .. }
+            | SendPacket::ShutdownAdvertisement { parameters, .. }
+            | SendPacket::TakeoverIntent { parameters, .. } => {
+                Some(parameters.advertised_interval())
+            }
+            SendPacket::GratuitousARP { .. }
+            | SendPacket::ReplyARP { .. }
+            | SendPacket::NeighborAdvertisement { .. }
+            | SendPacket::SolicitedNeighborAdvertisement { .. } => None,
+        }
+    }
+}
+
+impl<A: AddressFamily> fmt::Display for SendPacket<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendPacket::Advertisement { parameters, .. }
+            | SendPacket::ShutdownAdvertisement { parameters, .. } => write!(
+                f,
+                "ADVERTISEMENT vrid={} prio={} interval={}",
+                u8::from(parameters.vrid),
+                self.priority().unwrap_or(WirePriority::Shutdown),
+                parameters.advertised_interval()
+            ),
+            SendPacket::TakeoverIntent { parameters, .. } => write!(
+                f,
+                "TAKEOVER-INTENT vrid={} prio={} interval={}",
+                u8::from(parameters.vrid),
+                parameters.mode.priority(),
+                parameters.advertised_interval()
+            ),
+            SendPacket::GratuitousARP {
+                sender_mac,
+                sender_ip,
+            } => write!(f, "GARP {sender_ip} is-at {sender_mac}"),
+            SendPacket::NeighborAdvertisement {
+                sender_mac,
+                sender_ip,
+                flags,
+            } => write!(f, "NA {sender_ip} is-at {sender_mac}{flags}"),
+            SendPacket::SolicitedNeighborAdvertisement {
+                sender_mac,
+                sender_ip,
+                target_mac,
+                target_ip,
+                flags,
+            } => write!(
+                f,
+                "NA {sender_ip} is-at {sender_mac} to {target_ip} ({target_mac}){flags}"
+            ),
+            SendPacket::ReplyARP {
+                sender_mac,
+                sender_ip,
+                target_mac,
+                target_ip,
+                broadcast,
+            } => {
+                write!(
+                    f,
+                    "ARP REPLY {sender_ip} is-at {sender_mac} to {target_ip} ({target_mac})"
+                )?;
+                match broadcast {
+                    true => write!(f, " broadcast"),
+                    false => Ok(()),
+                }
+            }
+        }
+    }
+}
+
 // VRRP advertisement
 // {
 //     // VRRP pakcet
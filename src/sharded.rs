@@ -0,0 +1,147 @@
+use crate::router_set::validate_config;
+use crate::{
+    ActionHandler, Clock, ConfigError, ConfigPolicy, Input, InterfaceId, Parameters, RouterSet,
+    SystemClock, VRID,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A `RouterSet` partitioned by interface and VRID across worker threads, for hosts
+/// running hundreds of virtual routers.
+///
+/// Every shard owns its virtual routers, timers and action handler, and is fed over a
+/// channel so dispatching an input never waits for another shard.
+pub struct ShardedRouterSet {
+    shards: Vec<Shard>,
+    config_policy: ConfigPolicy,
+}
+
+struct Shard {
+    sender: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+enum Message {
+    Config(Vec<Parameters>),
+    Input(InterfaceId, VRID, Input),
+}
+
+// Without any router the shard still wakes up now and then, which is harmless.
+const IDLE: Duration = Duration::from_secs(1);
+
+impl ShardedRouterSet {
+    /// Starts `shards` worker threads, each passing its actions to the handler built for it.
+    pub fn new<H>(shards: usize, handler: impl FnMut(usize) -> H) -> Self
+    where
+        H: ActionHandler + Send + 'static,
+    {
+        Self::with_clock(shards, SystemClock, handler)
+    }
+
+    /// Like `new`, with the workers taking the time from `clock`.
+    pub fn with_clock<C, H>(shards: usize, clock: C, mut handler: impl FnMut(usize) -> H) -> Self
+    where
+        C: Clock + Clone + Send + 'static,
+        H: ActionHandler + Send + 'static,
+    {
+        let shards = (0..shards.max(1))
+            .map(|index| {
+                let (sender, receiver) = mpsc::channel();
+                let handler = handler(index);
+                let clock = clock.clone();
+                let thread = thread::spawn(move || run(receiver, clock, handler));
+                Shard {
+                    sender,
+                    thread: Some(thread),
+                }
+            })
+            .collect();
+        Self {
+            shards,
+            config_policy: ConfigPolicy::default(),
+        }
+    }
+
+    /// See `RouterSet::with_config_policy`.
+    pub fn with_config_policy(mut self, config_policy: ConfigPolicy) -> Self {
+        self.config_policy = config_policy;
+        self
+    }
+
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Like `RouterSet::apply_config`, with the configuration validated up front and the
+    /// actions passed to the handlers of the shards.
+    pub fn apply_config(&self, config: Vec<Parameters>) -> Result<(), Vec<ConfigError>> {
+        let mut partitions = vec![Vec::new(); self.shards.len()];
+        for (key, parameters) in validate_config(config, self.config_policy)? {
+            partitions[self.shard_of(key.0, key.1)].push(parameters);
+        }
+        for (shard, config) in self.shards.iter().zip(partitions) {
+            let _ = shard.sender.send(Message::Config(config));
+        }
+        Ok(())
+    }
+
+    /// Queues the input for the virtual router; inputs for unknown ones are dropped.
+    pub fn dispatch(&self, interface: InterfaceId, vrid: VRID, input: Input) {
+        let shard = &self.shards[self.shard_of(interface, vrid)];
+        let _ = shard.sender.send(Message::Input(interface, vrid, input));
+    }
+
+    /// Gracefully shuts down every virtual router and waits for the workers to finish.
+    pub fn shutdown(self) {
+        let _ = self.apply_config(Vec::new());
+    }
+
+    fn shard_of(&self, interface: InterfaceId, vrid: VRID) -> usize {
+        let mut hasher = DefaultHasher::new();
+        (interface, vrid).hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+}
+
+impl Drop for ShardedRouterSet {
+    fn drop(&mut self) {
+        for shard in &mut self.shards {
+            // Closing the channel stops the worker once it drained the queued messages.
+            let (closed, _) = mpsc::channel();
+            shard.sender = closed;
+            if let Some(thread) = shard.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+fn run(receiver: Receiver<Message>, clock: impl Clock, mut handler: impl ActionHandler) {
+    let mut routers = RouterSet::new();
+    loop {
+        let now = clock.now();
+        let timeout = routers
+            .next_timer(now)
+            .map_or(IDLE, |next| next.saturating_duration_since(now));
+        let message = receiver.recv_timeout(timeout);
+
+        let now = clock.now();
+        match message {
+            Ok(Message::Config(config)) => {
+                // Validated by `ShardedRouterSet::apply_config`.
+                if let Ok(actions) = routers.apply_config(now, config) {
+                    actions.for_each(|action| handler.handle(action));
+                }
+            }
+            Ok(Message::Input(interface, vrid, input)) => {
+                routers.dispatch(now, interface, vrid, input, &mut handler);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        routers.handle_timers(now, &mut handler);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Mode, Router};
+use std::fmt::Write;
+
+impl Router {
+    /// Describes the state machine of this virtual router as a Graphviz digraph,
+    /// with the current state highlighted.
+    pub fn dot(&self) -> String {
+        let parameters = self.parameters();
+        let current = self.state().name();
+        let preempt = parameters.mode.should_preempt();
+
+        let mut edges = vec![];
+        match parameters.mode {
+            Mode::Owner => edges.push(("Initialized", "Active", "Startup")),
+            Mode::Monitor => {
+                edges.push(("Initialized", "Monitoring", "Startup"));
+                edges.push(("Monitoring", "Monitoring", "ADVERTISEMENT"));
+                edges.push(("Monitoring", "Initialized", "Shutdown"));
+            }
+            Mode::Backup(_) => {
+                edges.push(("Initialized", "Backup", "Startup"));
+                edges.push(("Backup", "Active", "Active_Down_Timer fires"));
+                edges.push(("Backup", "Backup", "higher priority ADVERTISEMENT"));
+                if !preempt {
+                    edges.push(("Backup", "Backup", "lower priority ADVERTISEMENT"));
+                }
+                edges.push(("Backup", "Backup", "priority 0 ADVERTISEMENT"));
+                edges.push(("Backup", "Initialized", "Shutdown"));
+                edges.push(("Active", "Backup", "higher priority ADVERTISEMENT"));
+            }
+        }
+        if parameters.mode != Mode::Monitor {
+            edges.push(("Active", "Active", "Adver_Timer fires"));
+            edges.push(("Active", "Active", "lower priority ADVERTISEMENT"));
+            if parameters.shutdown_repeat > 0 {
+                edges.push(("Active", "ShuttingDown", "Shutdown"));
+                edges.push(("ShuttingDown", "ShuttingDown", "Repeat_Timer fires"));
+                edges.push(("ShuttingDown", "Initialized", "last repeat sent"));
+            } else {
+                edges.push(("Active", "Initialized", "Shutdown"));
+            }
+        }
+
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph vrid_{} {{", u8::from(parameters.vrid));
+        let _ = writeln!(
+            dot,
+            "  label=\"VRID {} priority {}{}\";",
+            u8::from(parameters.vrid),
+            parameters.mode.priority(),
+            if preempt { "" } else { " (no preempt)" },
+        );
+        let mut states = match parameters.mode {
+            Mode::Owner => vec!["Initialized", "Active"],
+            Mode::Backup(_) => vec!["Initialized", "Backup", "Active"],
+            Mode::Monitor => vec!["Initialized", "Monitoring"],
+        };
+        if parameters.mode != Mode::Monitor && parameters.shutdown_repeat > 0 {
+            states.push("ShuttingDown");
+        }
+        for state in states {
+            let style = if state == current {
+                " style=filled fillcolor=lightblue"
+            } else {
+                ""
+            };
+            let _ = writeln!(dot, "  {state} [shape=box{style}];");
+        }
+        for (from, to, label) in edges {
+            let _ = writeln!(dot, "  {from} -> {to} [label=\"{label}\"];");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
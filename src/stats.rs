@@ -0,0 +1,39 @@
+use crate::Jitter;
+
+/// Counters of noteworthy events seen by a `Router`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Changes between the Backup and Active states.
+    pub transitions: u64,
+    pub advertisements_sent: u64,
+    /// Advertisements received after startup, including shutdown advertisements.
+    pub advertisements_received: u64,
+    /// Advertisements whose source MAC address is not the virtual router MAC address.
+    pub sender_mac_mismatches: u64,
+    /// ARP packets from other hosts claiming a virtual address while Active.
+    pub address_conflicts: u64,
+    /// Advertisements received while Active carrying our own primary address and priority.
+    pub reflected_advertisements: u64,
+    /// Shutdown advertisements ignored by a Backup because they did not come from its Active router.
+    pub rejected_shutdown_advertisements: u64,
+    /// Our own advertisements looped back and dropped, see
+    /// `Parameters::drop_own_advertisements`.
+    pub own_advertisements_dropped: u64,
+    /// Advertisements with a Max Advertise Interval of 0 taken as 1 centisecond, see
+    /// `Parameters::zero_interval_as_one_centi`.
+    pub compat_zero_intervals: u64,
+    /// ARP requests left unanswered by `Parameters::arp_reply_limit`.
+    pub arp_requests_rate_limited: u64,
+    pub advertisement_jitter: Jitter,
+}
+
+/// The advertisements of the virtual routers on one interface, see
+/// `RouterSet::interface_stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InterfaceStats {
+    pub routers: usize,
+    pub active: usize,
+    pub advertisements_sent: u64,
+    /// The advertisements the Active routers send per second at their current intervals.
+    pub advertisements_per_second: f64,
+}
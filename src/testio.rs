@@ -0,0 +1,131 @@
+//! A loopback transport connecting two routers in the same process, for examples and tests.
+
+use crate::{
+    wire, ActionHandler, Event, InterfaceId, Level, LogEvent, ReceivedPacket, RoutePacket,
+    SendPacket, VirtualAddresses,
+};
+use pnet_base::MacAddr;
+use std::io;
+use std::net::Ipv4Addr;
+use std::os::unix::net::UnixDatagram;
+
+// Every frame is prefixed with the source MAC and source IP address of the sender.
+const SOURCE_LEN: usize = 10;
+
+/// One end of a datagram socketpair carrying VRRP advertisements.
+///
+/// Actions are passed to it through `Router::dispatch`: advertisements are written to the
+/// peer, `Activate`/`Deactivate` are recorded in `is_active`, everything else is dropped.
+pub struct LoopbackTransport {
+    socket: UnixDatagram,
+    active: bool,
+}
+
+impl LoopbackTransport {
+    pub fn pair() -> io::Result<(Self, Self)> {
+        let (left, right) = UnixDatagram::pair()?;
+        left.set_nonblocking(true)?;
+        right.set_nonblocking(true)?;
+        Ok((
+            Self {
+                socket: left,
+                active: false,
+            },
+            Self {
+                socket: right,
+                active: false,
+            },
+        ))
+    }
+
+    /// Whether the virtual addresses are currently activated on this end.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Returns the next packet sent by the peer, if any.
+    pub fn receive(&self) -> io::Result<Option<ReceivedPacket>> {
+        let mut buffer = [0u8; 1500];
+        loop {
+            let length = match self.socket.recv(&mut buffer) {
+                Ok(length) => length,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(error) => return Err(error),
+            };
+            if let Some(packet) = decode(&buffer[..length]) {
+                return Ok(Some(packet));
+            }
+        }
+    }
+
+    fn write(&self, frame: Result<Vec<u8>, wire::WireError>) {
+        // A full socket buffer behaves like a lossy link, and so does an unencodable frame.
+        if let Ok(frame) = frame {
+            let _ = self.socket.send(&frame);
+        }
+    }
+}
+
+impl ActionHandler for LoopbackTransport {
+    fn join_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+
+    fn leave_multicast(&mut self, _: InterfaceId, _: Ipv4Addr) {}
+
+    fn activate(&mut self, _: InterfaceId, _: VirtualAddresses) {
+        self.active = true;
+    }
+
+    fn deactivate(&mut self, _: InterfaceId) {
+        self.active = false;
+    }
+
+    fn send(&mut self, _: InterfaceId, packet: SendPacket) {
+        if let Some(message) = wire::encode_packet(&packet) {
+            self.write(message.map(|message| frame(&packet, message)));
+        }
+    }
+
+    fn route(&mut self, _: RoutePacket) {}
+
+    fn notify(&mut self, _: Event) {}
+
+    fn log(&mut self, _: Level, _: LogEvent) {}
+}
+
+// Prefixes the message with the sender of the advertisement.
+fn frame(packet: &SendPacket, message: Vec<u8>) -> Vec<u8> {
+    let mut frame = Vec::new();
+    if let SendPacket::Advertisement { parameters, .. }
+    | SendPacket::ShutdownAdvertisement { parameters, .. }
+    | SendPacket::TakeoverIntent { parameters, .. } = packet
+    {
+        frame.extend_from_slice(&mac_octets(parameters.mac_address()));
+        frame.extend_from_slice(&parameters.primary_ip().octets());
+    }
+    frame.extend(message);
+    frame
+}
+
+fn decode(frame: &[u8]) -> Option<ReceivedPacket> {
+    if frame.len() < SOURCE_LEN {
+        return None;
+    }
+    let sender_mac = MacAddr(frame[0], frame[1], frame[2], frame[3], frame[4], frame[5]);
+    let sender_ip = Ipv4Addr::new(frame[6], frame[7], frame[8], frame[9]);
+
+    let advertisement = wire::decode(&frame[SOURCE_LEN..]).ok()?;
+    Some(
+        ReceivedPacket::advertisement(
+            sender_mac,
+            sender_ip,
+            advertisement.priority,
+            advertisement.max_advertise_interval,
+        )
+        .with_takeover_intent(advertisement.takeover_intent),
+    )
+}
+
+fn mac_octets(mac_address: MacAddr) -> [u8; 6] {
+    let MacAddr(a, b, c, d, e, f) = mac_address;
+    [a, b, c, d, e, f]
+}
@@ -0,0 +1,55 @@
+use crate::Interval;
+use std::ops::Add;
+use std::time::{Duration, Instant};
+
+/// A point on the timeline of a `Router`.
+///
+/// Every time a router is given or hands out is a `Timestamp`, so instants of unrelated
+/// clocks cannot be mixed up with its timers by accident; conversions are explicit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(Instant);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        Self(Instant::now())
+    }
+
+    pub const fn from_instant(instant: Instant) -> Self {
+        Self(instant)
+    }
+
+    pub const fn as_instant(self) -> Instant {
+        self.0
+    }
+
+    pub fn checked_sub(self, duration: Duration) -> Option<Timestamp> {
+        self.0.checked_sub(duration).map(Self)
+    }
+
+    /// The time elapsed since `earlier`, zero if it is later.
+    pub fn saturating_duration_since(self, earlier: Timestamp) -> Duration {
+        self.0.saturating_duration_since(earlier.0)
+    }
+}
+
+impl From<Timestamp> for Instant {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl Add<Interval> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Interval) -> Self::Output {
+        Self(self.0 + rhs)
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        Self(self.0 + rhs)
+    }
+}
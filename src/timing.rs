@@ -0,0 +1,14 @@
+//! The timer arithmetic of https://datatracker.ietf.org/doc/html/rfc9568#section-6.1
+
+use crate::Interval;
+use std::num::NonZeroU8;
+
+/// Skew_Time: `((256 - Priority) * Active_Adver_Interval) / 256`
+pub fn skew_time(priority: NonZeroU8, active_adver_interval: Interval) -> Interval {
+    ((256 - priority.get() as u16) * active_adver_interval) / 256
+}
+
+/// Active_Down_Interval: `(3 * Active_Adver_Interval) + Skew_Time`
+pub fn active_down_interval(priority: NonZeroU8, active_adver_interval: Interval) -> Interval {
+    3 * active_adver_interval + skew_time(priority, active_adver_interval)
+}
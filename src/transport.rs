@@ -0,0 +1,168 @@
+//! Sending and receiving raw Ethernet frames, so IO drivers do not depend on a particular
+//! capture backend.
+
+use crate::{Clock, SystemClock, Timestamp};
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+
+/// A link that carries whole Ethernet frames.
+pub trait Transport {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()>;
+
+    /// Waits until `deadline` for the next frame and copies it into `buffer`, truncated to
+    /// its length. Returns the length copied, or `None` if no frame arrived in time.
+    fn recv_frame(&mut self, buffer: &mut [u8], deadline: Timestamp) -> io::Result<Option<usize>>;
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        (**self).send_frame(frame)
+    }
+
+    fn recv_frame(&mut self, buffer: &mut [u8], deadline: Timestamp) -> io::Result<Option<usize>> {
+        (**self).recv_frame(buffer, deadline)
+    }
+}
+
+/// One end of an in-process link, for tests and examples.
+pub struct MemoryTransport<C = SystemClock> {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    clock: C,
+}
+
+impl MemoryTransport {
+    /// Two ends receiving the frames sent by each other.
+    pub fn pair() -> (Self, Self) {
+        let (left_sender, right_receiver) = mpsc::channel();
+        let (right_sender, left_receiver) = mpsc::channel();
+        (
+            Self {
+                sender: left_sender,
+                receiver: left_receiver,
+                clock: SystemClock,
+            },
+            Self {
+                sender: right_sender,
+                receiver: right_receiver,
+                clock: SystemClock,
+            },
+        )
+    }
+}
+
+impl<C: Clock> MemoryTransport<C> {
+    /// Measures the receive deadlines on `clock`.
+    pub fn with_clock<D: Clock>(self, clock: D) -> MemoryTransport<D> {
+        MemoryTransport {
+            sender: self.sender,
+            receiver: self.receiver,
+            clock,
+        }
+    }
+}
+
+impl<C: Clock> Transport for MemoryTransport<C> {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        // Like a cable without anyone at the other end, frames to a dropped peer are lost.
+        let _ = self.sender.send(frame.to_vec());
+        Ok(())
+    }
+
+    fn recv_frame(&mut self, buffer: &mut [u8], deadline: Timestamp) -> io::Result<Option<usize>> {
+        let timeout = deadline.saturating_duration_since(self.clock.now());
+        match self.receiver.recv_timeout(timeout) {
+            Ok(frame) => Ok(Some(copy(&frame, buffer))),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+fn copy(frame: &[u8], buffer: &mut [u8]) -> usize {
+    let length = frame.len().min(buffer.len());
+    buffer[..length].copy_from_slice(&frame[..length]);
+    length
+}
+
+#[cfg(feature = "linux")]
+mod packet;
+#[cfg(feature = "pcap-dump")]
+mod pcap;
+
+#[cfg(feature = "linux")]
+pub use packet::PacketTransport;
+#[cfg(feature = "pcap-dump")]
+pub use pcap::{PcapDump, PcapSwitch};
+#[cfg(feature = "datalink")]
+pub use pnet::PnetTransport;
+
+#[cfg(feature = "datalink")]
+mod pnet {
+    use super::{copy, Transport};
+    use crate::{Clock, SystemClock, Timestamp};
+    use pnet_datalink::{Channel, Config, DataLinkReceiver, DataLinkSender, NetworkInterface};
+    use std::io;
+    use std::time::Duration;
+
+    // How often a blocked receive checks whether its deadline passed.
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// A raw socket on a network interface, opened through `pnet_datalink`.
+    pub struct PnetTransport<C = SystemClock> {
+        sender: Box<dyn DataLinkSender>,
+        receiver: Box<dyn DataLinkReceiver>,
+        clock: C,
+    }
+
+    impl PnetTransport {
+        pub fn open(interface: &NetworkInterface) -> io::Result<Self> {
+            let config = Config {
+                read_timeout: Some(POLL_INTERVAL),
+                ..Default::default()
+            };
+            match pnet_datalink::channel(interface, config)? {
+                Channel::Ethernet(sender, receiver) => Ok(Self {
+                    sender,
+                    receiver,
+                    clock: SystemClock,
+                }),
+                _ => Err(io::Error::other("unsupported channel type")),
+            }
+        }
+    }
+
+    impl<C: Clock> PnetTransport<C> {
+        /// Measures the receive deadlines on `clock`.
+        pub fn with_clock<D: Clock>(self, clock: D) -> PnetTransport<D> {
+            PnetTransport {
+                sender: self.sender,
+                receiver: self.receiver,
+                clock,
+            }
+        }
+    }
+
+    impl<C: Clock> Transport for PnetTransport<C> {
+        fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+            self.sender
+                .send_to(frame, None)
+                .unwrap_or_else(|| Err(io::Error::other("frame not sent")))
+        }
+
+        fn recv_frame(
+            &mut self,
+            buffer: &mut [u8],
+            deadline: Timestamp,
+        ) -> io::Result<Option<usize>> {
+            while self.clock.now() < deadline {
+                match self.receiver.next() {
+                    Ok(frame) => return Ok(Some(copy(frame, buffer))),
+                    Err(error) if error.kind() == io::ErrorKind::TimedOut => {}
+                    Err(error) => return Err(error),
+                }
+            }
+            Ok(None)
+        }
+    }
+}
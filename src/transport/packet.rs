@@ -0,0 +1,181 @@
+use super::{copy, Transport};
+use crate::filter::Instruction;
+use crate::linux::interface_index;
+use crate::{Clock, SystemClock, Timestamp};
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+// https://man7.org/linux/man-pages/man7/packet.7.html
+const PACKET_FANOUT: libc::c_int = 18;
+const PACKET_FANOUT_HASH: u32 = 0;
+const PACKET_FANOUT_FLAG_DEFRAG: u32 = 0x8000;
+// https://man7.org/linux/man-pages/man7/socket.7.html
+const SO_ATTACH_FILTER: libc::c_int = 26;
+
+const ETH_P_ALL: u16 = 0x0003;
+
+// Accepts ARP, and IPv4 carrying VRRP (protocol 112), in full.
+const FILTER: [(u16, u8, u8, u32); 7] = [
+    (0x28, 0, 0, 12),      // ldh [12], the EtherType
+    (0x15, 3, 0, 0x0806),  // jeq ARP, accept
+    (0x15, 0, 3, 0x0800),  // jne IPv4, drop
+    (0x30, 0, 0, 23),      // ldb [23], the IPv4 protocol
+    (0x15, 0, 1, 112),     // jne VRRP, drop
+    (0x06, 0, 0, 0x40000), // accept
+    (0x06, 0, 0, 0),       // drop
+];
+
+/// An `AF_PACKET` socket on a Linux interface, with a socket filter so the kernel only
+/// passes VRRP advertisements and ARP packets to user space.
+pub struct PacketTransport<C = SystemClock> {
+    socket: OwnedFd,
+    clock: C,
+}
+
+impl PacketTransport {
+    pub fn open(interface: &str) -> io::Result<Self> {
+        let interface_index = interface_index(interface)?;
+
+        // Created for no protocol so nothing is queued before the filter is attached.
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut filter = FILTER.map(|(code, jt, jf, k)| libc::sock_filter { code, jt, jf, k });
+        let program = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_mut_ptr(),
+        };
+        set_option(&socket, libc::SOL_SOCKET, SO_ATTACH_FILTER, &program)?;
+
+        let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        address.sll_family = libc::AF_PACKET as libc::c_ushort;
+        address.sll_protocol = ETH_P_ALL.to_be();
+        address.sll_ifindex = interface_index as libc::c_int;
+        let result = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            socket,
+            clock: SystemClock,
+        })
+    }
+}
+
+impl<C: Clock> PacketTransport<C> {
+    /// Measures the receive deadlines on `clock`.
+    pub fn with_clock<D: Clock>(self, clock: D) -> PacketTransport<D> {
+        PacketTransport {
+            socket: self.socket,
+            clock,
+        }
+    }
+
+    /// Replaces the socket filter, e.g. with `RouterSet::capture_filter` to only receive the
+    /// traffic of the configured virtual routers.
+    pub fn set_filter(&self, program: &[Instruction]) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: program.len() as libc::c_ushort,
+            filter: program.as_ptr() as *mut libc::sock_filter,
+        };
+        set_option(&self.socket, libc::SOL_SOCKET, SO_ATTACH_FILTER, &program)
+    }
+
+    /// Joins the fanout group `group`, spreading the received frames over every socket in
+    /// it by flow, so all advertisements of one sender reach the same socket.
+    pub fn join_fanout(&self, group: u16) -> io::Result<()> {
+        let fanout = u32::from(group) | (PACKET_FANOUT_HASH | PACKET_FANOUT_FLAG_DEFRAG) << 16;
+        set_option(&self.socket, libc::SOL_PACKET, PACKET_FANOUT, &fanout)
+    }
+}
+
+impl<C: Clock> Transport for PacketTransport<C> {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let result = unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                frame.as_ptr() as *const libc::c_void,
+                frame.len(),
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn recv_frame(&mut self, buffer: &mut [u8], deadline: Timestamp) -> io::Result<Option<usize>> {
+        let mut frame = [0u8; 1514];
+        loop {
+            let timeout = deadline.saturating_duration_since(self.clock.now());
+            let mut poll = libc::pollfd {
+                fd: self.socket.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // Rounded up so the deadline has passed when poll times out.
+            let timeout = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as libc::c_int;
+            match unsafe { libc::poll(&mut poll, 1, timeout) } {
+                0 => return Ok(None),
+                result if result < 0 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(error);
+                }
+                _ => {}
+            }
+            let length = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    frame.as_mut_ptr() as *mut libc::c_void,
+                    frame.len(),
+                    libc::MSG_DONTWAIT,
+                )
+            };
+            if length >= 0 {
+                return Ok(Some(copy(&frame[..length as usize], buffer)));
+            }
+            let error = io::Error::last_os_error();
+            if !matches!(
+                error.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+            ) {
+                return Err(error);
+            }
+        }
+    }
+}
+
+fn set_option<T>(
+    socket: &OwnedFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: &T,
+) -> io::Result<()> {
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use super::Transport;
+use crate::{Timestamp, VRID};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const PROTOCOL_VRRP: u8 = 112;
+const LINKTYPE_ETHERNET: u32 = 1;
+const SNAPLEN: u32 = 65535;
+const HEADER_LEN: u64 = 24;
+const RECORD_HEADER_LEN: u64 = 16;
+
+/// Turns a `PcapDump` on and off, from any thread.
+#[derive(Clone, Debug)]
+pub struct PcapSwitch(Arc<AtomicBool>);
+
+impl PcapSwitch {
+    pub fn set(&self, on: bool) {
+        self.0.store(on, Ordering::Relaxed);
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Mirrors the VRRP and ARP frames sent and received through a transport to pcap files, for
+/// debugging election disputes in the field.
+///
+/// The current file is `path`; when it grows past the maximum size it is renamed to
+/// `path.1`, the previous `path.1` to `path.2`, and so on, keeping a bounded number of files.
+pub struct PcapDump<T> {
+    inner: T,
+    switch: PcapSwitch,
+    vrids: Vec<VRID>,
+    path: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+    file: Option<File>,
+    written: u64,
+    error: Option<io::Error>,
+}
+
+impl<T: Transport> PcapDump<T> {
+    /// Dumps every VRRP and ARP frame, to files of up to 10 MB, keeping 5 of them.
+    pub fn new(inner: T, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            switch: PcapSwitch(Arc::new(AtomicBool::new(true))),
+            vrids: Vec::new(),
+            path: path.into(),
+            max_file_size: 10_000_000,
+            max_files: 5,
+            file: None,
+            written: 0,
+            error: None,
+        }
+    }
+
+    /// Only dumps the advertisements of these VRIDs, and the ARP frames from their virtual
+    /// MAC addresses.
+    pub fn with_vrids(self, vrids: Vec<VRID>) -> Self {
+        Self { vrids, ..self }
+    }
+
+    pub fn with_rotation(self, max_file_size: u64, max_files: usize) -> Self {
+        Self {
+            max_file_size,
+            max_files: max_files.max(1),
+            ..self
+        }
+    }
+
+    pub fn switch(&self) -> PcapSwitch {
+        self.switch.clone()
+    }
+
+    /// The error that turned the dump off, if writing it failed. Frames are still passed on.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn dump(&mut self, frame: &[u8]) {
+        if !self.switch.is_on() || !self.is_dumped(frame) {
+            return;
+        }
+        if let Err(error) = self.write_record(frame) {
+            self.switch.set(false);
+            self.file = None;
+            self.error = Some(error);
+        }
+    }
+
+    fn is_dumped(&self, frame: &[u8]) -> bool {
+        let Some(ethertype) = frame.get(12..14) else {
+            return false;
+        };
+        let vrid = match u16::from_be_bytes([ethertype[0], ethertype[1]]) {
+            // The sender hardware address of the ARP packet.
+            ETHERTYPE_ARP => match frame.get(22..28) {
+                Some([0x00, 0x00, 0x5E, 0x00, 0x01, vrid]) => Some(*vrid),
+                Some(_) => None,
+                None => return false,
+            },
+            ETHERTYPE_IPV4 => {
+                let Some(&version_ihl) = frame.get(14) else {
+                    return false;
+                };
+                if frame.get(23) != Some(&PROTOCOL_VRRP) {
+                    return false;
+                }
+                let header_len = 4 * (version_ihl & 0x0f) as usize;
+                match frame.get(14 + header_len + 1) {
+                    Some(&vrid) => Some(vrid),
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+        self.vrids.is_empty()
+            || vrid.is_some_and(|vrid| self.vrids.iter().any(|&other| u8::from(other) == vrid))
+    }
+
+    fn write_record(&mut self, frame: &[u8]) -> io::Result<()> {
+        let length = frame.len().min(SNAPLEN as usize);
+        if self.file.is_some()
+            && self.written + RECORD_HEADER_LEN + length as u64 > self.max_file_size
+        {
+            self.file = None;
+            self.rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let mut file = File::create(&self.path)?;
+                file.write_all(&header())?;
+                self.written = HEADER_LEN;
+                self.file.insert(file)
+            }
+        };
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + length);
+        record.extend_from_slice(&(time.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&time.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&(length as u32).to_le_bytes());
+        record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        record.extend_from_slice(&frame[..length]);
+        file.write_all(&record)?;
+        self.written += record.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        let rotated = |index: usize| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{index}"));
+            PathBuf::from(path)
+        };
+        for index in (1..self.max_files).rev() {
+            let from = match index {
+                1 => self.path.clone(),
+                index => rotated(index - 1),
+            };
+            match fs::rename(&from, rotated(index)) {
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for PcapDump<T> {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.inner.send_frame(frame)?;
+        self.dump(frame);
+        Ok(())
+    }
+
+    fn recv_frame(&mut self, buffer: &mut [u8], deadline: Timestamp) -> io::Result<Option<usize>> {
+        let received = self.inner.recv_frame(buffer, deadline)?;
+        if let Some(length) = received {
+            self.dump(&buffer[..length]);
+        }
+        Ok(received)
+    }
+}
+
+// The pcap file header, with microsecond timestamps.
+fn header() -> [u8; HEADER_LEN as usize] {
+    let mut header = [0; HEADER_LEN as usize];
+    header[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    header[4..6].copy_from_slice(&2u16.to_le_bytes());
+    header[6..8].copy_from_slice(&4u16.to_le_bytes());
+    header[16..20].copy_from_slice(&SNAPLEN.to_le_bytes());
+    header[20..24].copy_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    header
+}
@@ -1,10 +1,13 @@
 use pnet_base::MacAddr;
 use std::num::NonZeroU8;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct VRID(NonZeroU8);
 
 impl VRID {
+    pub const MIN: VRID = VRID(NonZeroU8::MIN);
+    pub const MAX: VRID = VRID(NonZeroU8::MAX);
+
     pub fn into_mac_address(self) -> MacAddr {
         // https://datatracker.ietf.org/doc/html/rfc9568#section-7.3
         //    The virtual router MAC address associated with a virtual router is an
@@ -37,3 +40,9 @@ impl TryFrom<u8> for VRID {
         Ok(VRID(value.try_into()?))
     }
 }
+
+impl From<VRID> for u8 {
+    fn from(vrid: VRID) -> Self {
+        vrid.0.get()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{Action, InterfaceId, VirtualAddress, VirtualAddresses};
+use std::io;
+use std::mem;
+use windows_sys::Win32::Foundation::{
+    ERROR_NOT_FOUND, ERROR_OBJECT_ALREADY_EXISTS, NO_ERROR, WIN32_ERROR,
+};
+use windows_sys::Win32::NetworkManagement::IpHelper::{
+    ConvertInterfaceAliasToLuid, ConvertInterfaceLuidToIndex, CreateUnicastIpAddressEntry,
+    DeleteUnicastIpAddressEntry, InitializeUnicastIpAddressEntry, MIB_UNICASTIPADDRESS_ROW,
+};
+use windows_sys::Win32::NetworkManagement::Ndis::NET_LUID_LH;
+use windows_sys::Win32::Networking::WinSock::{
+    IpDadStatePreferred, IpPrefixOriginManual, IpSuffixOriginManual, AF_INET, IN_ADDR, IN_ADDR_0,
+    SOCKADDR_IN,
+};
+
+/// Carries out `Action::Activate` and `Action::Deactivate` by adding and removing the
+/// virtual addresses on a Windows interface through the IP Helper API. Frames are sent and
+/// received with `transport::PnetTransport`, which captures through Npcap on Windows.
+/// Like `NetlinkExecutor`, each virtual router needs its own executor, and only actions for
+/// the `InterfaceId` of the adapter's interface index are carried out.
+///
+/// Experimental: it has not been tested on Windows yet.
+pub struct IpHelperExecutor {
+    interface: String,
+    luid: NET_LUID_LH,
+    interface_index: u32,
+    virtual_addresses: VirtualAddresses,
+}
+
+impl IpHelperExecutor {
+    /// `interface` is the alias of the adapter, as listed by `Get-NetAdapter`.
+    pub fn new(interface: &str, virtual_addresses: VirtualAddresses) -> io::Result<Self> {
+        let alias = interface.encode_utf16().chain([0]).collect::<Vec<_>>();
+        let mut luid: NET_LUID_LH = unsafe { mem::zeroed() };
+        check(unsafe { ConvertInterfaceAliasToLuid(alias.as_ptr(), &mut luid) })?;
+        let mut interface_index = 0;
+        check(unsafe { ConvertInterfaceLuidToIndex(&luid, &mut interface_index) })?;
+
+        Ok(Self {
+            interface: interface.to_owned(),
+            luid,
+            interface_index,
+            virtual_addresses,
+        })
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    pub fn interface_id(&self) -> InterfaceId {
+        InterfaceId(self.interface_index)
+    }
+
+    /// Executes the address related actions for this interface; every other action is
+    /// ignored.
+    pub fn execute(&mut self, action: &Action) -> io::Result<()> {
+        match action {
+            Action::Activate {
+                interface,
+                addresses,
+            } if *interface == self.interface_id() => {
+                self.virtual_addresses = addresses.clone();
+                self.activate()
+            }
+            Action::Deactivate { interface } if *interface == self.interface_id() => {
+                self.deactivate()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn activate(&mut self) -> io::Result<()> {
+        for virtual_address in &self.virtual_addresses {
+            let row = self.row(virtual_address);
+            match check(unsafe { CreateUnicastIpAddressEntry(&row) }) {
+                // Still assigned, e.g. after a crash while Active.
+                Err(error) if error.raw_os_error() == Some(ERROR_OBJECT_ALREADY_EXISTS as i32) => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn deactivate(&mut self) -> io::Result<()> {
+        for virtual_address in &self.virtual_addresses {
+            let row = self.row(virtual_address);
+            match check(unsafe { DeleteUnicastIpAddressEntry(&row) }) {
+                // The address was already removed, e.g. by an operator.
+                Err(error) if error.raw_os_error() == Some(ERROR_NOT_FOUND as i32) => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn row(&self, virtual_address: &VirtualAddress) -> MIB_UNICASTIPADDRESS_ROW {
+        let mut row: MIB_UNICASTIPADDRESS_ROW = unsafe { mem::zeroed() };
+        unsafe { InitializeUnicastIpAddressEntry(&mut row) };
+        row.InterfaceLuid = self.luid;
+        row.Address.Ipv4 = SOCKADDR_IN {
+            sin_family: AF_INET,
+            sin_port: 0,
+            sin_addr: IN_ADDR {
+                // In network byte order.
+                S_un: IN_ADDR_0 {
+                    S_addr: u32::from_ne_bytes(virtual_address.address.octets()),
+                },
+            },
+            sin_zero: [0; 8],
+        };
+        row.OnLinkPrefixLength = virtual_address.prefix_len;
+        row.PrefixOrigin = IpPrefixOriginManual;
+        row.SuffixOrigin = IpSuffixOriginManual;
+        row.DadState = IpDadStatePreferred;
+        row
+    }
+}
+
+fn check(error: WIN32_ERROR) -> io::Result<()> {
+    match error {
+        NO_ERROR => Ok(()),
+        error => Err(io::Error::from_raw_os_error(error as i32)),
+    }
+}
@@ -0,0 +1,463 @@
+//! The IPv4 advertisement format of https://datatracker.ietf.org/doc/html/rfc9568#section-5.1
+//!
+//! `encode` and `decode` only handle the VRRP message itself, the checksum is left zero on
+//! encoding and not verified on decoding since it covers the IP pseudo header. Callers that
+//! build the IP packet fill it in with `checksum` and verify it with
+//! `ParseContext::check_checksum`. `encode_frame` and `decode_frame` do so for drivers that
+//! send and receive whole Ethernet frames, including the ARP packets.
+
+use crate::compat::Compat;
+use crate::{
+    Destination, Interval, Parameters, ReceivedPacket, SendPacket, WirePriority, VRID,
+    VRRP_MULTICAST_GROUP,
+};
+use pnet_base::MacAddr;
+use std::net::Ipv4Addr;
+
+pub const VERSION: u8 = 3;
+pub const ADVERTISEMENT: u8 = 1;
+pub const HEADER_LEN: usize = 8;
+/// Advertisements are sent without IP options.
+pub const IPV4_HEADER_LEN: usize = 20;
+/// The Count IPvX Addr field is a single octet.
+pub const MAX_ADDRESSES: usize = u8::MAX as usize;
+/// Advertisements must not have been forwarded, https://datatracker.ietf.org/doc/html/rfc9568#section-7.1
+pub const TTL: u8 = 255;
+/// The IP protocol number of VRRP.
+pub const PROTOCOL: u8 = 112;
+/// Non-standard, a reserved bit of the Max Advertise Interval field set on the advertisement
+/// a Backup sends before preempting, see `Parameters::graceful_takeover`.
+pub const TAKEOVER_INTENT: u16 = 0x8000;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ARP_LEN: usize = 28;
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+// The Internetwork Control precedence, like other routing protocols.
+const TOS: u8 = 0xc0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Advertisement {
+    pub vrid: VRID,
+    pub priority: WirePriority,
+    pub max_advertise_interval: Interval,
+    pub addresses: Vec<Ipv4Addr>,
+    /// See `TAKEOVER_INTENT`.
+    pub takeover_intent: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WireError {
+    /// Shorter than the fixed header.
+    Truncated {
+        length: usize,
+    },
+    Version(u8),
+    Type(u8),
+    /// VRID 0 is reserved.
+    Vrid,
+    /// The message length does not match the Count IPvX Addr field.
+    Length {
+        count: u8,
+        length: usize,
+    },
+    /// More virtual addresses than the Count IPvX Addr field can express.
+    TooManyAddresses(usize),
+    /// The IP packet would be longer than `Parameters::mtu`.
+    ExceedsMtu {
+        length: usize,
+        mtu: u16,
+    },
+}
+
+/// The length of the IP packet of an advertisement with `count` addresses.
+pub fn packet_len(count: usize) -> usize {
+    IPV4_HEADER_LEN + HEADER_LEN + 4 * count
+}
+
+/// Encodes an advertisement of the parameters, see `SendPacket::priority`.
+pub fn encode(parameters: &Parameters, priority: WirePriority) -> Result<Vec<u8>, WireError> {
+    let count = parameters.virtual_addresses.len();
+    if count > MAX_ADDRESSES {
+        return Err(WireError::TooManyAddresses(count));
+    }
+    let length = packet_len(count);
+    if let Some(mtu) = parameters.mtu.filter(|&mtu| length > mtu as usize) {
+        return Err(WireError::ExceedsMtu { length, mtu });
+    }
+
+    let mut message = Vec::with_capacity(HEADER_LEN + 4 * count);
+    message.push(VERSION << 4 | ADVERTISEMENT);
+    message.push(parameters.vrid.into());
+    message.push(priority.into());
+    message.push(count as u8);
+    // The upper 4 bits are reserved.
+    let max_advertise_interval = parameters.advertised_interval().as_wire_centis();
+    message.extend_from_slice(&max_advertise_interval.to_be_bytes());
+    message.extend_from_slice(&[0, 0]);
+    for virtual_address in &parameters.virtual_addresses {
+        message.extend_from_slice(&virtual_address.address.octets());
+    }
+    Ok(message)
+}
+
+/// Encodes an advertisement to send, `None` for ARP packets.
+pub fn encode_packet(packet: &SendPacket) -> Option<Result<Vec<u8>, WireError>> {
+    let (
+        SendPacket::Advertisement { parameters, .. }
+        | SendPacket::ShutdownAdvertisement { parameters, .. }
+        | SendPacket::TakeoverIntent { parameters, .. },
+        Some(priority),
+    ) = (packet, packet.priority())
+    else {
+        return None;
+    };
+    let message = encode(parameters, priority).map(|mut message| {
+        if let SendPacket::TakeoverIntent { .. } = packet {
+            message[4] |= (TAKEOVER_INTENT >> 8) as u8;
+        }
+        message
+    });
+    Some(message)
+}
+
+/// Encodes the Ethernet frame of a packet to send: advertisements in an IPv4 packet with
+/// their checksum filled in, and ARP packets. `None` for unicast advertisements, whose
+/// destination MAC address the caller has to resolve.
+pub fn encode_frame(packet: &SendPacket) -> Option<Result<Vec<u8>, WireError>> {
+    let destination_mac = packet.destination_mac()?;
+    let frame = match packet {
+        SendPacket::Advertisement {
+            parameters,
+            source_ip,
+            destination: Destination::Multicast,
+        }
+        | SendPacket::ShutdownAdvertisement {
+            parameters,
+            source_ip,
+            destination: Destination::Multicast,
+        }
+        | SendPacket::TakeoverIntent {
+            parameters,
+            source_ip,
+            destination: Destination::Multicast,
+        } => {
+            let message = match encode_packet(packet)? {
+                Ok(message) => message,
+                Err(error) => return Some(Err(error)),
+            };
+            let ip = ipv4_packet(*source_ip, VRRP_MULTICAST_GROUP, message);
+            ethernet_frame(
+                destination_mac,
+                parameters.mac_address(),
+                ETHERTYPE_IPV4,
+                &ip,
+            )
+        }
+        SendPacket::Advertisement { .. }
+        | SendPacket::ShutdownAdvertisement { .. }
+        | SendPacket::TakeoverIntent { .. } => return None,
+        SendPacket::GratuitousARP {
+            sender_mac,
+            sender_ip,
+        } => {
+            // Gratuitous ARP requests leave the target hardware address zero.
+            let arp = arp_packet(
+                ARP_REQUEST,
+                *sender_mac,
+                *sender_ip,
+                MacAddr::zero(),
+                *sender_ip,
+            );
+            ethernet_frame(destination_mac, *sender_mac, ETHERTYPE_ARP, &arp)
+        }
+        SendPacket::ReplyARP {
+            sender_mac,
+            sender_ip,
+            target_mac,
+            target_ip,
+            ..
+        } => {
+            let arp = arp_packet(ARP_REPLY, *sender_mac, *sender_ip, *target_mac, *target_ip);
+            ethernet_frame(destination_mac, *sender_mac, ETHERTYPE_ARP, &arp)
+        }
+        SendPacket::NeighborAdvertisement { sender_ip, .. }
+        | SendPacket::SolicitedNeighborAdvertisement { sender_ip, .. } => match *sender_ip {},
+    };
+    Some(Ok(frame))
+}
+
+/// Decodes a received Ethernet frame carrying an intact advertisement, after the checks of
+/// `context` on its IP header, or an ARP packet. Advertisements come with their VRID, ARP
+/// packets concern every virtual router on the interface.
+pub fn decode_frame(
+    frame: &[u8],
+    context: &mut ParseContext,
+) -> Option<(Option<VRID>, ReceivedPacket)> {
+    let source_mac = mac_address(frame.get(6..12)?);
+    let payload = frame.get(ETHERNET_HEADER_LEN..)?;
+    match u16::from_be_bytes([frame[12], frame[13]]) {
+        ETHERTYPE_IPV4 => {
+            let header_len = 4 * (*payload.first()? & 0x0f) as usize;
+            if payload.len() < header_len.max(IPV4_HEADER_LEN) || payload[9] != PROTOCOL {
+                return None;
+            }
+            let source_ip = ipv4_address(&payload[12..16]);
+            let destination_ip = ipv4_address(&payload[16..20]);
+            if !context.check_ttl(source_ip, destination_ip, payload[8]) {
+                return None;
+            }
+            let total_len = u16::from_be_bytes([payload[2], payload[3]]) as usize;
+            let message = payload.get(header_len..total_len)?;
+            if !context.check_checksum(source_ip, destination_ip, message) {
+                return None;
+            }
+            let advertisement = decode(message).ok()?;
+            let packet = ReceivedPacket::advertisement(
+                source_mac,
+                source_ip,
+                advertisement.priority,
+                advertisement.max_advertise_interval,
+            )
+            .with_takeover_intent(advertisement.takeover_intent);
+            Some((Some(advertisement.vrid), packet))
+        }
+        ETHERTYPE_ARP if payload.len() >= ARP_LEN => {
+            let sender_mac = mac_address(&payload[8..14]);
+            let sender_ip = ipv4_address(&payload[14..18]);
+            let target_mac = mac_address(&payload[18..24]);
+            let target_ip = ipv4_address(&payload[24..28]);
+            let packet = match u16::from_be_bytes([payload[6], payload[7]]) {
+                _ if sender_ip == target_ip => ReceivedPacket::GratuitousARP {
+                    sender_mac,
+                    sender_ip,
+                },
+                ARP_REQUEST => ReceivedPacket::RequestARP {
+                    sender_mac,
+                    sender_ip,
+                    target_ip,
+                },
+                ARP_REPLY => ReceivedPacket::ReplyARP {
+                    sender_mac,
+                    sender_ip,
+                    target_mac,
+                    target_ip,
+                },
+                _ => return None,
+            };
+            Some((None, packet))
+        }
+        _ => None,
+    }
+}
+
+fn ipv4_packet(source: Ipv4Addr, destination: Ipv4Addr, mut message: Vec<u8>) -> Vec<u8> {
+    let message_checksum = checksum(source, destination, &message);
+    message[6..8].copy_from_slice(&message_checksum.to_be_bytes());
+
+    let mut ip = Vec::with_capacity(IPV4_HEADER_LEN + message.len());
+    ip.extend_from_slice(&[0x45, TOS]);
+    ip.extend_from_slice(&((IPV4_HEADER_LEN + message.len()) as u16).to_be_bytes());
+    ip.extend_from_slice(&[0, 0, 0, 0, TTL, PROTOCOL, 0, 0]);
+    ip.extend_from_slice(&source.octets());
+    ip.extend_from_slice(&destination.octets());
+    let header_checksum = internet_checksum(ip.iter().copied());
+    ip[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+    ip.extend_from_slice(&message);
+    ip
+}
+
+fn arp_packet(
+    operation: u16,
+    sender_mac: MacAddr,
+    sender_ip: Ipv4Addr,
+    target_mac: MacAddr,
+    target_ip: Ipv4Addr,
+) -> Vec<u8> {
+    let mut arp = Vec::with_capacity(ARP_LEN);
+    // Ethernet and IPv4 addresses.
+    arp.extend_from_slice(&[0, 1, 0x08, 0x00, 6, 4]);
+    arp.extend_from_slice(&operation.to_be_bytes());
+    arp.extend_from_slice(&mac_octets(sender_mac));
+    arp.extend_from_slice(&sender_ip.octets());
+    arp.extend_from_slice(&mac_octets(target_mac));
+    arp.extend_from_slice(&target_ip.octets());
+    arp
+}
+
+fn ethernet_frame(
+    destination: MacAddr,
+    source: MacAddr,
+    ethertype: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&mac_octets(destination));
+    frame.extend_from_slice(&mac_octets(source));
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn mac_octets(mac_address: MacAddr) -> [u8; 6] {
+    let MacAddr(a, b, c, d, e, f) = mac_address;
+    [a, b, c, d, e, f]
+}
+
+fn mac_address(octets: &[u8]) -> MacAddr {
+    MacAddr(
+        octets[0], octets[1], octets[2], octets[3], octets[4], octets[5],
+    )
+}
+
+fn ipv4_address(octets: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])
+}
+
+/// The checks on the IP header of a received advertisement, which the caller parses.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParseContext {
+    /// The peers advertisements may be unicast from.
+    pub unicast_peers: Vec<Ipv4Addr>,
+    /// Accept unicast advertisements from `unicast_peers` whatever their TTL, for peers behind
+    /// routed hops. This gives up the protection against advertisements from off-link hosts.
+    pub insecure_accept_any_ttl_from_unicast_peers: bool,
+    /// Advertisements only accepted because of the relaxed TTL check.
+    pub relaxed_ttl_acceptances: u64,
+    /// The quirks of peers to tolerate in the IP header and checksum.
+    pub compat: Compat,
+    /// Advertisements only accepted because of `Compat::accept_any_ttl_from`.
+    pub compat_ttl_acceptances: u64,
+    /// Advertisements only accepted because of `Compat::accept_checksum_without_pseudo_header`.
+    pub compat_checksum_acceptances: u64,
+}
+
+impl ParseContext {
+    pub fn with_unicast_peers(self, unicast_peers: Vec<Ipv4Addr>) -> Self {
+        Self {
+            unicast_peers,
+            ..self
+        }
+    }
+
+    pub fn with_insecure_accept_any_ttl_from_unicast_peers(self, accept: bool) -> Self {
+        Self {
+            insecure_accept_any_ttl_from_unicast_peers: accept,
+            ..self
+        }
+    }
+
+    pub fn with_compat(self, compat: Compat) -> Self {
+        Self { compat, ..self }
+    }
+
+    /// Whether an advertisement from `source` to `destination` with `ttl` may be accepted.
+    pub fn check_ttl(&mut self, source: Ipv4Addr, destination: Ipv4Addr, ttl: u8) -> bool {
+        if ttl == TTL {
+            return true;
+        }
+        let relaxed = self.insecure_accept_any_ttl_from_unicast_peers
+            && !destination.is_multicast()
+            && self.unicast_peers.contains(&source);
+        if relaxed {
+            self.relaxed_ttl_acceptances += 1;
+            return true;
+        }
+        let compat = self.compat.accept_any_ttl_from.contains(&source);
+        if compat {
+            self.compat_ttl_acceptances += 1;
+        }
+        compat
+    }
+
+    /// Whether the checksum of an advertisement `message` from `source` to `destination` is
+    /// valid.
+    pub fn check_checksum(
+        &mut self,
+        source: Ipv4Addr,
+        destination: Ipv4Addr,
+        message: &[u8],
+    ) -> bool {
+        if checksum(source, destination, message) == 0 {
+            return true;
+        }
+        let compat = self.compat.accept_checksum_without_pseudo_header
+            && internet_checksum(message.iter().copied()) == 0;
+        if compat {
+            self.compat_checksum_acceptances += 1;
+        }
+        compat
+    }
+}
+
+/// The checksum of an advertisement `message` from `source` to `destination`, over the IP
+/// pseudo header of https://datatracker.ietf.org/doc/html/rfc9568#section-5.2.8. Computed
+/// with the checksum field zero it is the value to send; over a received message it is zero
+/// when the message is intact.
+pub fn checksum(source: Ipv4Addr, destination: Ipv4Addr, message: &[u8]) -> u16 {
+    let length = (message.len() as u16).to_be_bytes();
+    let pseudo_header = source
+        .octets()
+        .into_iter()
+        .chain(destination.octets())
+        .chain([0, PROTOCOL])
+        .chain(length);
+    internet_checksum(pseudo_header.chain(message.iter().copied()))
+}
+
+/// The Internet checksum of https://datatracker.ietf.org/doc/html/rfc1071, as used by the
+/// IPv4 header. Like `checksum`, it is zero over intact data that includes the checksum.
+pub fn internet_checksum(bytes: impl Iterator<Item = u8>) -> u16 {
+    let mut sum = 0u32;
+    let mut high = None;
+    for byte in bytes {
+        match high.take() {
+            None => high = Some(byte),
+            Some(high) => sum += u16::from_be_bytes([high, byte]) as u32,
+        }
+    }
+    if let Some(high) = high {
+        sum += u16::from_be_bytes([high, 0]) as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+pub fn decode(message: &[u8]) -> Result<Advertisement, WireError> {
+    if message.len() < HEADER_LEN {
+        return Err(WireError::Truncated {
+            length: message.len(),
+        });
+    }
+    match (message[0] >> 4, message[0] & 0x0f) {
+        (VERSION, ADVERTISEMENT) => {}
+        (VERSION, message_type) => return Err(WireError::Type(message_type)),
+        (version, _) => return Err(WireError::Version(version)),
+    }
+    let vrid = VRID::try_from(message[1]).map_err(|_| WireError::Vrid)?;
+    let count = message[3];
+    if message.len() != HEADER_LEN + 4 * count as usize {
+        return Err(WireError::Length {
+            count,
+            length: message.len(),
+        });
+    }
+
+    let reserved_and_centis = u16::from_be_bytes([message[4], message[5]]);
+    let centis = reserved_and_centis & Interval::MAX_WIRE_CENTIS;
+    let addresses = message[HEADER_LEN..]
+        .chunks_exact(4)
+        .map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+        .collect();
+    Ok(Advertisement {
+        vrid,
+        priority: message[2].into(),
+        max_advertise_interval: Interval::from_centis(centis),
+        addresses,
+        takeover_intent: reserved_and_centis & TAKEOVER_INTENT != 0,
+    })
+}
@@ -0,0 +1,93 @@
+#![cfg(unix)]
+
+use std::net::Ipv4Addr;
+use vrrp_rs::testio::LoopbackTransport;
+use vrrp_rs::{
+    ActionHandler, BackupMode, Command, Input, Interval, Mode, Parameters, Priority, Router, State,
+    Timestamp, VRID,
+};
+
+struct Node {
+    router: Router,
+    transport: LoopbackTransport,
+}
+
+impl Node {
+    fn new(primary_ip: Ipv4Addr, priority: u8, transport: LoopbackTransport) -> Self {
+        let mode = BackupMode::with_primary_ip(primary_ip)
+            .with_priority(Priority::try_from(priority).unwrap());
+        let parameters = Parameters::new(
+            VRID::try_from(7).unwrap(),
+            vec![Ipv4Addr::new(10, 0, 0, 1)].try_into().unwrap(),
+            Mode::from(mode),
+        );
+        Self {
+            router: Router::new(parameters),
+            transport,
+        }
+    }
+
+    fn handle(&mut self, now: Timestamp, input: Input) {
+        self.router.dispatch(now, input, &mut self.transport);
+    }
+
+    fn poll(&mut self, now: Timestamp) {
+        if self.router.state() != &State::Initialized && self.router.next_timer(now) <= now {
+            self.handle(now, Input::Timer);
+        }
+        while let Some(packet) = self.transport.receive().unwrap() {
+            self.handle(now, packet.into());
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        matches!(self.router.state(), State::Active { .. }) && self.transport.is_active()
+    }
+}
+
+fn run(nodes: &mut [&mut Node], now: &mut Timestamp, duration: Interval) {
+    let end = *now + duration;
+    while *now < end {
+        *now = *now + Interval::from_centis(1);
+        for node in nodes.iter_mut() {
+            node.poll(*now);
+        }
+    }
+}
+
+#[test]
+fn election_failover_and_preemption() {
+    let (left, right) = LoopbackTransport::pair().unwrap();
+    let mut high = Node::new(Ipv4Addr::new(192, 168, 0, 2), 200, left);
+    let mut low = Node::new(Ipv4Addr::new(192, 168, 0, 3), 100, right);
+    let mut now = Timestamp::now();
+
+    high.handle(now, Command::Startup.into());
+    low.handle(now, Command::Startup.into());
+    run(&mut [&mut high, &mut low], &mut now, Interval::from_secs(5));
+    assert!(
+        high.is_active(),
+        "the higher priority router wins the election"
+    );
+    assert!(!low.is_active());
+
+    high.handle(now, Command::Shutdown.into());
+    assert!(!high.transport.is_active());
+    run(&mut [&mut high, &mut low], &mut now, Interval::from_secs(1));
+    assert!(
+        low.is_active(),
+        "the backup takes over after the shutdown advertisement"
+    );
+
+    high.handle(now, Command::Startup.into());
+    run(&mut [&mut high, &mut low], &mut now, Interval::from_secs(5));
+    assert!(high.is_active(), "the higher priority router preempts");
+    assert!(!low.is_active());
+}
+
+#[test]
+fn transport_ignores_unrelated_actions() {
+    let (mut left, right) = LoopbackTransport::pair().unwrap();
+    left.route(vrrp_rs::RoutePacket::Accept);
+    assert_eq!(right.receive().unwrap(), None);
+}